@@ -11,3 +11,67 @@ define i32 @builtins.putchar.wrapper(i8* %env, i32 %c) {
     %1 = call i32 @putchar(i32 %c)
     ret i32 %1
 }
+
+; Writes `c` to stdout encoded as UTF-8, one byte at a time, so that unlike
+; `putchar` it can print non-ASCII chars
+define {} @builtins.print_char.wrapper(i8* %env, i32 %c) {
+entry:
+    %is_ascii = icmp ult i32 %c, 128
+    br i1 %is_ascii, label %one_byte, label %multi_byte
+
+one_byte:
+    call i32 @putchar(i32 %c)
+    ret {} zeroinitializer
+
+multi_byte:
+    %is_two_bytes = icmp ult i32 %c, 2048
+    br i1 %is_two_bytes, label %two_bytes, label %three_or_four_bytes
+
+two_bytes:
+    %lead2 = lshr i32 %c, 6
+    %lead2.tagged = or i32 %lead2, 192
+    call i32 @putchar(i32 %lead2.tagged)
+    br label %last_byte
+
+three_or_four_bytes:
+    %is_three_bytes = icmp ult i32 %c, 65536
+    br i1 %is_three_bytes, label %three_bytes, label %four_bytes
+
+three_bytes:
+    %lead3 = lshr i32 %c, 12
+    %lead3.tagged = or i32 %lead3, 224
+    call i32 @putchar(i32 %lead3.tagged)
+    br label %second_to_last_byte
+
+four_bytes:
+    %lead4 = lshr i32 %c, 18
+    %lead4.tagged = or i32 %lead4, 240
+    call i32 @putchar(i32 %lead4.tagged)
+    %cont3 = lshr i32 %c, 12
+    %cont3.bits = and i32 %cont3, 63
+    %cont3.tagged = or i32 %cont3.bits, 128
+    call i32 @putchar(i32 %cont3.tagged)
+    br label %second_to_last_byte
+
+second_to_last_byte:
+    %cont2 = lshr i32 %c, 6
+    %cont2.bits = and i32 %cont2, 63
+    %cont2.tagged = or i32 %cont2.bits, 128
+    call i32 @putchar(i32 %cont2.tagged)
+    br label %last_byte
+
+last_byte:
+    %cont1.bits = and i32 %c, 63
+    %cont1.tagged = or i32 %cont1.bits, 128
+    call i32 @putchar(i32 %cont1.tagged)
+    ret {} zeroinitializer
+}
+
+declare i64 @write(i32, i8*, i64)
+
+; Writes `msg` to stderr and exits with status 101
+define void @builtins.panic(i8* %msg, i64 %len) cold noreturn {
+    %1 = call i64 @write(i32 2, i8* %msg, i64 %len)
+    call void @exit(i32 101)
+    unreachable
+}
@@ -1,30 +1,51 @@
+//! Generating LLVM IR from the type checked HIR.
+//!
+//! # Memory
+//! Nothing that the generated code allocates on the heap is ever freed: there
+//! is no ownership tracking or garbage collection yet, so a program keeps all
+//! of its allocations until it exits.
+//! * The fields of recursive structs and enums (see [`ty::boxed_fields`]) are
+//!   boxed. Copying a value copies the pointer, so several values may share a
+//!   box. Writing to a boxed field first copies it to a new box, so that the
+//!   other values are unaffected, and the old box is left in place because they
+//!   may still point to it.
+//! * Every closure value has its own environment on the heap, holding the
+//!   variables it captures. Closures are copied freely and may be returned from
+//!   the fn that creates them, so no single owner could free it.
+//! * A local that a lambda mutates lives on the heap, so that the lambda and
+//!   the fn that declares it share it however long either lives.
+
 #![allow(clippy::cast_possible_truncation)]
 
 use arena::ArenaMap;
 use either::Either;
 use inkwell::{
+    attributes::{Attribute, AttributeLoc},
     basic_block::BasicBlock,
     builder::Builder,
     context::Context,
     memory_buffer::MemoryBuffer,
-    module::Module,
-    types::{BasicType, BasicTypeEnum, FunctionType, StructType},
-    values::{BasicValue, BasicValueEnum, FunctionValue, PointerValue},
+    module::{Linkage, Module},
+    types::{AnyType, BasicType, BasicTypeEnum, FunctionType, IntType, StructType},
+    values::{BasicValue, BasicValueEnum, FunctionValue, IntValue, PointerValue, StructValue},
     AddressSpace, FloatPredicate, IntPredicate,
 };
-use std::ops::Index;
+use std::{collections::HashSet, ops::Index};
+use text_size::{TextRange, TextSize};
 use walrus_semantics::{
     builtins::Builtin,
+    consteval::{self, ConstValue},
     hir::{
-        self, ArithmeticBinop, Binop, CmpBinop, Expr, ExprId, Field, FieldInit, FnDefId, LazyBinop,
-        Lit, Param, PatId, Unop, VarId,
+        self, ArithmeticBinop, Binop, BitBinop, CmpBinop, CompoundBinop, ConstDefId, EnumDefId,
+        Expr, ExprId, ExternFnDefId, Field, FieldInit, FieldPat, FnDefId, Label, LazyBinop, Lit,
+        MatchArm, Param, PatId, StructField, TypeId, Unop, VarId,
     },
-    scopes::{self, Denotation},
+    scopes::{self, Denotation, TraitId},
     ty,
-    ty::{Ctor, FnType, Type},
+    ty::{Ctor, FnType, MethodCall, Type},
 };
 
-use crate::free_vars::FreeVars;
+use crate::free_vars::{Capture, FreeVars};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HirModule {
@@ -33,6 +54,22 @@ pub struct HirModule {
     pub scopes: scopes::Scopes,
 }
 
+/// An error that stops code being generated for a module. Most errors are
+/// reported by the checks in `walrus_semantics` first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodegenError {
+    /// An `extern fn` declaring a C function that the builtins already declare
+    /// with a different type.
+    ExternFnTypeMismatch {
+        id: ExternFnDefId,
+        expected: String,
+        got: String,
+    },
+    /// A `const` whose value could not be evaluated, see
+    /// [`consteval::ConstValues::diagnostics`].
+    ConstNotEvaluated(ConstDefId),
+}
+
 pub struct Compiler<'ctx> {
     pub llvm: &'ctx Context,
     pub module: Module<'ctx>,
@@ -41,15 +78,46 @@ pub struct Compiler<'ctx> {
     pub hir: hir::ModuleData,
     pub scopes: scopes::Scopes,
     pub types: ty::InferenceResult,
+    /// The fields of recursive structs and enums that are stored behind a
+    /// pointer, see [`ty::boxed_fields`].
+    pub boxed_fields: HashSet<TypeId>,
+    /// The values of `const` items, see [`consteval::eval_consts`].
+    pub consts: consteval::ConstValues,
+
+    pub file: SourceFile,
+    /// Trap on integer overflow, division by zero and shifts by at least the
+    /// bit width instead of wrapping or invoking undefined behaviour.
+    pub checked_arithmetic: bool,
+}
+
+/// The file being compiled, used to report source locations at runtime.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    pub name: String,
+    pub text: String,
+}
+
+impl SourceFile {
+    /// The 1-based line and column of `offset`. Columns are counted in chars.
+    fn line_col(&self, offset: TextSize) -> (usize, usize) {
+        let before = &self.text[..usize::from(offset)];
+        let line = before.matches('\n').count() + 1;
+        let col = before
+            .rsplit('\n')
+            .next()
+            .map_or(0, |line| line.chars().count())
+            + 1;
+        (line, col)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Loop<'ctx> {
+    label: Option<Label>,
     body_bb: BasicBlock<'ctx>,
     exit_bb: BasicBlock<'ctx>,
     result_alloca: PointerValue<'ctx>,
 
-    does_continue: bool,
     does_break: bool,
 }
 
@@ -57,7 +125,20 @@ pub struct Loop<'ctx> {
 pub struct Vars<'a> {
     locals: ArenaMap<PatId, PointerValue<'a>>,
     fns: ArenaMap<FnDefId, FunctionValue<'a>>,
-    current_loop: Option<Loop<'a>>,
+    /// The LLVM name of each fn, see [`Compiler::fn_names`].
+    fn_names: ArenaMap<FnDefId, String>,
+    /// The copy of a generic fn for each list of type arguments it is used
+    /// with, in the order they were first used.
+    instances: Vec<(FnDefId, Vec<Type>, FunctionValue<'a>)>,
+    /// The external declaration of each `extern fn`.
+    extern_fns: ArenaMap<ExternFnDefId, FunctionValue<'a>>,
+    /// The global holding the value of each `const`.
+    consts: ArenaMap<ConstDefId, PointerValue<'a>>,
+    /// The loops enclosing the current expression, innermost last.
+    loops: Vec<Loop<'a>>,
+    /// The locals that a lambda mutates. These live on the heap, so that the
+    /// lambda can outlive the function that declares them.
+    heap_locals: HashSet<PatId>,
 }
 
 impl<'a> Index<PatId> for Vars<'a> {
@@ -68,6 +149,14 @@ impl<'a> Index<FnDefId> for Vars<'a> {
     type Output = FunctionValue<'a>;
     fn index(&self, id: FnDefId) -> &Self::Output { &self.fns[id] }
 }
+impl<'a> Index<ExternFnDefId> for Vars<'a> {
+    type Output = FunctionValue<'a>;
+    fn index(&self, id: ExternFnDefId) -> &Self::Output { &self.extern_fns[id] }
+}
+impl<'a> Index<ConstDefId> for Vars<'a> {
+    type Output = PointerValue<'a>;
+    fn index(&self, id: ConstDefId) -> &Self::Output { &self.consts[id] }
+}
 
 type Value<'ctx> = Option<BasicValueEnum<'ctx>>;
 
@@ -83,8 +172,12 @@ impl<'ctx> Compiler<'ctx> {
         };
         match ctor {
             ty::Ctor::Bool => self.llvm.bool_type().into(),
-            ty::Ctor::Int | ty::Ctor::Char => self.llvm.i32_type().into(),
+            ty::Ctor::I8 | ty::Ctor::U8 => self.llvm.i8_type().into(),
+            ty::Ctor::I16 | ty::Ctor::U16 => self.llvm.i16_type().into(),
+            ty::Ctor::Int | ty::Ctor::U32 | ty::Ctor::Char => self.llvm.i32_type().into(),
+            ty::Ctor::I64 | ty::Ctor::U64 => self.llvm.i64_type().into(),
             ty::Ctor::Float => self.llvm.f32_type().into(),
+            ty::Ctor::F64 => self.llvm.f64_type().into(),
             ty::Ctor::Tuple => {
                 let field_types = params
                     .iter()
@@ -93,23 +186,40 @@ impl<'ctx> Compiler<'ctx> {
                 self.llvm.struct_type(&field_types, false).into()
             }
             ty::Ctor::Fn => self.closure_type(&ty.as_fn().unwrap()),
-            ty::Ctor::Struct(id) => {
-                let struct_def = &self.hir[*id];
-                let field_types = struct_def
-                    .fields
-                    .iter()
-                    .map(|field| {
-                        let field_type = &self.types[field.ty];
-                        self.value_type(field_type)
-                    })
+            ty::Ctor::Struct(id) => self.fields_type(&self.hir[*id].fields).into(),
+            // `{tag, variant_0, ..., variant_n}`: each variant gets its own slot, and only
+            // the one selected by `tag` is initialized
+            ty::Ctor::Enum(id) => {
+                let enum_def = &self.hir[*id];
+                let field_types = std::iter::once(self.enum_tag_type().into())
+                    .chain(
+                        (enum_def.variants.iter())
+                            .map(|variant| self.fields_type(&variant.fields).into()),
+                    )
                     .collect::<Vec<_>>();
                 self.llvm.struct_type(&field_types, false).into()
             }
-            ty::Ctor::Enum(_) => todo!(),
-            ty::Ctor::Never => unreachable!(),
+            // generic fns are only generated once their type parameters are known
+            ty::Ctor::Never | ty::Ctor::Param(..) => unreachable!(),
         }
     }
 
+    fn fields_type(&self, fields: &[StructField]) -> StructType<'ctx> {
+        let field_types = fields
+            .iter()
+            .map(|field| {
+                if self.boxed_fields.contains(&field.ty) {
+                    return self.void_ptr_type();
+                }
+                let field_type = &self.types[field.ty];
+                self.value_type(field_type)
+            })
+            .collect::<Vec<_>>();
+        self.llvm.struct_type(&field_types, false)
+    }
+
+    fn enum_tag_type(&self) -> IntType<'ctx> { self.llvm.i32_type() }
+
     fn fn_type(&self, ty: &FnType) -> FunctionType<'ctx> {
         let FnType { params, ret } = ty;
         if ret == &Type::NEVER {
@@ -129,6 +239,47 @@ impl<'ctx> Compiler<'ctx> {
         }
     }
 
+    /// The signature of an `extern fn`, which inference only records as the
+    /// types of its parameters and return type.
+    fn extern_fn_sig(&self, id: ExternFnDefId) -> FnType {
+        let extern_fn_def = &self.hir[id];
+        FnType {
+            params: (extern_fn_def.params.iter())
+                .map(|param| self.types[param.pat].clone())
+                .collect(),
+            ret: extern_fn_def
+                .ret_type
+                .map_or(Type::UNIT, |ty| self.types[ty].clone()),
+        }
+    }
+
+    /// The type of an `extern fn`, which takes no environment. `()` and
+    /// `Never` are returned as `void`.
+    fn extern_fn_type(&self, ty: &FnType) -> FunctionType<'ctx> {
+        let FnType { params, ret } = ty;
+        let params = params
+            .iter()
+            .map(|ty| self.value_type(ty))
+            .collect::<Vec<_>>();
+        if ret == &Type::UNIT || ret == &Type::NEVER {
+            self.llvm.void_type().fn_type(&params, false)
+        } else {
+            self.value_type(ret).fn_type(&params, false)
+        }
+    }
+
+    /// C expects integers narrower than `int` to be sign or zero extended by
+    /// whichever side of the call produces them.
+    fn add_int_ext_attribute(&self, llvm_fn: FunctionValue<'ctx>, loc: AttributeLoc, ty: &Type) {
+        let kind = match ty.ctor() {
+            Some(Ctor::I8 | Ctor::I16) => "signext",
+            Some(Ctor::U8 | Ctor::U16 | Ctor::Bool) => "zeroext",
+            _ => return,
+        };
+        let kind_id = Attribute::get_named_enum_kind_id(kind);
+        llvm_fn.add_attribute(loc, self.llvm.create_enum_attribute(kind_id, 0));
+    }
+
     fn closure_type(&self, ty: &FnType) -> BasicTypeEnum<'ctx> {
         let struct_type = self.llvm.struct_type(
             &[
@@ -149,7 +300,87 @@ impl<'ctx> Compiler<'ctx> {
 
     fn unit_type(&self) -> StructType<'ctx> { self.tuple_type(&[]) }
 
-    fn codegen_module(self) -> Module<'ctx> {
+    /// The environment of a closure holds a copy of each variable it only
+    /// reads, and a pointer to each variable it assigns to.
+    fn env_type(&self, free_vars: &FreeVars) -> StructType<'ctx> {
+        let field_types = free_vars
+            .iter()
+            .map(|(pat, capture)| {
+                let ty = self.value_type(&self.types[pat]);
+                match capture {
+                    Capture::Read => ty,
+                    Capture::Mutate => ty.ptr_type(AddressSpace::Generic).into(),
+                }
+            })
+            .collect::<Vec<_>>();
+        self.llvm.struct_type(&field_types, false)
+    }
+
+    /// The name of `ty` as it is written in the source, which names the
+    /// copies of generic fns.
+    fn type_name(&self, ty: &Type) -> String {
+        let names = |tys: &[Type]| {
+            (tys.iter())
+                .map(|ty| self.type_name(ty))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match ty.ctor() {
+            Some(ty::Ctor::Struct(id)) => self.hir[self.hir[*id].name].to_string(),
+            Some(ty::Ctor::Enum(id)) => self.hir[self.hir[*id].name].to_string(),
+            Some(ty::Ctor::Tuple) => format!("({})", names(ty.as_tuple().unwrap())),
+            Some(ty::Ctor::Fn) => {
+                let FnType { params, ret } = ty.as_fn().unwrap();
+                format!("({}) -> {}", names(&params), self.type_name(&ret))
+            }
+            _ => Builtin::of_type(ty).unwrap().name().to_string(),
+        }
+    }
+
+    /// The types of the copy of the generic fn `id` for the type arguments
+    /// `args`. The signatures in `type_of_fn` stay generic, since other
+    /// generic fns are instantiated by matching against them.
+    fn instance_types(
+        types: &ty::InferenceResult,
+        id: FnDefId,
+        args: &[Type],
+    ) -> ty::InferenceResult {
+        let mut types = types.clone();
+        (types.type_of_expr.values_mut())
+            .chain(types.type_of_pat.values_mut())
+            .chain(types.type_of_type.values_mut())
+            .for_each(|ty| *ty = ty.subst(id, args));
+        types
+    }
+
+    /// The LLVM names of all fns in the module.
+    fn fn_names(&self) -> ArenaMap<FnDefId, String> {
+        let mut fn_names = ArenaMap::default();
+        for (id, func) in self.hir.fn_defs.iter() {
+            fn_names.insert(id, self.hir[func.name].to_string());
+        }
+        // fns in `impl` blocks are prefixed with the name of their type, so that
+        // they do not clash with each other or with toplevel fns
+        for (_, impl_def) in self.hir.impl_defs.iter() {
+            let ty = &self.types[impl_def.ty];
+            let ty_name = match ty.ctor() {
+                Some(ty::Ctor::Struct(id)) => self.hir[self.hir[*id].name].as_str(),
+                Some(ty::Ctor::Enum(id)) => self.hir[self.hir[*id].name].as_str(),
+                _ => match Builtin::of_type(ty) {
+                    Some(builtin) => builtin.name(),
+                    // impls for other types are rejected by inference
+                    None => continue,
+                },
+            };
+            for id in &impl_def.fns {
+                let name = &self.hir[self.hir[*id].name];
+                fn_names.insert(*id, format!("{ty_name}::{name}"));
+            }
+        }
+        fn_names
+    }
+
+    fn codegen_module(mut self) -> Result<Module<'ctx>, Vec<CodegenError>> {
         let builtins_source = include_str!("builtins.ll");
         let builtins =
             MemoryBuffer::create_from_memory_range_copy(builtins_source.as_bytes(), "builtins");
@@ -160,16 +391,93 @@ impl<'ctx> Compiler<'ctx> {
             .unwrap();
         self.module.link_in_module(builtins).unwrap();
         let mut vars = Vars::default();
+        for (id, expr) in self.hir.exprs.iter() {
+            if let Expr::Lambda { .. } = expr {
+                let free_vars = self.free_vars(id);
+                vars.heap_locals.extend(
+                    free_vars
+                        .iter()
+                        .filter(|(_, capture)| **capture == Capture::Mutate)
+                        .map(|(free_var, _)| free_var),
+                );
+            }
+        }
 
-        for (id, func) in self.hir.fn_defs.iter() {
+        vars.fn_names = self.fn_names();
+        for (id, fn_def) in self.hir.fn_defs.iter() {
+            if fn_def.is_generic() {
+                continue;
+            }
             let fn_type = self.fn_type(&self.types[id]);
-            let name = self.hir[func.name].as_str();
-            let llvm_fn = self.module.add_function(name, fn_type, None);
+            let llvm_fn = self.module.add_function(&vars.fn_names[id], fn_type, None);
             vars.fns.insert(id, llvm_fn);
         }
 
-        for (id, _) in self.hir.fn_defs.iter() {
-            self.codegen_fn(&mut vars, id)
+        let mut errors = Vec::new();
+        for (id, extern_fn_def) in self.hir.extern_fn_defs.iter() {
+            let name = self.hir[extern_fn_def.name].as_str();
+            let sig = self.extern_fn_sig(id);
+            let fn_type = self.extern_fn_type(&sig);
+            // the builtins may already declare the same C function
+            let llvm_fn = match self.module.get_function(name) {
+                Some(llvm_fn) => {
+                    if llvm_fn.get_type() != fn_type {
+                        errors.push(CodegenError::ExternFnTypeMismatch {
+                            id,
+                            expected: llvm_fn.get_type().print_to_string().to_string(),
+                            got: fn_type.print_to_string().to_string(),
+                        });
+                    }
+                    llvm_fn
+                }
+                None => {
+                    let llvm_fn = self
+                        .module
+                        .add_function(name, fn_type, Some(Linkage::External));
+                    for (idx, param) in sig.params.iter().enumerate() {
+                        self.add_int_ext_attribute(llvm_fn, AttributeLoc::Param(idx as u32), param);
+                    }
+                    self.add_int_ext_attribute(llvm_fn, AttributeLoc::Return, &sig.ret);
+                    llvm_fn
+                }
+            };
+            vars.extern_fns.insert(id, llvm_fn);
+        }
+        for (id, const_def) in self.hir.const_defs.iter() {
+            let value = match self.consts.values.get(id) {
+                Some(value) => value,
+                None => {
+                    errors.push(CodegenError::ConstNotEvaluated(id));
+                    continue;
+                }
+            };
+            let ty = &self.types[const_def.ty];
+            let value = self.codegen_const_value(ty, value);
+            let name = self.hir[const_def.name].as_str();
+            let global = self.module.add_global(value.get_type(), None, name);
+            global.set_initializer(&value);
+            global.set_constant(true);
+            vars.consts.insert(id, global.as_pointer_value());
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        for (id, fn_def) in self.hir.fn_defs.iter() {
+            if !fn_def.is_generic() {
+                let llvm_fn = vars[id];
+                self.codegen_fn(&mut vars, id, llvm_fn)
+            }
+        }
+
+        // generating a copy of a generic fn may use other generic fns with
+        // new type arguments, which adds more copies to generate
+        let types = self.types.clone();
+        let mut generated = 0;
+        while let Some((id, args, llvm_fn)) = vars.instances.get(generated).cloned() {
+            self.types = Self::instance_types(&types, id, &args);
+            self.codegen_fn(&mut vars, id, llvm_fn);
+            generated += 1;
         }
 
         if let Err(e) = self.module.verify() {
@@ -178,11 +486,45 @@ impl<'ctx> Compiler<'ctx> {
             panic!()
         }
 
-        self.module
+        Ok(self.module)
+    }
+
+    /// The LLVM constant for a value computed by [`consteval`].
+    #[allow(clippy::cast_sign_loss)]
+    fn codegen_const_value(&self, ty: &Type, value: &ConstValue) -> BasicValueEnum<'ctx> {
+        match value {
+            ConstValue::Bool(b) => self.llvm.bool_type().const_int((*b).into(), false).into(),
+            // `const_int` truncates to the width of the type, so the two's
+            // complement bits of negative values are kept
+            ConstValue::Int(i) => self
+                .value_type(ty)
+                .into_int_type()
+                .const_int(*i as u64, false)
+                .into(),
+            ConstValue::Float(f) => self
+                .value_type(ty)
+                .into_float_type()
+                .const_float(f.0)
+                .into(),
+            ConstValue::Char(c) => self.llvm.i32_type().const_int((*c).into(), false).into(),
+            ConstValue::Tuple(values) => {
+                let values = (ty.as_tuple().unwrap().iter())
+                    .zip(values)
+                    .map(|(ty, value)| self.codegen_const_value(ty, value))
+                    .collect::<Vec<_>>();
+                self.llvm.const_struct(&values, false).into()
+            }
+            ConstValue::Struct(id, values) => {
+                let values = (self.hir[*id].fields.iter())
+                    .zip(values)
+                    .map(|(field, value)| self.codegen_const_value(&self.types[field.ty], value))
+                    .collect::<Vec<_>>();
+                self.llvm.const_struct(&values, false).into()
+            }
+        }
     }
 
-    fn codegen_fn(&self, vars: &mut Vars<'ctx>, id: FnDefId) {
-        let llvm_fn = vars[id];
+    fn codegen_fn(&self, vars: &mut Vars<'ctx>, id: FnDefId, llvm_fn: FunctionValue<'ctx>) {
         let fn_def = &self.hir[id];
         let name = &self.hir[fn_def.name];
         let bb = self
@@ -210,13 +552,21 @@ impl<'ctx> Compiler<'ctx> {
         }
     }
 
-    fn codegen_local_var(&self, vars: &mut Vars<'ctx>, id: PatId, val: BasicValueEnum) {
+    fn codegen_local_var(&self, vars: &mut Vars<'ctx>, id: PatId, val: BasicValueEnum<'ctx>) {
         let pat = &self.hir[id];
         let pat_type = &self.types[id];
         match pat {
-            hir::Pat::Var(var) => {
-                let name = format!("{}.alloca", self.hir[*var]);
-                let alloca = self.builder.build_alloca(self.value_type(pat_type), &name);
+            hir::Pat::Var { var, .. } => {
+                let alloca = if vars.heap_locals.contains(&id) {
+                    // never freed, since a closure may still refer to it
+                    let name = format!("{}.malloc", self.hir[*var]);
+                    self.builder
+                        .build_malloc(self.value_type(pat_type), &name)
+                        .unwrap()
+                } else {
+                    let name = format!("{}.alloca", self.hir[*var]);
+                    self.builder.build_alloca(self.value_type(pat_type), &name)
+                };
                 vars.locals.insert(id, alloca);
                 self.builder.build_store(vars[id], val);
             }
@@ -240,31 +590,94 @@ impl<'ctx> Compiler<'ctx> {
                     .unwrap();
                 self.codegen_local_var(vars, *id, val)
             }),
+            hir::Pat::Enum {
+                variant, fields, ..
+            } => {
+                let enum_id = pat_type.as_enum().unwrap();
+                self.codegen_enum_pat_fields(enum_id, *variant, fields, val, |id, val| {
+                    self.codegen_local_var(vars, id, val)
+                })
+            }
+        }
+    }
+
+    /// Extracts the fields of the enum `value` named by `fields` and passes
+    /// them to `f`, along with the pattern they are matched against. `value`
+    /// must be built with `variant`.
+    fn codegen_enum_pat_fields(
+        &self,
+        enum_id: EnumDefId,
+        variant: VarId,
+        fields: &[FieldPat],
+        value: BasicValueEnum<'ctx>,
+        mut f: impl FnMut(PatId, BasicValueEnum<'ctx>),
+    ) {
+        let enum_def = &self.hir[enum_id];
+        let enum_name = &self.hir[enum_def.name];
+        let variant_name = &self.hir[variant];
+        let (tag, variant_def) = (enum_def.variants.iter().enumerate())
+            .find(|(_, variant)| &self.hir[variant.name] == variant_name)
+            .unwrap();
+        let variant_value = self
+            .builder
+            .build_extract_value(
+                value.into_struct_value(),
+                tag as u32 + 1,
+                &format!("{enum_name}::{variant_name}"),
+            )
+            .unwrap();
+        for field in fields {
+            let field_name = &self.hir[field.name];
+            let (idx, field_def) = (variant_def.fields.iter().enumerate())
+                .find(|(_, field)| &self.hir[field.name] == field_name)
+                .unwrap();
+            let value = self.build_extract_field(
+                variant_value.into_struct_value(),
+                idx as u32,
+                *field_def,
+                &format!("{enum_name}::{variant_name}.{field_name}"),
+            );
+            f(field.pat, value);
         }
     }
 
-    fn codegen_expr(&self, vars: &mut Vars<'ctx>, id: ExprId) -> Value {
+    fn codegen_expr(&self, vars: &mut Vars<'ctx>, id: ExprId) -> Value<'ctx> {
         let expr = &self.hir[id];
         match expr {
-            Expr::Lit(lit) => Some(self.codegen_lit(*lit)),
+            Expr::Lit(lit) => Some(self.codegen_lit(id, *lit)),
             Expr::Var(var) => Some(self.codegen_var(vars, id, *var)),
             Expr::Tuple(exprs) => self.codegen_tuple(vars, id, exprs),
             Expr::Struct { fields, .. } => self.codegen_struct(vars, id, fields),
-            Expr::Enum { .. } => todo!(),
+            Expr::Enum {
+                variant, fields, ..
+            } => self.codegen_enum(vars, id, *variant, fields),
             Expr::Field { expr, field } => self.codegen_field(vars, *expr, *field),
             Expr::If {
                 test,
                 then_branch,
                 else_branch,
             } => self.codegen_if(vars, *test, *then_branch, *else_branch),
-            Expr::Loop(body) => self.codegen_loop(vars, id, *body),
-            Expr::Break(expr) => self.codegen_break(vars, *expr),
-            Expr::Continue => self.codegen_continue(vars),
+            Expr::Match { test, arms } => self.codegen_match(vars, id, *test, arms),
+            Expr::Loop { label, body } => self.codegen_loop(vars, id, label, *body),
+            Expr::Break { label, expr } => self.codegen_break(vars, label, *expr),
+            Expr::Continue { label } => self.codegen_continue(vars, label),
             Expr::Return(expr) => self.codegen_return(vars, *expr),
             Expr::Call { func, args } => self.codegen_call(vars, *func, args),
+            Expr::MethodCall {
+                receiver,
+                method,
+                args,
+            } => self.codegen_method_call(vars, id, *receiver, *method, args),
+            Expr::Path { ty, name } => Some(self.codegen_path(vars, id, *ty, *name)),
             Expr::Lambda { params, expr } => Some(self.codegen_lambda(vars, id, params, *expr)),
-            Expr::Unop { op, expr } => self.codegen_unop(vars, *op, *expr),
-            Expr::Binop { lhs, op, rhs } => self.codegen_binop(vars, *lhs, *op, *rhs),
+            Expr::Unop { op, op_span, expr } => self.codegen_unop(vars, *op, *op_span, *expr),
+            Expr::Binop {
+                lhs,
+                op,
+                op_span,
+                rhs,
+            } => self.codegen_binop(vars, *lhs, *op, *op_span, *rhs),
+            Expr::Cast { expr, .. } => self.codegen_cast(vars, id, *expr),
             Expr::Block { stmts, expr } => {
                 for stmt in stmts {
                     match stmt {
@@ -285,7 +698,7 @@ impl<'ctx> Compiler<'ctx> {
         }
     }
 
-    fn codegen_lvalue(&self, vars: &mut Vars<'ctx>, id: ExprId) -> Option<PointerValue> {
+    fn codegen_lvalue(&self, vars: &mut Vars<'ctx>, id: ExprId) -> Option<PointerValue<'ctx>> {
         let expr = &self.hir[id];
         match expr {
             Expr::Var(var) => {
@@ -297,58 +710,56 @@ impl<'ctx> Compiler<'ctx> {
                 }
             }
             Expr::Field { expr, field } => {
-                let base_value = self.codegen_expr(vars, *expr)?;
-
-                let struct_id = self.types[*expr].as_struct().unwrap();
-                let struct_def = &self.hir[struct_id];
-                let struct_name = &self.hir[struct_def.name];
-
-                let value = match field {
-                    Field::Tuple(idx) => self
-                        .builder
-                        .build_struct_gep(
-                            base_value.into_pointer_value(),
-                            *idx as u32,
-                            &format!("{struct_name}.{idx}"),
-                        )
-                        .unwrap(),
-                    Field::Named(name) => {
-                        let name = &self.hir[*name];
-                        let idx = struct_def
-                            .fields
-                            .iter()
-                            .position(|field| &self.hir[field.name] == name)
-                            .unwrap();
-                        self.builder
-                            .build_struct_gep(
-                                base_value.into_pointer_value(),
-                                idx as u32,
-                                &format!("{struct_name}.{name}"),
-                            )
-                            .unwrap()
+                let base_ptr = self.codegen_lvalue(vars, *expr)?;
+                let (idx, name) = self.field_index(*expr, *field);
+                let gep = self.builder.build_struct_gep(base_ptr, idx, &name).unwrap();
+                match self.struct_field(*expr, *field) {
+                    Some(field) if self.boxed_fields.contains(&field.ty) => {
+                        // other values may point to the same box, so it is
+                        // copied before being written to, and never freed
+                        let old_value =
+                            self.build_unbox(self.builder.build_load(gep, &name), field.ty, &name);
+                        let new_box = self.build_box(old_value, &name);
+                        self.builder.build_store(gep, new_box);
+                        let ptr_type = self
+                            .value_type(&self.types[field.ty])
+                            .ptr_type(AddressSpace::Generic);
+                        Some(self.builder.build_pointer_cast(
+                            new_box.into_pointer_value(),
+                            ptr_type,
+                            &name,
+                        ))
                     }
-                };
-                Some(value)
+                    _ => Some(gep),
+                }
             }
             _ => unreachable!(),
         }
     }
 
-    fn codegen_unit(&self) -> BasicValueEnum { self.llvm.const_struct(&[], false).into() }
+    fn codegen_unit(&self) -> BasicValueEnum<'ctx> { self.llvm.const_struct(&[], false).into() }
 
-    fn codegen_undef(&self) -> BasicValueEnum { self.llvm.i8_type().get_undef().into() }
-
-    fn codegen_lit(&self, lit: Lit) -> BasicValueEnum {
+    fn codegen_lit(&self, expr: ExprId, lit: Lit) -> BasicValueEnum<'ctx> {
+        // numeric literals take the width of whatever type inference chose for them
+        let ty = &self.types[expr];
         match lit {
             Lit::Bool(false) => self.llvm.bool_type().const_int(0, false).into(),
             Lit::Bool(true) => self.llvm.bool_type().const_int(1, false).into(),
-            Lit::Int(val) => self.llvm.i32_type().const_int(val.into(), false).into(),
-            Lit::Float(val) => self.llvm.f32_type().const_float(val.0.into()).into(),
+            Lit::Int(val, _) => self
+                .value_type(ty)
+                .into_int_type()
+                .const_int(val, false)
+                .into(),
+            Lit::Float(val, _) => self
+                .value_type(ty)
+                .into_float_type()
+                .const_float(val.0)
+                .into(),
             Lit::Char(val) => self.llvm.i32_type().const_int(val.into(), false).into(),
         }
     }
 
-    fn codegen_var(&self, vars: &Vars<'ctx>, expr: ExprId, var: VarId) -> BasicValueEnum {
+    fn codegen_var(&self, vars: &mut Vars<'ctx>, expr: ExprId, var: VarId) -> BasicValueEnum<'ctx> {
         let var = &self.hir[var];
         let denotation = self.scopes.lookup_expr(expr, var);
         match denotation {
@@ -357,20 +768,185 @@ impl<'ctx> Compiler<'ctx> {
                 let fn_def = &self.hir[id];
                 let fn_name = &self.hir[fn_def.name].as_str();
                 let fn_type = self.types[expr].as_fn().unwrap();
-                let fn_value = vars[id];
+                let fn_value = self.fn_value(vars, id, &fn_type);
                 self.codegen_fn_value(fn_name, fn_value, &fn_type)
             }
+            Some(Denotation::ExternFn(id)) => {
+                let fn_type = self.types[expr].as_fn().unwrap();
+                let wrapper_fn = self.extern_fn_wrapper(vars, id);
+                self.codegen_fn_value(var.as_str(), wrapper_fn, &fn_type)
+            }
             Some(Denotation::Builtin(b)) => self.codegen_builtin(b),
+            Some(Denotation::Const(id)) => self.builder.build_load(vars[id], var.as_str()),
             _ => unreachable!(),
         }
     }
 
+    /// A fn taking an environment like any other closure, that forwards its
+    /// arguments to the `extern fn`. It is only built the first time the
+    /// `extern fn` is used as a value.
+    fn extern_fn_wrapper(&self, vars: &Vars<'ctx>, id: ExternFnDefId) -> FunctionValue<'ctx> {
+        let name = &self.hir[self.hir[id].name];
+        let wrapper_name = format!("{name}.wrapper");
+        if let Some(wrapper_fn) = self.module.get_function(&wrapper_name) {
+            return wrapper_fn;
+        }
+
+        let fn_type = self.fn_type(&self.extern_fn_sig(id));
+        let wrapper_fn = self.module.add_function(&wrapper_name, fn_type, None);
+        let old_bb = self.builder.get_insert_block();
+        let bb = self
+            .llvm
+            .append_basic_block(wrapper_fn, &format!("{wrapper_name}.entry"));
+        self.builder.position_at_end(bb);
+        let args = wrapper_fn.get_param_iter().skip(1).collect::<Vec<_>>();
+        if let Some(value) = self.build_extern_call(vars, id, &args) {
+            self.builder.build_return(Some(&value));
+        }
+        if let Some(old_bb) = old_bb {
+            self.builder.position_at_end(old_bb);
+        }
+        wrapper_fn
+    }
+
+    /// Calls an `extern fn` with the C calling convention. A `void` result
+    /// is turned back into `()`, or into `unreachable` if the return type is
+    /// `Never`.
+    fn build_extern_call(
+        &self,
+        vars: &Vars<'ctx>,
+        id: ExternFnDefId,
+        args: &[BasicValueEnum<'ctx>],
+    ) -> Value<'ctx> {
+        let call = self.builder.build_call(vars[id], args, "call");
+        match call.try_as_basic_value() {
+            Either::Left(value) => Some(value),
+            Either::Right(_) if self.extern_fn_sig(id).ret == Type::NEVER => {
+                self.builder.build_unreachable();
+                None
+            }
+            Either::Right(_) => Some(self.codegen_unit()),
+        }
+    }
+
+    /// The LLVM function of the fn `id`, used with the type `fn_type`. A
+    /// generic fn is copied for each list of type arguments it is used with,
+    /// and the copies are generated after every other fn.
+    fn fn_value(
+        &self,
+        vars: &mut Vars<'ctx>,
+        id: FnDefId,
+        fn_type: &FnType,
+    ) -> FunctionValue<'ctx> {
+        let fn_def = &self.hir[id];
+        if !fn_def.is_generic() {
+            return vars[id];
+        }
+        let mut args = vec![None; fn_def.generics.len()];
+        Type::from(self.types[id].clone()).match_params(id, &fn_type.clone().into(), &mut args);
+        let args = args.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+        let instance =
+            (vars.instances.iter()).find(|(fn_id, fn_args, _)| *fn_id == id && fn_args == &args);
+        if let Some((.., llvm_fn)) = instance {
+            return *llvm_fn;
+        }
+        let arg_names = args.iter().map(|ty| self.type_name(ty)).collect::<Vec<_>>();
+        let name = format!("{}<{}>", vars.fn_names[id], arg_names.join(", "));
+        let fn_type = self.fn_type(&self.types[id].subst(id, &args));
+        let llvm_fn = self.module.add_function(&name, fn_type, None);
+        vars.instances.push((id, args, llvm_fn));
+        llvm_fn
+    }
+
+    /// The fn named `method` in the `impl` block of the trait `trait_id` for
+    /// `ty`. Methods of the built-in impls of `Eq` and `Ord` have no fn.
+    fn lookup_trait_method(&self, ty: &Type, trait_id: TraitId, method: VarId) -> Option<FnDefId> {
+        let denotation = match ty.ctor()? {
+            ty::Ctor::Struct(id) => Denotation::Struct(*id),
+            ty::Ctor::Enum(id) => Denotation::Enum(*id),
+            _ => Denotation::Builtin(Builtin::of_type(ty)?),
+        };
+        (self.scopes).lookup_trait_method(denotation, trait_id, &self.hir[method])
+    }
+
+    /// What the method call `expr` calls in this copy of its fn. A fn of a
+    /// trait that the receiver's type parameter is bounded by is the fn of
+    /// the type argument's impl, which inference checked exists.
+    fn resolve_method_call(&self, expr: ExprId, receiver_type: &Type, method: VarId) -> MethodCall {
+        let trait_id = match self.types.method_calls[expr] {
+            MethodCall::Trait(trait_id) => trait_id,
+            call => return call,
+        };
+        let builtin_cmp = match trait_id {
+            TraitId::Builtin(builtin) => builtin.builtin_cmp(),
+            TraitId::Def(_) => None,
+        };
+        let impl_fn = self.lookup_trait_method(receiver_type, trait_id, method);
+        match (impl_fn, builtin_cmp) {
+            (Some(id), _) => MethodCall::Fn(id),
+            (None, Some(op)) => MethodCall::BuiltinCmp(op),
+            (None, None) => unreachable!("the type argument does not implement the trait"),
+        }
+    }
+
+    /// The fn overloading an operator through the builtin trait
+    /// `operator_trait` for operands of type `ty`.
+    fn lookup_operator(&self, ty: &Type, operator_trait: Option<Builtin>) -> Option<FnDefId> {
+        let denotation = match ty.ctor()? {
+            ty::Ctor::Struct(id) => Denotation::Struct(*id),
+            ty::Ctor::Enum(id) => Denotation::Enum(*id),
+            _ => return None,
+        };
+        self.scopes.lookup_operator(denotation, operator_trait?)
+    }
+
+    /// Calls a fn from an `impl` block, which has no captured variables.
+    fn build_direct_call(
+        &self,
+        llvm_fn: FunctionValue<'ctx>,
+        args: &[BasicValueEnum<'ctx>],
+    ) -> Value<'ctx> {
+        let env_ptr = self.void_ptr_type().const_zero();
+        let args = &std::iter::once(env_ptr)
+            .chain(args.iter().copied())
+            .collect::<Vec<_>>();
+        match self
+            .builder
+            .build_call(llvm_fn, args, "call")
+            .try_as_basic_value()
+        {
+            Either::Left(value) => Some(value),
+            Either::Right(_) => {
+                self.builder.build_unreachable();
+                None
+            }
+        }
+    }
+
+    fn codegen_path(
+        &self,
+        vars: &mut Vars<'ctx>,
+        expr: ExprId,
+        ty: VarId,
+        name: VarId,
+    ) -> BasicValueEnum<'ctx> {
+        let denotation = self.scopes.lookup_expr(expr, &self.hir[ty]).unwrap();
+        let id = self
+            .scopes
+            .lookup_method(denotation, &self.hir[name])
+            .unwrap();
+        let fn_name = self.hir[name].as_str();
+        let fn_type = self.types[expr].as_fn().unwrap();
+        let fn_value = self.fn_value(vars, id, &fn_type);
+        self.codegen_fn_value(fn_name, fn_value, &fn_type)
+    }
+
     fn codegen_fn_value(
         &self,
         fn_name: &str,
         fn_value: FunctionValue,
         fn_type: &FnType,
-    ) -> BasicValueEnum {
+    ) -> BasicValueEnum<'ctx> {
         let code_ptr = fn_value.as_global_value().as_pointer_value();
         let closure_alloca = self.builder.build_alloca(
             self.closure_type(fn_type),
@@ -392,11 +968,29 @@ impl<'ctx> Compiler<'ctx> {
         self.builder.build_load(closure_alloca, fn_name)
     }
 
-    fn codegen_builtin(&self, builtin: Builtin) -> BasicValueEnum {
+    fn codegen_builtin(&self, builtin: Builtin) -> BasicValueEnum<'ctx> {
         match builtin {
-            Builtin::Bool | Builtin::Int | Builtin::Float | Builtin::Char | Builtin::Never => {
-                unreachable!()
-            }
+            Builtin::Bool
+            | Builtin::Int
+            | Builtin::I8
+            | Builtin::I16
+            | Builtin::I64
+            | Builtin::U8
+            | Builtin::U16
+            | Builtin::U32
+            | Builtin::U64
+            | Builtin::Float
+            | Builtin::F64
+            | Builtin::Char
+            | Builtin::Never
+            | Builtin::Eq
+            | Builtin::Ord
+            | Builtin::Add
+            | Builtin::Sub
+            | Builtin::Mul
+            | Builtin::Div
+            | Builtin::Rem
+            | Builtin::Neg => unreachable!(),
             Builtin::Exit => {
                 let exit_wrapper_fn = self.module.get_function("builtins.exit.wrapper").unwrap();
                 self.codegen_fn_value("exit", exit_wrapper_fn, &builtin.ty().as_fn().unwrap())
@@ -408,10 +1002,17 @@ impl<'ctx> Compiler<'ctx> {
                     .unwrap();
                 self.codegen_fn_value("putchar", wrapper_fn, &builtin.ty().as_fn().unwrap())
             }
+            Builtin::PrintChar => {
+                let wrapper_fn = self
+                    .module
+                    .get_function("builtins.print_char.wrapper")
+                    .unwrap();
+                self.codegen_fn_value("print_char", wrapper_fn, &builtin.ty().as_fn().unwrap())
+            }
         }
     }
 
-    fn codegen_tuple(&self, vars: &mut Vars<'ctx>, expr: ExprId, exprs: &[ExprId]) -> Value {
+    fn codegen_tuple(&self, vars: &mut Vars<'ctx>, expr: ExprId, exprs: &[ExprId]) -> Value<'ctx> {
         let types = self.types[expr].as_tuple().unwrap();
         let tuple_type = self.tuple_type(types);
         let tuple_alloca = self.builder.build_alloca(tuple_type, "tuple.alloca");
@@ -426,7 +1027,12 @@ impl<'ctx> Compiler<'ctx> {
         Some(self.builder.build_load(tuple_alloca, "tuple"))
     }
 
-    fn codegen_struct(&self, vars: &mut Vars<'ctx>, expr: ExprId, fields: &[FieldInit]) -> Value {
+    fn codegen_struct(
+        &self,
+        vars: &mut Vars<'ctx>,
+        expr: ExprId,
+        fields: &[FieldInit],
+    ) -> Value<'ctx> {
         let struct_id = self.types[expr].as_struct().unwrap();
         let struct_def = &self.hir[struct_id];
         let struct_name = &self.hir[struct_def.name];
@@ -454,22 +1060,169 @@ impl<'ctx> Compiler<'ctx> {
                 .iter()
                 .find(|(name, _)| name == &field_name)
                 .unwrap();
-            let value = value;
-            self.builder.build_store(gep, (*value)?);
+            let value =
+                self.build_field_value(*field, (*value)?, &format!("{struct_name}.{field_name}"));
+            self.builder.build_store(gep, value);
         }
         Some(self.builder.build_load(struct_alloca, struct_name.as_str()))
     }
 
-    fn codegen_field(&self, vars: &mut Vars<'ctx>, expr: ExprId, field: Field) -> Value {
+    fn codegen_enum(
+        &self,
+        vars: &mut Vars<'ctx>,
+        expr: ExprId,
+        variant: VarId,
+        fields: &[FieldInit],
+    ) -> Value<'ctx> {
+        let enum_id = self.types[expr].as_enum().unwrap();
+        let enum_def = &self.hir[enum_id];
+        let enum_name = &self.hir[enum_def.name];
+        let variant_name = &self.hir[variant];
+        let (tag, variant_def) = (enum_def.variants.iter().enumerate())
+            .find(|(_, variant)| &self.hir[variant.name] == variant_name)
+            .unwrap();
+
+        let enum_type = self.value_type(&self.types[expr]);
+        let init_exprs = fields
+            .iter()
+            .map(|field| (&self.hir[field.name], self.codegen_expr(vars, field.val)))
+            .collect::<Vec<_>>();
+        let enum_alloca = self
+            .builder
+            .build_alloca(enum_type, &format!("{enum_name}.alloca"));
+
+        let tag_gep = self
+            .builder
+            .build_struct_gep(enum_alloca, 0, &format!("{enum_name}.tag"))
+            .unwrap();
+        let tag_value = self.enum_tag_type().const_int(tag as u64, false);
+        self.builder.build_store(tag_gep, tag_value);
+
+        let variant_gep = self
+            .builder
+            .build_struct_gep(
+                enum_alloca,
+                tag as u32 + 1,
+                &format!("{enum_name}::{variant_name}"),
+            )
+            .unwrap();
+        for (idx, field) in variant_def.fields.iter().enumerate() {
+            let field_name = &self.hir[field.name];
+            let gep = self
+                .builder
+                .build_struct_gep(
+                    variant_gep,
+                    idx as u32,
+                    &format!("{enum_name}::{variant_name}.{field_name}"),
+                )
+                .unwrap();
+            let (_, value) = init_exprs
+                .iter()
+                .find(|(name, _)| name == &field_name)
+                .unwrap();
+            let value = self.build_field_value(
+                *field,
+                (*value)?,
+                &format!("{enum_name}::{variant_name}.{field_name}"),
+            );
+            self.builder.build_store(gep, value);
+        }
+        Some(self.builder.build_load(enum_alloca, enum_name.as_str()))
+    }
+
+    fn codegen_field(&self, vars: &mut Vars<'ctx>, expr: ExprId, field: Field) -> Value<'ctx> {
         let base_value = self.codegen_expr(vars, expr)?;
-        let value = match field {
-            Field::Tuple(idx) => self
+        let (idx, name) = self.field_index(expr, field);
+        let value = match self.struct_field(expr, field) {
+            Some(field) => {
+                self.build_extract_field(base_value.into_struct_value(), idx, *field, &name)
+            }
+            None => self
                 .builder
-                .build_extract_value(base_value.into_struct_value(), idx, &format!("tuple.{idx}"))
+                .build_extract_value(base_value.into_struct_value(), idx, &name)
                 .unwrap(),
+        };
+        Some(value)
+    }
+
+    /// The definition of `field` if `base` is a struct.
+    fn struct_field(&self, base: ExprId, field: Field) -> Option<&StructField> {
+        let name = match field {
+            Field::Tuple(_) => return None,
+            Field::Named(name) => &self.hir[name],
+        };
+        let struct_id = self.types[base].as_struct()?;
+        (self.hir[struct_id].fields.iter()).find(|field| &self.hir[field.name] == name)
+    }
+
+    /// The value to store in `field`: a pointer to a copy of `value` on the
+    /// heap if the field is boxed, or else `value` itself.
+    fn build_field_value(
+        &self,
+        field: StructField,
+        value: BasicValueEnum<'ctx>,
+        name: &str,
+    ) -> BasicValueEnum<'ctx> {
+        if self.boxed_fields.contains(&field.ty) {
+            self.build_box(value, name)
+        } else {
+            value
+        }
+    }
+
+    /// Extracts `field`, which is at index `idx` in `value`, unboxing it if
+    /// necessary.
+    fn build_extract_field(
+        &self,
+        value: StructValue<'ctx>,
+        idx: u32,
+        field: StructField,
+        name: &str,
+    ) -> BasicValueEnum<'ctx> {
+        let value = self.builder.build_extract_value(value, idx, name).unwrap();
+        if self.boxed_fields.contains(&field.ty) {
+            self.build_unbox(value, field.ty, name)
+        } else {
+            value
+        }
+    }
+
+    /// Copies `value` to the heap, returning an `i8*` pointing to it.
+    fn build_box(&self, value: BasicValueEnum<'ctx>, name: &str) -> BasicValueEnum<'ctx> {
+        let ptr = self
+            .builder
+            .build_malloc(value.get_type(), &format!("{name}.box"))
+            .unwrap();
+        self.builder.build_store(ptr, value);
+        self.builder
+            .build_pointer_cast(ptr, self.void_ptr_type().into_pointer_type(), name)
+            .into()
+    }
+
+    /// Loads the value of type `ty` pointed to by the `i8*` `ptr`.
+    fn build_unbox(
+        &self,
+        ptr: BasicValueEnum<'ctx>,
+        ty: TypeId,
+        name: &str,
+    ) -> BasicValueEnum<'ctx> {
+        let ptr_type = self
+            .value_type(&self.types[ty])
+            .ptr_type(AddressSpace::Generic);
+        let ptr = self
+            .builder
+            .build_pointer_cast(ptr.into_pointer_value(), ptr_type, name);
+        self.builder.build_load(ptr, name)
+    }
+
+    /// The index of `field` within the value of `base`, along with a name for
+    /// the extracted value.
+    fn field_index(&self, base: ExprId, field: Field) -> (u32, String) {
+        match field {
+            Field::Tuple(idx) => (idx, format!("tuple.{idx}")),
             Field::Named(name) => {
                 let name = &self.hir[name];
-                let struct_id = self.types[expr].as_struct().unwrap();
+                let struct_id = self.types[base].as_struct().unwrap();
                 let struct_def = &self.hir[struct_id];
                 let struct_name = &self.hir[struct_def.name];
                 let idx = struct_def
@@ -477,16 +1230,9 @@ impl<'ctx> Compiler<'ctx> {
                     .iter()
                     .position(|field| &self.hir[field.name] == name)
                     .unwrap();
-                self.builder
-                    .build_extract_value(
-                        base_value.into_struct_value(),
-                        idx as u32,
-                        &format!("{struct_name}.{name}"),
-                    )
-                    .unwrap()
+                (idx as u32, format!("{struct_name}.{name}"))
             }
-        };
-        Some(value)
+        }
     }
 
     fn codegen_if(
@@ -495,7 +1241,7 @@ impl<'ctx> Compiler<'ctx> {
         test: ExprId,
         then_branch: ExprId,
         else_branch: Option<ExprId>,
-    ) -> Value {
+    ) -> Value<'ctx> {
         let bb = self.builder.get_insert_block().unwrap();
         let end_bb = self.llvm.insert_basic_block_after(bb, "if.end");
         let else_bb = self.llvm.insert_basic_block_after(bb, "if.else");
@@ -506,39 +1252,154 @@ impl<'ctx> Compiler<'ctx> {
 
         // then branch
         self.builder.position_at_end(then_bb);
-        let then_value = match else_branch {
-            Some(_) => self
-                .codegen_expr(vars, then_branch)
-                .unwrap_or_else(|| self.codegen_undef()),
-            None => {
-                self.codegen_expr(vars, then_branch);
-                self.codegen_unit()
-            }
-        };
-        self.builder.build_unconditional_branch(end_bb);
+        let then_value = self
+            .codegen_expr(vars, then_branch)
+            .map(|value| match else_branch {
+                Some(_) => value,
+                None => self.codegen_unit(),
+            });
+        // a branch that diverges (eg `break`) has already been terminated, and a
+        // branch containing control flow may end in a different block than it
+        // started in
+        let then_bb = self.builder.get_insert_block().unwrap();
+        if then_value.is_some() {
+            self.builder.build_unconditional_branch(end_bb);
+        }
 
         // else branch
         self.builder.position_at_end(else_bb);
         let else_value = match else_branch {
-            Some(else_branch) => self
-                .codegen_expr(vars, else_branch)
-                .unwrap_or_else(|| self.codegen_undef()),
-            None => self.codegen_unit(),
+            Some(else_branch) => self.codegen_expr(vars, else_branch),
+            None => Some(self.codegen_unit()),
         };
-        self.builder.build_unconditional_branch(end_bb);
+        let else_bb = self.builder.get_insert_block().unwrap();
+        if else_value.is_some() {
+            self.builder.build_unconditional_branch(end_bb);
+        }
 
         // merge the 2 branches
         self.builder.position_at_end(end_bb);
-        let ty = match else_branch {
-            Some(_) => self.value_type(&self.types[then_branch]),
-            None => self.tuple_type(&[]).into(),
-        };
-        let phi = self.builder.build_phi(ty, "if.merge");
-        phi.add_incoming(&[(&then_value, then_bb), (&else_value, else_bb)]);
-        Some(phi.as_basic_value())
-    }
-
-    fn codegen_loop(&self, vars: &mut Vars<'ctx>, expr: ExprId, body: ExprId) -> Value {
+        let incoming = [(then_value, then_bb), (else_value, else_bb)]
+            .iter()
+            .filter_map(|(value, bb)| Some(((*value)?, *bb)))
+            .collect::<Vec<_>>();
+        if incoming.is_empty() {
+            // both branches diverge
+            self.builder.build_unreachable();
+            return None;
+        }
+        let phi = self.builder.build_phi(incoming[0].0.get_type(), "if.merge");
+        for (value, bb) in &incoming {
+            phi.add_incoming(&[(value, *bb)]);
+        }
+        Some(phi.as_basic_value())
+    }
+
+    fn codegen_match(
+        &self,
+        vars: &mut Vars<'ctx>,
+        expr: ExprId,
+        test: ExprId,
+        arms: &[MatchArm],
+    ) -> Value<'ctx> {
+        let bb = self.builder.get_insert_block().unwrap();
+        let end_bb = self.llvm.insert_basic_block_after(bb, "match.end");
+        let test_value = self.codegen_expr(vars, test)?;
+
+        let mut incoming = Vec::new();
+        for arm in arms {
+            let next_bb = self.llvm.prepend_basic_block(end_bb, "match.next");
+            self.build_pat_test(arm.pat, test_value, next_bb);
+            self.codegen_local_var(vars, arm.pat, test_value);
+            // an arm containing control flow may end in a different block than
+            // it started in
+            if let Some(value) = self.codegen_expr(vars, arm.expr) {
+                incoming.push((value, self.builder.get_insert_block().unwrap()));
+                self.builder.build_unconditional_branch(end_bb);
+            }
+            self.builder.position_at_end(next_bb);
+        }
+        // the arms are exhaustive
+        self.builder.build_unreachable();
+
+        self.builder.position_at_end(end_bb);
+        if incoming.is_empty() {
+            // every arm diverges
+            self.builder.build_unreachable();
+            return None;
+        }
+        let phi = self
+            .builder
+            .build_phi(self.value_type(&self.types[expr]), "match.merge");
+        for (value, bb) in &incoming {
+            phi.add_incoming(&[(value, *bb)]);
+        }
+        Some(phi.as_basic_value())
+    }
+
+    /// Branches to `no_match_bb` if `value` does not match `pat`, or else
+    /// continues in a new block.
+    fn build_pat_test(
+        &self,
+        pat: PatId,
+        value: BasicValueEnum<'ctx>,
+        no_match_bb: BasicBlock<'ctx>,
+    ) {
+        match &self.hir[pat] {
+            hir::Pat::Var { .. } | hir::Pat::Ignore => {}
+            hir::Pat::Tuple(pats) => pats.iter().enumerate().for_each(|(idx, pat)| {
+                let value = self
+                    .builder
+                    .build_extract_value(
+                        value.into_struct_value(),
+                        idx as u32,
+                        &format!("tuple.{idx}"),
+                    )
+                    .unwrap();
+                self.build_pat_test(*pat, value, no_match_bb)
+            }),
+            hir::Pat::Enum {
+                variant, fields, ..
+            } => {
+                let enum_id = self.types[pat].as_enum().unwrap();
+                let enum_def = &self.hir[enum_id];
+                let enum_name = &self.hir[enum_def.name];
+                let variant_name = &self.hir[*variant];
+                let tag = (enum_def.variants.iter())
+                    .position(|variant| &self.hir[variant.name] == variant_name)
+                    .unwrap();
+                let tag_value = self
+                    .builder
+                    .build_extract_value(value.into_struct_value(), 0, &format!("{enum_name}.tag"))
+                    .unwrap();
+                let is_variant = self.builder.build_int_compare(
+                    IntPredicate::EQ,
+                    tag_value.into_int_value(),
+                    self.enum_tag_type().const_int(tag as u64, false),
+                    &format!("is.{enum_name}::{variant_name}"),
+                );
+                let match_bb = self
+                    .llvm
+                    .prepend_basic_block(no_match_bb, &format!("{enum_name}::{variant_name}"));
+                self.builder
+                    .build_conditional_branch(is_variant, match_bb, no_match_bb);
+                self.builder.position_at_end(match_bb);
+
+                // the fields are only initialized once the tag has matched
+                self.codegen_enum_pat_fields(enum_id, *variant, fields, value, |pat, value| {
+                    self.build_pat_test(pat, value, no_match_bb)
+                })
+            }
+        }
+    }
+
+    fn codegen_loop(
+        &self,
+        vars: &mut Vars<'ctx>,
+        expr: ExprId,
+        label: &Option<Label>,
+        body: ExprId,
+    ) -> Value<'ctx> {
         let old_bb = self.builder.get_insert_block().unwrap();
         let exit_bb = self.llvm.insert_basic_block_after(old_bb, "loop.exit");
         let body_bb = self.llvm.insert_basic_block_after(old_bb, "loop.body");
@@ -554,47 +1415,50 @@ impl<'ctx> Compiler<'ctx> {
         self.builder.build_unconditional_branch(body_bb);
 
         self.builder.position_at_end(body_bb);
-        let old_loop = vars.current_loop.clone();
         let new_loop = Loop {
+            label: label.clone(),
             result_alloca,
             body_bb,
             exit_bb,
-            does_continue: false,
             does_break: false,
         };
-        vars.current_loop = Some(new_loop);
-        let _body = self.codegen_expr(vars, body);
-        let Loop {
-            does_continue,
-            does_break,
-            ..
-        } = vars.current_loop.as_ref().unwrap();
+        vars.loops.push(new_loop);
+        if self.codegen_expr(vars, body).is_some() {
+            // the body ran to its end without diverging, so go round again
+            self.builder.build_unconditional_branch(body_bb);
+        }
+        let does_break = vars.loops.pop().unwrap().does_break;
 
-        let ret = match dbg!((does_continue, does_break)) {
-            (false, false) => {
-                self.builder.build_unconditional_branch(body_bb);
-                self.builder.position_at_end(exit_bb);
-                self.builder.build_unreachable();
-                None
-            }
-            (true, false) => {
-                self.builder.position_at_end(exit_bb);
-                self.builder.build_unreachable();
-                None
-            }
-            (_, true) => {
-                self.builder.position_at_end(exit_bb);
-                let result = self.builder.build_load(result_alloca, "loop.result");
-                Some(result)
-            }
+        self.builder.position_at_end(exit_bb);
+        let ret = if does_break {
+            Some(self.builder.build_load(result_alloca, "loop.result"))
+        } else {
+            self.builder.build_unreachable();
+            None
         };
 
-        vars.current_loop = old_loop;
-
         ret
     }
 
-    fn codegen_break(&self, vars: &mut Vars<'ctx>, expr: Option<ExprId>) -> Value {
+    /// The loop targeted by a `break` or `continue` with `label`.
+    fn target_loop<'v>(vars: &'v mut Vars<'ctx>, label: &Option<Label>) -> &'v mut Loop<'ctx> {
+        match label {
+            None => vars.loops.last_mut().unwrap(),
+            Some(label) => vars
+                .loops
+                .iter_mut()
+                .rev()
+                .find(|target| target.label.as_ref() == Some(label))
+                .unwrap(),
+        }
+    }
+
+    fn codegen_break(
+        &self,
+        vars: &mut Vars<'ctx>,
+        label: &Option<Label>,
+        expr: Option<ExprId>,
+    ) -> Value<'ctx> {
         let value = match expr {
             None => self.codegen_unit(),
             Some(expr) => self.codegen_expr(vars, expr)?,
@@ -605,25 +1469,31 @@ impl<'ctx> Compiler<'ctx> {
             exit_bb,
             ref mut does_break,
             ..
-        } = vars.current_loop.as_mut().unwrap();
+        } = Self::target_loop(vars, label);
         *does_break = true;
         self.builder.build_store(*result_alloca, value);
         self.builder.build_unconditional_branch(*exit_bb);
         None
     }
 
-    fn codegen_continue(&self, vars: &mut Vars<'ctx>) -> Value {
-        let Loop {
-            body_bb,
-            ref mut does_continue,
-            ..
-        } = vars.current_loop.as_mut().unwrap();
-        *does_continue = true;
+    fn codegen_continue(&self, vars: &mut Vars<'ctx>, label: &Option<Label>) -> Value<'ctx> {
+        let Loop { body_bb, .. } = Self::target_loop(vars, label);
         self.builder.build_unconditional_branch(*body_bb);
         None
     }
 
-    fn codegen_call(&self, vars: &mut Vars<'ctx>, func: ExprId, args: &[ExprId]) -> Value {
+    fn codegen_call(&self, vars: &mut Vars<'ctx>, func: ExprId, args: &[ExprId]) -> Value<'ctx> {
+        // an `extern fn` is called directly, rather than through its wrapper
+        if let Expr::Var(var) = self.hir[func] {
+            if let Some(Denotation::ExternFn(id)) = self.scopes.lookup_expr(func, &self.hir[var]) {
+                let args = args
+                    .iter()
+                    .map(|arg| self.codegen_expr(vars, *arg))
+                    .collect::<Option<Vec<_>>>()?;
+                return self.build_extern_call(vars, id, &args);
+            }
+        }
+
         let closure_value = self.codegen_expr(vars, func)?.into_struct_value();
         let code_ptr = self
             .builder
@@ -650,14 +1520,53 @@ impl<'ctx> Compiler<'ctx> {
         }
     }
 
+    /// Methods are known statically, so they are called directly rather than
+    /// through a closure.
+    fn codegen_method_call(
+        &self,
+        vars: &mut Vars<'ctx>,
+        expr: ExprId,
+        receiver: ExprId,
+        method: VarId,
+        args: &[ExprId],
+    ) -> Value<'ctx> {
+        let receiver_type = &self.types[receiver];
+        let call = self.resolve_method_call(expr, receiver_type, method);
+        let args = std::iter::once(&receiver).chain(args).collect::<Vec<_>>();
+        let values = (args.iter())
+            .map(|arg| self.codegen_expr(vars, **arg))
+            .collect::<Option<Vec<_>>>()?;
+        let id = match call {
+            MethodCall::Fn(id) => id,
+            MethodCall::BuiltinCmp(op) => {
+                let value = self.build_cmp(vars, receiver_type, values[0], op, values[1]);
+                return Some(value.into());
+            }
+            MethodCall::Trait(_) => unreachable!("resolved by `resolve_method_call`"),
+        };
+        let fn_type = FnType {
+            params: args.iter().map(|arg| self.types[**arg].clone()).collect(),
+            ret: self.types[expr].clone(),
+        };
+        let llvm_fn = self.fn_value(vars, id, &fn_type);
+        self.build_direct_call(llvm_fn, &values)
+    }
+
     fn codegen_lambda(
         &self,
         vars: &mut Vars<'ctx>,
         expr: ExprId,
         params: &[Param],
         body: ExprId,
-    ) -> BasicValueEnum {
-        let free_vars = self.free_vars(expr);
+    ) -> BasicValueEnum<'ctx> {
+        let mut free_vars = self.free_vars(expr);
+        // a local that another lambda mutates must be shared, even with
+        // lambdas that only read it
+        for (free_var, capture) in free_vars.iter_mut() {
+            if vars.heap_locals.contains(&free_var) {
+                *capture = Capture::Mutate;
+            }
+        }
 
         let code_ptr = self
             .codegen_lambda_body(
@@ -686,28 +1595,30 @@ impl<'ctx> Compiler<'ctx> {
             .build_struct_gep(closure_alloca, 1, "closure.env")
             .unwrap();
 
-        // store free variables
-        let free_vars_type = self.tuple_type(
-            &free_vars
-                .iter()
-                .map(|(pat, _)| self.types[pat].clone())
-                .collect::<Vec<_>>(),
-        );
-        let env_alloca = self.builder.build_alloca(free_vars_type, "env.alloca");
-        for (idx, (free_var, ())) in free_vars.iter().enumerate() {
+        // store free variables. The env is on the heap, so that the closure can
+        // be returned from the function that creates it. It is never freed, see
+        // the module docs
+        let env_malloc = self
+            .builder
+            .build_malloc(self.env_type(&free_vars), "env.malloc")
+            .unwrap();
+        for (idx, (free_var, capture)) in free_vars.iter().enumerate() {
             let gep = self
                 .builder
-                .build_struct_gep(env_alloca, idx as u32, &format!("env.{idx}.gep"))
+                .build_struct_gep(env_malloc, idx as u32, &format!("env.{idx}.gep"))
                 .unwrap();
-            let val = self
-                .builder
-                .build_load(vars[free_var], &format!("env.{idx}"));
+            let val = match capture {
+                Capture::Read => self
+                    .builder
+                    .build_load(vars[free_var], &format!("env.{idx}")),
+                Capture::Mutate => vars[free_var].into(),
+            };
             self.builder.build_store(gep, val);
         }
-        let env_alloca = self
+        let env = self
             .builder
-            .build_bitcast(env_alloca, self.void_ptr_type(), "env");
-        self.builder.build_store(env_gep, env_alloca);
+            .build_bitcast(env_malloc, self.void_ptr_type(), "env");
+        self.builder.build_store(env_gep, env);
         self.builder.build_load(closure_alloca, "closure")
     }
 
@@ -718,7 +1629,7 @@ impl<'ctx> Compiler<'ctx> {
         expr: ExprId,
         params: &[Param],
         body: ExprId,
-    ) -> FunctionValue {
+    ) -> FunctionValue<'ctx> {
         let fn_type = self.fn_type(&self.types[expr].as_fn().unwrap());
         let llvm_fn = self.module.add_function("lambda", fn_type, None);
         let old_bb = self.builder.get_insert_block().unwrap();
@@ -727,27 +1638,26 @@ impl<'ctx> Compiler<'ctx> {
         self.builder.position_at_end(bb);
 
         // load free vars
-        let free_vars_type = self.tuple_type(
-            &free_vars
-                .iter()
-                .map(|(pat, _)| self.types[pat].clone())
-                .collect::<Vec<_>>(),
-        );
-
         let env_param = llvm_fn.get_nth_param(0).unwrap();
         env_param.set_name("env_ptr");
         let env_ptr = self.builder.build_bitcast(
             env_param,
-            free_vars_type.ptr_type(AddressSpace::Generic),
+            self.env_type(free_vars).ptr_type(AddressSpace::Generic),
             "env_ptr",
         );
         let env = self.builder.build_load(env_ptr.into_pointer_value(), "env");
-        for (idx, (free_var, ())) in free_vars.iter().enumerate() {
+        for (idx, (free_var, capture)) in free_vars.iter().enumerate() {
             let val = self
                 .builder
                 .build_extract_value(env.into_struct_value(), idx as u32, &format!("env.{idx}"))
                 .unwrap();
-            self.codegen_local_var(vars, free_var, val)
+            match capture {
+                Capture::Read => self.codegen_local_var(vars, free_var, val),
+                // assignments in the body go through the enclosing scope's variable
+                Capture::Mutate => {
+                    vars.locals.insert(free_var, val.into_pointer_value());
+                }
+            }
         }
 
         // load params
@@ -770,7 +1680,7 @@ impl<'ctx> Compiler<'ctx> {
         llvm_fn
     }
 
-    fn codegen_return(&self, vars: &mut Vars<'ctx>, expr: Option<ExprId>) -> Value {
+    fn codegen_return(&self, vars: &mut Vars<'ctx>, expr: Option<ExprId>) -> Value<'ctx> {
         let value = match expr {
             Some(expr) => self.codegen_expr(vars, expr)?,
             None => self.codegen_unit(),
@@ -779,9 +1689,18 @@ impl<'ctx> Compiler<'ctx> {
         None
     }
 
-    fn codegen_unop(&self, vars: &mut Vars<'ctx>, op: Unop, expr: ExprId) -> Value {
+    fn codegen_unop(
+        &self,
+        vars: &mut Vars<'ctx>,
+        op: Unop,
+        op_span: TextRange,
+        expr: ExprId,
+    ) -> Value<'ctx> {
         let ty = &self.types[expr].ctor().unwrap();
         let value = self.codegen_expr(vars, expr)?;
+        if let Some(fn_id) = self.lookup_operator(&self.types[expr], op.operator_trait()) {
+            return self.build_direct_call(vars[fn_id], &[value]);
+        }
         let value = match (op, ty) {
             (Unop::Not, _) => self
                 .builder
@@ -792,26 +1711,156 @@ impl<'ctx> Compiler<'ctx> {
                     "",
                 )
                 .into(),
-            (Unop::Sub, &Ctor::Int) => self
-                .builder
-                .build_int_neg(value.into_int_value(), "")
-                .into(),
-            (Unop::Sub, &Ctor::Float) => self
+            (Unop::Sub, ctor) if ctor.is_int() => {
+                let value = value.into_int_value();
+                // the range of a negative literal is checked when it is lowered
+                let is_neg_lit = matches!(self.hir[expr], Expr::Lit(Lit::Int(..)));
+                if self.checked_arithmetic && !is_neg_lit {
+                    // only `MIN` has no signed negation, and only `0` an unsigned one
+                    let int_type = value.get_type();
+                    let overflow = if ctor.is_signed_int() {
+                        let min = int_type.const_int(1 << (int_type.get_bit_width() - 1), false);
+                        self.builder
+                            .build_int_compare(IntPredicate::EQ, value, min, "")
+                    } else {
+                        self.builder.build_int_compare(
+                            IntPredicate::NE,
+                            value,
+                            int_type.const_zero(),
+                            "",
+                        )
+                    };
+                    self.build_panic_if(op_span, overflow, "attempt to negate with overflow");
+                }
+                self.builder.build_int_neg(value, "").into()
+            }
+            (Unop::Sub, ctor) if ctor.is_float() => self
                 .builder
                 .build_float_neg(value.into_float_value(), "")
                 .into(),
             (Unop::Sub, _) => unreachable!(),
             (Unop::Add, _) => value,
+            (Unop::BitNot, _) => self.builder.build_not(value.into_int_value(), "").into(),
+        };
+        Some(value)
+    }
+
+    fn codegen_cast(&self, vars: &mut Vars<'ctx>, id: ExprId, expr: ExprId) -> Value<'ctx> {
+        let value = self.codegen_expr(vars, expr)?;
+
+        let from_ty = &self.types[expr];
+        let to_ty = &self.types[id];
+        let from = *from_ty.ctor().unwrap();
+        let to = *to_ty.ctor().unwrap();
+        let llvm_to = self.value_type(to_ty);
+
+        // `Bool` and `Char` are unsigned as far as casts are concerned
+        let is_signed = from.is_signed_int();
+
+        let value = match (from, to) {
+            _ if from == to => value,
+            (_, to) if to.is_int() || to == Ctor::Char => {
+                let to_type = llvm_to.into_int_type();
+                if from.is_float() {
+                    let value = value.into_float_value();
+                    if to.is_signed_int() {
+                        self.builder
+                            .build_float_to_signed_int(value, to_type, "cast")
+                            .into()
+                    } else {
+                        self.builder
+                            .build_float_to_unsigned_int(value, to_type, "cast")
+                            .into()
+                    }
+                } else {
+                    let value = value.into_int_value();
+                    let from_width = value.get_type().get_bit_width();
+                    let to_width = to_type.get_bit_width();
+                    if from_width > to_width {
+                        self.builder
+                            .build_int_truncate(value, to_type, "cast")
+                            .into()
+                    } else if from_width < to_width && is_signed {
+                        self.builder
+                            .build_int_s_extend(value, to_type, "cast")
+                            .into()
+                    } else if from_width < to_width {
+                        self.builder
+                            .build_int_z_extend(value, to_type, "cast")
+                            .into()
+                    } else {
+                        value.into()
+                    }
+                }
+            }
+            (_, to) if to.is_float() => {
+                let to_type = llvm_to.into_float_type();
+                if from.is_float() {
+                    self.builder
+                        .build_float_cast(value.into_float_value(), to_type, "cast")
+                        .into()
+                } else if is_signed {
+                    self.builder
+                        .build_signed_int_to_float(value.into_int_value(), to_type, "cast")
+                        .into()
+                } else {
+                    self.builder
+                        .build_unsigned_int_to_float(value.into_int_value(), to_type, "cast")
+                        .into()
+                }
+            }
+            _ => unreachable!(format!("cannot cast {from_ty:?} to {to_ty:?}")),
         };
         Some(value)
     }
 
-    fn codegen_binop(&self, vars: &mut Vars<'ctx>, lhs: ExprId, op: Binop, rhs: ExprId) -> Value {
+    fn codegen_binop(
+        &self,
+        vars: &mut Vars<'ctx>,
+        lhs: ExprId,
+        op: Binop,
+        op_span: TextRange,
+        rhs: ExprId,
+    ) -> Value<'ctx> {
+        // comparisons are overloaded in `build_cmp`, which also compares fields
+        if let Binop::Arithmetic(_) = op {
+            if let Some(fn_id) = self.lookup_operator(&self.types[lhs], op.operator_trait()) {
+                let lhs_value = self.codegen_expr(vars, lhs)?;
+                let rhs_value = self.codegen_expr(vars, rhs)?;
+                return self.build_overloaded_binop(vars, fn_id, op, lhs_value, rhs_value);
+            }
+        }
         match op {
             Binop::Lazy(op) => self.codegen_lazy_binop(vars, lhs, op, rhs),
-            Binop::Arithmetic(op) => self.codegen_arithmetic_binop(vars, lhs, op, rhs),
+            Binop::Arithmetic(op) => self.codegen_arithmetic_binop(vars, lhs, op, op_span, rhs),
+            Binop::Bit(op) => self.codegen_bit_binop(vars, lhs, op, op_span, rhs),
             Binop::Cmp(op) => self.codegen_cmp_binop(vars, lhs, op, rhs),
             Binop::Assign => self.codegen_assign(vars, lhs, rhs),
+            Binop::CompoundAssign(op) => self.codegen_compound_assign(vars, lhs, op, op_span, rhs),
+        }
+    }
+
+    /// `!=` and the orderings other than `<` call `eq` or `lt` with their
+    /// operands swapped and/or negate the result.
+    fn build_overloaded_binop(
+        &self,
+        vars: &Vars<'ctx>,
+        fn_id: FnDefId,
+        op: Binop,
+        lhs_value: BasicValueEnum<'ctx>,
+        rhs_value: BasicValueEnum<'ctx>,
+    ) -> Value<'ctx> {
+        let (args, negate) = match op {
+            Binop::Cmp(CmpBinop::NotEq | CmpBinop::GreaterEq) => ([lhs_value, rhs_value], true),
+            Binop::Cmp(CmpBinop::Greater) => ([rhs_value, lhs_value], false),
+            Binop::Cmp(CmpBinop::LessEq) => ([rhs_value, lhs_value], true),
+            _ => ([lhs_value, rhs_value], false),
+        };
+        let value = self.build_direct_call(vars[fn_id], &args)?;
+        if negate {
+            Some(self.builder.build_not(value.into_int_value(), "").into())
+        } else {
+            Some(value)
         }
     }
 
@@ -821,7 +1870,7 @@ impl<'ctx> Compiler<'ctx> {
         lhs: ExprId,
         op: LazyBinop,
         rhs: ExprId,
-    ) -> Value {
+    ) -> Value<'ctx> {
         let bool_type = self.llvm.bool_type();
         let bb = self.builder.get_insert_block().unwrap();
         let end_bb = self.llvm.insert_basic_block_after(bb, &format!("{op}.end"));
@@ -832,8 +1881,13 @@ impl<'ctx> Compiler<'ctx> {
             .llvm
             .insert_basic_block_after(bb, &format!("{op}.then"));
         let lhs_value = self.codegen_expr(vars, lhs)?;
+        // `then` short-circuits with the lhs, `else` evaluates the rhs
+        let (true_bb, false_bb) = match op {
+            LazyBinop::Or => (then_bb, else_bb),
+            LazyBinop::And => (else_bb, then_bb),
+        };
         self.builder
-            .build_conditional_branch(lhs_value.into_int_value(), then_bb, else_bb);
+            .build_conditional_branch(lhs_value.into_int_value(), true_bb, false_bb);
 
         // then branch
         self.builder.position_at_end(then_bb);
@@ -842,15 +1896,13 @@ impl<'ctx> Compiler<'ctx> {
         // else branch
         self.builder.position_at_end(else_bb);
         let rhs_value = self.codegen_expr(vars, rhs)?;
+        let rhs_bb = self.builder.get_insert_block().unwrap();
         self.builder.build_unconditional_branch(end_bb);
 
         // merge the 2 branches
         self.builder.position_at_end(end_bb);
         let phi = self.builder.build_phi(bool_type, &format!("{op}.merge"));
-        match op {
-            LazyBinop::Or => phi.add_incoming(&[(&lhs_value, then_bb), (&rhs_value, else_bb)]),
-            LazyBinop::And => phi.add_incoming(&[(&rhs_value, then_bb), (&lhs_value, else_bb)]),
-        }
+        phi.add_incoming(&[(&lhs_value, then_bb), (&rhs_value, rhs_bb)]);
         Some(phi.as_basic_value())
     }
 
@@ -859,15 +1911,34 @@ impl<'ctx> Compiler<'ctx> {
         vars: &mut Vars<'ctx>,
         lhs: ExprId,
         op: ArithmeticBinop,
+        op_span: TextRange,
         rhs: ExprId,
-    ) -> Value {
+    ) -> Value<'ctx> {
         let lhs_value = self.codegen_expr(vars, lhs)?;
         let rhs_value = self.codegen_expr(vars, rhs)?;
+        Some(self.build_arithmetic_binop(&self.types[lhs], lhs_value, op, op_span, rhs_value))
+    }
 
-        let lhs_ty = &self.types[lhs];
-        let rhs_ty = &self.types[rhs];
-
-        let ctor = self.types[lhs].ctor().unwrap();
+    fn build_arithmetic_binop(
+        &self,
+        ty: &Type,
+        lhs_value: BasicValueEnum<'ctx>,
+        op: ArithmeticBinop,
+        op_span: TextRange,
+        rhs_value: BasicValueEnum<'ctx>,
+    ) -> BasicValueEnum<'ctx> {
+        let ctor = ty.ctor().unwrap();
+        if self.checked_arithmetic && ctor.is_int() {
+            return self
+                .build_checked_int_binop(
+                    *ctor,
+                    lhs_value.into_int_value(),
+                    op,
+                    op_span,
+                    rhs_value.into_int_value(),
+                )
+                .into();
+        }
 
         #[rustfmt::skip]
         macro_rules! int_op {
@@ -879,19 +1950,219 @@ impl<'ctx> Compiler<'ctx> {
             ($op:ident) => {self.builder.$op(lhs_value.into_float_value(), rhs_value.into_float_value(), "") .into()};
         }
 
-        let value = match (ctor, op) {
-            (Ctor::Int, ArithmeticBinop::Add) => int_op!(build_int_add),
-            (Ctor::Int, ArithmeticBinop::Sub) => int_op!(build_int_sub),
-            (Ctor::Int, ArithmeticBinop::Mul) => int_op!(build_int_mul),
-            (Ctor::Int, ArithmeticBinop::Div) => int_op!(build_int_signed_div),
-            (Ctor::Float, ArithmeticBinop::Add) => float_op!(build_float_add),
-            (Ctor::Float, ArithmeticBinop::Sub) => float_op!(build_float_sub),
-            (Ctor::Float, ArithmeticBinop::Mul) => float_op!(build_float_mul),
-            (Ctor::Float, ArithmeticBinop::Div) => float_op!(build_float_div),
+        match (ctor, op) {
+            (ctor, ArithmeticBinop::Add) if ctor.is_int() => int_op!(build_int_add),
+            (ctor, ArithmeticBinop::Sub) if ctor.is_int() => int_op!(build_int_sub),
+            (ctor, ArithmeticBinop::Mul) if ctor.is_int() => int_op!(build_int_mul),
+            (ctor, ArithmeticBinop::Div) if ctor.is_signed_int() => {
+                int_op!(build_int_signed_div)
+            }
+            (ctor, ArithmeticBinop::Div) if ctor.is_unsigned_int() => {
+                int_op!(build_int_unsigned_div)
+            }
+            (ctor, ArithmeticBinop::Rem) if ctor.is_signed_int() => {
+                int_op!(build_int_signed_rem)
+            }
+            (ctor, ArithmeticBinop::Rem) if ctor.is_unsigned_int() => {
+                int_op!(build_int_unsigned_rem)
+            }
+            (ctor, ArithmeticBinop::Add) if ctor.is_float() => float_op!(build_float_add),
+            (ctor, ArithmeticBinop::Sub) if ctor.is_float() => float_op!(build_float_sub),
+            (ctor, ArithmeticBinop::Mul) if ctor.is_float() => float_op!(build_float_mul),
+            (ctor, ArithmeticBinop::Div) if ctor.is_float() => float_op!(build_float_div),
+            (ctor, ArithmeticBinop::Rem) if ctor.is_float() => float_op!(build_float_rem),
+
+            _ => unreachable!(format!("cannot perform binop {op} on {ty:?}")),
+        }
+    }
+
+    /// Integer arithmetic that branches to `builtins.panic` on overflow or
+    /// division by zero.
+    fn build_checked_int_binop(
+        &self,
+        ctor: Ctor,
+        lhs_value: IntValue<'ctx>,
+        op: ArithmeticBinop,
+        op_span: TextRange,
+        rhs_value: IntValue<'ctx>,
+    ) -> IntValue<'ctx> {
+        let int_type = lhs_value.get_type();
+        let signed = ctor.is_signed_int();
+        match op {
+            ArithmeticBinop::Add | ArithmeticBinop::Sub | ArithmeticBinop::Mul => {
+                let (name, verb) = match op {
+                    ArithmeticBinop::Add => ("add", "add"),
+                    ArithmeticBinop::Sub => ("sub", "subtract"),
+                    _ => ("mul", "multiply"),
+                };
+                let intrinsic_name = format!(
+                    "llvm.{}{name}.with.overflow.i{}",
+                    if signed { 's' } else { 'u' },
+                    int_type.get_bit_width()
+                );
+                let intrinsic = self
+                    .module
+                    .get_function(&intrinsic_name)
+                    .unwrap_or_else(|| {
+                        let ret_type = self
+                            .llvm
+                            .struct_type(&[int_type.into(), self.llvm.bool_type().into()], false);
+                        let fn_type = ret_type.fn_type(&[int_type.into(), int_type.into()], false);
+                        self.module.add_function(&intrinsic_name, fn_type, None)
+                    });
+                let result = self
+                    .builder
+                    .build_call(intrinsic, &[lhs_value.into(), rhs_value.into()], name)
+                    .try_as_basic_value()
+                    .left()
+                    .unwrap()
+                    .into_struct_value();
+                let value = self.builder.build_extract_value(result, 0, "").unwrap();
+                let overflow = self.builder.build_extract_value(result, 1, "").unwrap();
+                self.build_panic_if(
+                    op_span,
+                    overflow.into_int_value(),
+                    &format!("attempt to {verb} with overflow"),
+                );
+                value.into_int_value()
+            }
+            ArithmeticBinop::Div | ArithmeticBinop::Rem => {
+                let verb = match op {
+                    ArithmeticBinop::Div => "divide",
+                    _ => "calculate the remainder",
+                };
+                let is_zero = self.builder.build_int_compare(
+                    IntPredicate::EQ,
+                    rhs_value,
+                    int_type.const_zero(),
+                    "",
+                );
+                self.build_panic_if(op_span, is_zero, &format!("attempt to {verb} by zero"));
+
+                if !signed {
+                    return match op {
+                        ArithmeticBinop::Div => self
+                            .builder
+                            .build_int_unsigned_div(lhs_value, rhs_value, ""),
+                        _ => self
+                            .builder
+                            .build_int_unsigned_rem(lhs_value, rhs_value, ""),
+                    };
+                }
+
+                // `MIN / -1` does not fit in the result type
+                let min = int_type.const_int(1 << (int_type.get_bit_width() - 1), false);
+                let is_min = self
+                    .builder
+                    .build_int_compare(IntPredicate::EQ, lhs_value, min, "");
+                let is_minus_one = self.builder.build_int_compare(
+                    IntPredicate::EQ,
+                    rhs_value,
+                    int_type.const_all_ones(),
+                    "",
+                );
+                let overflow = self.builder.build_and(is_min, is_minus_one, "");
+                self.build_panic_if(
+                    op_span,
+                    overflow,
+                    &format!("attempt to {verb} with overflow"),
+                );
+                match op {
+                    ArithmeticBinop::Div => {
+                        self.builder.build_int_signed_div(lhs_value, rhs_value, "")
+                    }
+                    _ => self.builder.build_int_signed_rem(lhs_value, rhs_value, ""),
+                }
+            }
+        }
+    }
+
+    /// Branches to a call to `builtins.panic` if `cond` is true, and continues
+    /// codegen in the other branch.
+    fn build_panic_if(&self, op_span: TextRange, cond: IntValue<'ctx>, msg: &str) {
+        let bb = self.builder.get_insert_block().unwrap();
+        let panic_bb = self.llvm.insert_basic_block_after(bb, "panic");
+        let cont_bb = self.llvm.insert_basic_block_after(panic_bb, "panic.cont");
+        self.builder
+            .build_conditional_branch(cond, panic_bb, cont_bb);
+
+        self.builder.position_at_end(panic_bb);
+        self.build_panic(op_span, msg);
+
+        self.builder.position_at_end(cont_bb);
+    }
+
+    /// Prints `msg`, prefixed with the source location of the operator at
+    /// `op_span`, and exits.
+    fn build_panic(&self, op_span: TextRange, msg: &str) {
+        let (line, col) = self.file.line_col(op_span.start());
+        let msg = format!("{}:{line}:{col}: {msg}\n", self.file.name);
+
+        let panic_fn = self.module.get_function("builtins.panic").unwrap();
+        let msg_ptr = self
+            .builder
+            .build_global_string_ptr(&msg, "panic.msg")
+            .as_pointer_value();
+        let msg_len = self.llvm.i64_type().const_int(msg.len() as u64, false);
+        self.builder
+            .build_call(panic_fn, &[msg_ptr.into(), msg_len.into()], "");
+        self.builder.build_unreachable();
+    }
+
+    fn codegen_bit_binop(
+        &self,
+        vars: &mut Vars<'ctx>,
+        lhs: ExprId,
+        op: BitBinop,
+        op_span: TextRange,
+        rhs: ExprId,
+    ) -> Value<'ctx> {
+        let lhs_value = self.codegen_expr(vars, lhs)?;
+        let rhs_value = self.codegen_expr(vars, rhs)?;
+        Some(self.build_bit_binop(&self.types[lhs], lhs_value, op, op_span, rhs_value))
+    }
 
-            _ => unreachable!(format!("cannot perform binop {lhs_ty:?} {op} {rhs_ty:?}")),
+    fn build_bit_binop(
+        &self,
+        ty: &Type,
+        lhs_value: BasicValueEnum<'ctx>,
+        op: BitBinop,
+        op_span: TextRange,
+        rhs_value: BasicValueEnum<'ctx>,
+    ) -> BasicValueEnum<'ctx> {
+        let lhs_value = lhs_value.into_int_value();
+        let rhs_value = rhs_value.into_int_value();
+
+        let ctor = ty.ctor().unwrap();
+        if self.checked_arithmetic && matches!(op, BitBinop::Shl | BitBinop::Shr) {
+            // both sides have the same type, and a negative amount is too big
+            // when compared unsigned
+            let int_type = rhs_value.get_type();
+            let bit_width = int_type.const_int(u64::from(int_type.get_bit_width()), false);
+            let overflow =
+                self.builder
+                    .build_int_compare(IntPredicate::UGE, rhs_value, bit_width, "");
+            let verb = match op {
+                BitBinop::Shl => "shift left",
+                _ => "shift right",
+            };
+            self.build_panic_if(
+                op_span,
+                overflow,
+                &format!("attempt to {verb} with overflow"),
+            );
+        }
+        let value = match op {
+            BitBinop::And => self.builder.build_and(lhs_value, rhs_value, ""),
+            BitBinop::Or => self.builder.build_or(lhs_value, rhs_value, ""),
+            BitBinop::Xor => self.builder.build_xor(lhs_value, rhs_value, ""),
+            BitBinop::Shl => self.builder.build_left_shift(lhs_value, rhs_value, ""),
+            BitBinop::Shr => {
+                self.builder
+                    .build_right_shift(lhs_value, rhs_value, ctor.is_signed_int(), "")
+            }
         };
-        Some(value)
+        value.into()
     }
 
     fn codegen_cmp_binop(
@@ -900,134 +2171,363 @@ impl<'ctx> Compiler<'ctx> {
         lhs: ExprId,
         op: CmpBinop,
         rhs: ExprId,
-    ) -> Value {
+    ) -> Value<'ctx> {
         let lhs_value = self.codegen_expr(vars, lhs)?;
         let rhs_value = self.codegen_expr(vars, rhs)?;
+        Some(
+            self.build_cmp(vars, &self.types[lhs], lhs_value, op, rhs_value)
+                .into(),
+        )
+    }
 
-        let lhs_ty = &self.types[lhs];
-        let rhs_ty = &self.types[rhs];
-
-        let ctor = self.types[lhs].ctor().unwrap();
+    /// A struct or enum with an impl of `Eq` or `Ord` is compared with it,
+    /// including when it is a field of the values being compared.
+    fn build_cmp(
+        &self,
+        vars: &Vars<'ctx>,
+        ty: &Type,
+        lhs_value: BasicValueEnum<'ctx>,
+        op: CmpBinop,
+        rhs_value: BasicValueEnum<'ctx>,
+    ) -> IntValue<'ctx> {
+        if let Some(fn_id) = self.lookup_operator(ty, Binop::Cmp(op).operator_trait()) {
+            // inference checked that the impl returns `Bool`
+            let value =
+                self.build_overloaded_binop(vars, fn_id, Binop::Cmp(op), lhs_value, rhs_value);
+            return value.unwrap().into_int_value();
+        }
+        let ctor = ty.ctor().unwrap();
 
         #[rustfmt::skip]
         macro_rules! int_cmp {
-            ($op:expr) => {self.builder.build_int_compare($op,lhs_value.into_int_value(), rhs_value.into_int_value(), "") .into()};
+            ($op:expr) => {self.builder.build_int_compare($op,lhs_value.into_int_value(), rhs_value.into_int_value(), "")};
         }
 
         #[rustfmt::skip]
         macro_rules! float_cmp {
-            ($op:expr) => {self.builder.build_float_compare($op,lhs_value.into_float_value(), rhs_value.into_float_value(), "") .into()};
+            ($op:expr) => {self.builder.build_float_compare($op,lhs_value.into_float_value(), rhs_value.into_float_value(), "")};
         }
 
-        let value = match (ctor, op) {
-            (Ctor::Bool | Ctor::Int | Ctor::Char, CmpBinop::Eq) => {
-                int_cmp!(IntPredicate::EQ)
-            }
-            (Ctor::Bool | Ctor::Int | Ctor::Char, CmpBinop::NotEq) => {
-                int_cmp!(IntPredicate::NE)
-            }
-            (Ctor::Bool | Ctor::Int | Ctor::Char, CmpBinop::Less) => {
-                int_cmp!(IntPredicate::SLT)
-            }
-            (Ctor::Bool | Ctor::Int | Ctor::Char, CmpBinop::LessEq) => {
-                int_cmp!(IntPredicate::SLE)
-            }
-            (Ctor::Bool | Ctor::Int | Ctor::Char, CmpBinop::Greater) => {
-                int_cmp!(IntPredicate::SGT)
+        match ctor {
+            Ctor::Bool | Ctor::Char => int_cmp!(int_predicate(op, false)),
+            ctor if ctor.is_int() => int_cmp!(int_predicate(op, ctor.is_signed_int())),
+            ctor if ctor.is_float() => float_cmp!(float_predicate(op)),
+            Ctor::Tuple => {
+                let tys = ty.as_tuple().unwrap();
+                self.build_aggregate_cmp(vars, tys, lhs_value, op, rhs_value)
             }
-            (Ctor::Bool | Ctor::Int | Ctor::Char, CmpBinop::GreaterEq) => {
-                int_cmp!(IntPredicate::SGE)
+            Ctor::Struct(id) => {
+                let tys = (self.hir[*id].fields.iter())
+                    .map(|field| self.types[field.ty].clone())
+                    .collect::<Vec<_>>();
+                self.build_aggregate_cmp(vars, &tys, lhs_value, op, rhs_value)
             }
+            Ctor::Enum(id) => self.build_enum_cmp(vars, *id, lhs_value, op, rhs_value),
 
-            (Ctor::Float, CmpBinop::Eq) => {
-                float_cmp!(FloatPredicate::OEQ)
-            }
-            (Ctor::Float, CmpBinop::NotEq) => {
-                float_cmp!(FloatPredicate::ONE)
-            }
-            (Ctor::Float, CmpBinop::Less) => {
-                float_cmp!(FloatPredicate::OLT)
-            }
-            (Ctor::Float, CmpBinop::LessEq) => {
-                float_cmp!(FloatPredicate::OLE)
-            }
-            (Ctor::Float, CmpBinop::Greater) => {
-                float_cmp!(FloatPredicate::OGT)
+            _ => unreachable!(format!("cannot perform binop {op} on {ty:?}")),
+        }
+    }
+
+    /// Compares tuples or structs field by field: equality is the conjunction
+    /// of the fields' equalities, and ordering is lexicographic.
+    fn build_aggregate_cmp(
+        &self,
+        vars: &Vars<'ctx>,
+        tys: &[Type],
+        lhs_value: BasicValueEnum<'ctx>,
+        op: CmpBinop,
+        rhs_value: BasicValueEnum<'ctx>,
+    ) -> IntValue<'ctx> {
+        let bool_type = self.llvm.bool_type();
+        let lhs_value = lhs_value.into_struct_value();
+        let rhs_value = rhs_value.into_struct_value();
+        let fields = tys.iter().enumerate().map(|(idx, ty)| {
+            let lhs_field = self
+                .builder
+                .build_extract_value(lhs_value, idx as u32, "lhs")
+                .unwrap();
+            let rhs_field = self
+                .builder
+                .build_extract_value(rhs_value, idx as u32, "rhs")
+                .unwrap();
+            (ty, lhs_field, rhs_field)
+        });
+
+        match op {
+            CmpBinop::Eq | CmpBinop::NotEq => {
+                let eq = fields.fold(bool_type.const_int(1, false), |acc, (ty, lhs, rhs)| {
+                    let field_eq = self.build_cmp(vars, ty, lhs, CmpBinop::Eq, rhs);
+                    self.builder.build_and(acc, field_eq, "")
+                });
+                match op {
+                    CmpBinop::Eq => eq,
+                    _ => self.builder.build_not(eq, ""),
+                }
             }
-            (Ctor::Float, CmpBinop::GreaterEq) => {
-                float_cmp!(FloatPredicate::OGE)
+            _ => {
+                // Walk the fields backwards: the first field that differs decides
+                // the result, and if all fields are equal only `<=`/`>=` hold
+                let strict_op = match op {
+                    CmpBinop::Less | CmpBinop::LessEq => CmpBinop::Less,
+                    _ => CmpBinop::Greater,
+                };
+                let all_eq = matches!(op, CmpBinop::LessEq | CmpBinop::GreaterEq);
+                let init = bool_type.const_int(u64::from(all_eq), false);
+                fields.rev().fold(init, |acc, (ty, lhs, rhs)| {
+                    let field_eq = self.build_cmp(vars, ty, lhs, CmpBinop::Eq, rhs);
+                    let field_cmp = self.build_cmp(vars, ty, lhs, strict_op, rhs);
+                    self.builder
+                        .build_select(field_eq, acc, field_cmp, "")
+                        .into_int_value()
+                })
             }
+        }
+    }
 
-            _ => unreachable!(format!("cannot perform binop {lhs_ty:?} {op} {rhs_ty:?}")),
-        };
-        Some(value)
+    /// Enums are equal if they have the same tag and the fields of that
+    /// variant are equal. Every variant's fields are compared, and the result
+    /// for the active one is selected, since the other slots are undefined.
+    fn build_enum_cmp(
+        &self,
+        vars: &Vars<'ctx>,
+        id: EnumDefId,
+        lhs_value: BasicValueEnum<'ctx>,
+        op: CmpBinop,
+        rhs_value: BasicValueEnum<'ctx>,
+    ) -> IntValue<'ctx> {
+        let enum_def = &self.hir[id];
+        let lhs_value = lhs_value.into_struct_value();
+        let rhs_value = rhs_value.into_struct_value();
+        let lhs_tag = self
+            .builder
+            .build_extract_value(lhs_value, 0, "lhs.tag")
+            .unwrap()
+            .into_int_value();
+        let rhs_tag = self
+            .builder
+            .build_extract_value(rhs_value, 0, "rhs.tag")
+            .unwrap()
+            .into_int_value();
+
+        let false_value = self.llvm.bool_type().const_int(0, false);
+        let mut variants_eq = false_value;
+        for (tag, variant) in enum_def.variants.iter().enumerate() {
+            let idx = tag as u32 + 1;
+            let lhs_variant = self.builder.build_extract_value(lhs_value, idx, "lhs");
+            let rhs_variant = self.builder.build_extract_value(rhs_value, idx, "rhs");
+            let tys = (variant.fields.iter())
+                .map(|field| self.types[field.ty].clone())
+                .collect::<Vec<_>>();
+            let variant_eq = self.build_aggregate_cmp(
+                vars,
+                &tys,
+                lhs_variant.unwrap(),
+                CmpBinop::Eq,
+                rhs_variant.unwrap(),
+            );
+            let is_variant = self.builder.build_int_compare(
+                IntPredicate::EQ,
+                lhs_tag,
+                self.enum_tag_type().const_int(tag as u64, false),
+                "",
+            );
+            variants_eq = self
+                .builder
+                .build_select(is_variant, variant_eq, variants_eq, "")
+                .into_int_value();
+        }
+        let tags_eq = self
+            .builder
+            .build_int_compare(IntPredicate::EQ, lhs_tag, rhs_tag, "");
+        let eq = self
+            .builder
+            .build_select(tags_eq, variants_eq, false_value, "")
+            .into_int_value();
+        match op {
+            CmpBinop::Eq => eq,
+            CmpBinop::NotEq => self.builder.build_not(eq, ""),
+            _ => unreachable!(format!("cannot perform binop {op} on enums")),
+        }
     }
 
-    fn codegen_assign(&self, vars: &mut Vars<'ctx>, lhs: ExprId, rhs: ExprId) -> Value {
+    fn codegen_assign(&self, vars: &mut Vars<'ctx>, lhs: ExprId, rhs: ExprId) -> Value<'ctx> {
         let lhs = self.codegen_lvalue(vars, lhs)?;
         let rhs = self.codegen_expr(vars, rhs)?;
         self.builder.build_store(lhs, rhs);
         Some(self.codegen_unit())
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use std::{ffi::c_void, ptr};
 
-    use super::*;
-    use inkwell::OptimizationLevel;
+    fn codegen_compound_assign(
+        &self,
+        vars: &mut Vars<'ctx>,
+        lhs: ExprId,
+        op: CompoundBinop,
+        op_span: TextRange,
+        rhs: ExprId,
+    ) -> Value<'ctx> {
+        let ty = &self.types[lhs];
+        let lhs_ptr = self.codegen_lvalue(vars, lhs)?;
+        let rhs_value = self.codegen_expr(vars, rhs)?;
+        let lhs_value = self.builder.build_load(lhs_ptr, "");
+        let overload = self.lookup_operator(ty, Binop::from(op).operator_trait());
+        let value = match (op, overload) {
+            (_, Some(fn_id)) => {
+                self.build_overloaded_binop(vars, fn_id, op.into(), lhs_value, rhs_value)?
+            }
+            (CompoundBinop::Arithmetic(op), None) => {
+                self.build_arithmetic_binop(ty, lhs_value, op, op_span, rhs_value)
+            }
+            (CompoundBinop::Bit(op), None) => {
+                self.build_bit_binop(ty, lhs_value, op, op_span, rhs_value)
+            }
+        };
+        self.builder.build_store(lhs_ptr, value);
+        Some(self.codegen_unit())
+    }
+}
+
+const fn int_predicate(op: CmpBinop, signed: bool) -> IntPredicate {
+    match (op, signed) {
+        (CmpBinop::Eq, _) => IntPredicate::EQ,
+        (CmpBinop::NotEq, _) => IntPredicate::NE,
+        (CmpBinop::Less, true) => IntPredicate::SLT,
+        (CmpBinop::Less, false) => IntPredicate::ULT,
+        (CmpBinop::LessEq, true) => IntPredicate::SLE,
+        (CmpBinop::LessEq, false) => IntPredicate::ULE,
+        (CmpBinop::Greater, true) => IntPredicate::SGT,
+        (CmpBinop::Greater, false) => IntPredicate::UGT,
+        (CmpBinop::GreaterEq, true) => IntPredicate::SGE,
+        (CmpBinop::GreaterEq, false) => IntPredicate::UGE,
+    }
+}
+
+const fn float_predicate(op: CmpBinop) -> FloatPredicate {
+    match op {
+        CmpBinop::Eq => FloatPredicate::OEQ,
+        CmpBinop::NotEq => FloatPredicate::ONE,
+        CmpBinop::Less => FloatPredicate::OLT,
+        CmpBinop::LessEq => FloatPredicate::OLE,
+        CmpBinop::Greater => FloatPredicate::OGT,
+        CmpBinop::GreaterEq => FloatPredicate::OGE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::c_void, ptr};
+
+    use super::*;
+    use inkwell::OptimizationLevel;
     use insta::*;
 
     macro_rules! test_codegen_and_run {
         ($name:ident, $src:expr, $expected:expr) => {
             #[test]
-            fn $name() { test_codegen_and_run($src, $expected, true); }
+            fn $name() { test_codegen_and_run($src, $expected, true, false, true); }
+        };
+        ($name:ident, $src:expr, $expected:expr,checked) => {
+            #[test]
+            fn $name() { test_codegen_and_run($src, $expected, true, true, true); }
+        };
+        // a program that does not type-check, but still compiles
+        ($name:ident, $src:expr, $expected:expr,ill_typed) => {
+            #[test]
+            fn $name() { test_codegen_and_run($src, $expected, true, false, false); }
         };
     }
 
     macro_rules! test_codegen {
         ($name:ident, $src:expr) => {
             #[test]
-            fn $name() { test_codegen_and_run($src, (), false); }
+            fn $name() { test_codegen_and_run($src, (), false, false, true); }
+        };
+        ($name:ident, $src:expr,checked) => {
+            #[test]
+            fn $name() { test_codegen_and_run($src, (), false, true, true); }
         };
     }
 
-    #[track_caller]
-    fn test_codegen_and_run<T>(src: &str, expected: T, run: bool)
-    where
-        T: PartialEq + std::fmt::Debug,
-    {
+    macro_rules! test_codegen_and_run_in_child {
+        ($name:ident, $src:expr, $status:expr, $stdout:expr, $stderr:expr) => {
+            #[test]
+            fn $name() { test_codegen_and_run_in_child($src, $status, $stdout, $stderr, false); }
+        };
+        ($name:ident, $src:expr, $status:expr, $stdout:expr, $stderr:expr,checked) => {
+            #[test]
+            fn $name() { test_codegen_and_run_in_child($src, $status, $stdout, $stderr, true); }
+        };
+    }
+
+    /// Compiles `src`, which must type-check unless `type_checks` is false.
+    fn try_compile<'ctx>(
+        llvm: &'ctx Context,
+        src: &str,
+        checked_arithmetic: bool,
+        type_checks: bool,
+    ) -> Result<Module<'ctx>, Vec<CodegenError>> {
         let syntax = walrus_parser::parse(src);
-        let hir = walrus_semantics::hir::lower(&syntax);
+        let mut hir = walrus_semantics::hir::lower(&syntax);
         let scopes = walrus_semantics::scopes::scopes(&hir);
         let types = walrus_semantics::ty::infer(hir.clone(), scopes.clone());
-        dbg!(&syntax);
-        dbg!(&hir);
-        dbg!(&scopes);
-        dbg!(&types);
+        assert!(hir.diagnostics.is_empty(), "{:#?}", hir.diagnostics);
+        assert!(scopes.diagnostics.is_empty(), "{:#?}", scopes.diagnostics);
+        if type_checks {
+            assert!(types.diagnostics.is_empty(), "{:#?}", types.diagnostics);
+        }
 
-        let llvm = Context::create();
         let builder = llvm.create_builder();
         let module = llvm.create_module("module");
 
-        let llvm_module = {
-            let compiler = Compiler {
-                llvm: &llvm,
-                module,
-                builder,
-
-                hir: hir.data,
-                scopes,
-                types,
-            };
-            compiler.codegen_module()
+        let boxed_fields = walrus_semantics::ty::boxed_fields(&hir.data, &types);
+        let consts = walrus_semantics::consteval::eval_consts(&hir, &scopes, &types);
+        walrus_semantics::fold::fold(&mut hir.data, &scopes, &types, &consts);
+        let compiler = Compiler {
+            llvm,
+            module,
+            builder,
+
+            hir: hir.data,
+            scopes,
+            types,
+            boxed_fields,
+            consts,
+
+            file: SourceFile {
+                name: String::from("test.walrus"),
+                text: String::from(src),
+            },
+            checked_arithmetic,
         };
+        compiler.codegen_module()
+    }
+
+    fn compile<'ctx>(
+        llvm: &'ctx Context,
+        src: &str,
+        checked_arithmetic: bool,
+        type_checks: bool,
+    ) -> Module<'ctx> {
+        let llvm_module = try_compile(llvm, src, checked_arithmetic, type_checks).unwrap();
 
         let mut settings = insta::Settings::new();
         settings.set_snapshot_path("../snapshots");
         settings.set_prepend_module_to_snapshot(false);
         settings.bind(|| assert_display_snapshot!(llvm_module.print_to_string().to_string()));
+        llvm_module
+    }
+
+    #[track_caller]
+    fn test_codegen_and_run<T>(
+        src: &str,
+        expected: T,
+        run: bool,
+        checked_arithmetic: bool,
+        type_checks: bool,
+    ) where
+        T: PartialEq + std::fmt::Debug,
+    {
+        let llvm = Context::create();
+        let llvm_module = compile(&llvm, src, checked_arithmetic, type_checks);
 
         if run {
             let exec_engine = llvm_module
@@ -1044,12 +2544,83 @@ mod tests {
         }
     }
 
+    /// Runs `main` in a child process, so that it can write to stdout and
+    /// stderr, or exit, without taking the test harness with it.
+    #[track_caller]
+    fn test_codegen_and_run_in_child(
+        src: &str,
+        status: i32,
+        stdout: &str,
+        stderr: &str,
+        checked_arithmetic: bool,
+    ) {
+        let llvm = Context::create();
+        let llvm_module = compile(&llvm, src, checked_arithmetic, true);
+
+        // JIT before forking: the child must not take any of LLVM's locks,
+        // which another test's thread may have held when it was forked
+        let exec_engine = llvm_module
+            .create_jit_execution_engine(OptimizationLevel::None)
+            .unwrap();
+        let f = unsafe { exec_engine.get_function::<unsafe extern "C" fn(*mut c_void)>("main") }
+            .unwrap();
+        let got = unsafe { run_in_child(|| f.call(ptr::null_mut())) };
+        assert_eq!(got, (status, String::from(stdout), String::from(stderr)));
+    }
+
+    /// Returns the exit status, stdout and stderr of calling `f` in a forked
+    /// child.
+    unsafe fn run_in_child(f: impl FnOnce()) -> (i32, String, String) {
+        use std::{fs::File, io::Read, os::unix::io::FromRawFd};
+
+        let mut stdout = [0; 2];
+        let mut stderr = [0; 2];
+        assert_eq!(libc::pipe(stdout.as_mut_ptr()), 0);
+        assert_eq!(libc::pipe(stderr.as_mut_ptr()), 0);
+
+        // the child would otherwise print whatever this process has buffered
+        libc::fflush(ptr::null_mut());
+        let pid = libc::fork();
+        assert!(pid >= 0, "fork failed");
+        if pid == 0 {
+            libc::dup2(stdout[1], libc::STDOUT_FILENO);
+            libc::dup2(stderr[1], libc::STDERR_FILENO);
+            f();
+            libc::fflush(ptr::null_mut());
+            libc::_exit(0);
+        }
+
+        libc::close(stdout[1]);
+        libc::close(stderr[1]);
+        let read = |fd| {
+            let mut output = String::new();
+            File::from_raw_fd(fd).read_to_string(&mut output).unwrap();
+            output
+        };
+        let stdout = read(stdout[0]);
+        let stderr = read(stderr[0]);
+
+        let mut status = 0;
+        assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+        assert!(libc::WIFEXITED(status), "child did not exit: {:#x}", status);
+        (libc::WEXITSTATUS(status), stdout, stderr)
+    }
+
     test_codegen_and_run!(empty_fn, r#"fn main() -> _ {}"#, ());
     test_codegen_and_run!(lit_true, r#"fn main() -> _ { true }"#, true);
     test_codegen_and_run!(lit_false, r#"fn main() -> _ { false }"#, false);
     test_codegen_and_run!(lit_int, r#"fn main() -> _ { 1 }"#, 1_i32);
     test_codegen_and_run!(lit_float, r#"fn main() -> _ { 1.234 }"#, 1.234_f32);
     test_codegen_and_run!(lit_char, r#"fn main() -> _ { 'a' }"#, 'a');
+    test_codegen_and_run!(
+        lit_char_non_ascii,
+        r#"fn main() -> _ { '\u{1F600}' }"#,
+        '😀'
+    );
+
+    test_codegen_and_run!(lit_u8, r#"fn main() -> _ { let x: U8 = 255; x }"#, 255_u8);
+    test_codegen_and_run!(lit_i64, r#"fn main() -> I64 { 5 }"#, 5_i64);
+    test_codegen_and_run!(lit_f64, r#"fn main() -> F64 { 1.5 }"#, 1.5_f64);
 
     test_codegen_and_run!(tuple0, r#"fn main() -> _ { () }"#, ());
     test_codegen_and_run!(tuple1, r#"fn main() -> _ { (1,) }"#, (1_i32,));
@@ -1140,11 +2711,10 @@ fn main() -> _ {
     return 6;
 }
 "#,
-        5_i64
+        5_i64,
+        ill_typed
     );
 
-    // TODO
-    #[cfg(FALSE)]
     test_codegen_and_run!(
         if_then_return,
         r#"
@@ -1231,6 +2801,442 @@ fn main() -> _ {
         2.5_f32
     );
 
+    test_codegen_and_run!(u8_div, r#"fn main() -> _ {let x: U8 = 200; x / 2}"#, 100_u8);
+    test_codegen_and_run!(
+        u8_less,
+        r#"fn main() -> _ {let x: U8 = 200; x < 100}"#,
+        false
+    );
+
+    test_codegen_and_run!(
+        f64_add,
+        r#"fn main() -> _ {let x: F64 = 0.1; let y: F64 = 0.2; x + y}"#,
+        0.1_f64 + 0.2_f64
+    );
+    test_codegen_and_run!(
+        f64_less,
+        r#"fn main() -> _ {let x: F64 = 0.1; let y: F64 = 0.2; x < y}"#,
+        true
+    );
+
+    test_codegen_and_run!(cast_float_to_int, r#"fn main() -> _ { 2.7 as Int }"#, 2_i32);
+    test_codegen_and_run!(
+        cast_int_to_f64,
+        r#"fn main() -> _ { 3 as F64 / 2.0 }"#,
+        1.5_f64
+    );
+    test_codegen_and_run!(
+        cast_f64_to_float,
+        r#"fn main() -> _ { 0.5 as F64 as Float }"#,
+        0.5_f32
+    );
+    test_codegen_and_run!(cast_truncate, r#"fn main() -> _ { 300 as U8 }"#, 44_u8);
+    test_codegen_and_run!(
+        cast_sign_extend,
+        r#"fn main() -> _ { -1 as I8 as I64 }"#,
+        -1_i64
+    );
+    test_codegen_and_run!(
+        cast_zero_extend,
+        r#"fn main() -> _ { 255 as U8 as Int }"#,
+        255_i32
+    );
+    test_codegen_and_run!(cast_char_to_int, r#"fn main() -> _ { 'a' as Int }"#, 97_i32);
+    test_codegen_and_run!(
+        cast_int_lit_to_char,
+        r#"fn main() -> _ { 65 as Char as U32 }"#,
+        65_u32
+    );
+    test_codegen_and_run!(
+        cast_non_ascii_char_to_int,
+        "fn main() -> _ { '\u{20ac}' as U32 }",
+        0x20AC_u32
+    );
+
+    test_codegen_and_run!(int_rem, r#"fn main() -> _ { -7 % 2 }"#, -1_i32);
+    test_codegen_and_run!(u8_rem, r#"fn main() -> _ {let x: U8 = 250; x % 7}"#, 5_u8);
+    test_codegen_and_run!(float_rem, r#"fn main() -> _ { 7.5 % 2.0 }"#, 1.5_f32);
+    test_codegen_and_run!(bit_and, r#"fn main() -> _ { 12 & 10 }"#, 8_i32);
+    test_codegen_and_run!(bit_or, r#"fn main() -> _ { 12 | 10 }"#, 14_i32);
+    test_codegen_and_run!(bit_xor, r#"fn main() -> _ { 12 ^ 10 }"#, 6_i32);
+    test_codegen_and_run!(bit_xor_bool, r#"fn main() -> _ { true ^ true }"#, false);
+    test_codegen_and_run!(bit_not, r#"fn main() -> _ { ~0 }"#, -1_i32);
+    test_codegen_and_run!(shl, r#"fn main() -> _ { 1 << 4 }"#, 16_i32);
+    test_codegen_and_run!(shr_signed, r#"fn main() -> _ { -8 >> 1 }"#, -4_i32);
+    test_codegen_and_run!(
+        shr_unsigned,
+        r#"fn main() -> _ {let x: U8 = 128; x >> 7}"#,
+        1_u8
+    );
+    test_codegen_and_run!(
+        bit_precedence,
+        r#"fn main() -> _ { 1 | 2 ^ 3 & 4 << 1 + 1 }"#,
+        3_i32
+    );
+
+    test_codegen_and_run!(bool_less, r#"fn main() -> _ { false < true }"#, true);
+    test_codegen_and_run!(char_less, r#"fn main() -> _ { 'a' < 'b' }"#, true);
+    test_codegen_and_run!(unit_eq, r#"fn main() -> _ { () == () }"#, true);
+    test_codegen_and_run!(
+        tuple_eq,
+        r#"fn main() -> _ { (1, true, 'a') == (1, true, 'a') }"#,
+        true
+    );
+    test_codegen_and_run!(
+        tuple_not_eq,
+        r#"fn main() -> _ { (1, (2, 3)) != (1, (2, 4)) }"#,
+        true
+    );
+    test_codegen_and_run!(
+        tuple_less,
+        r#"fn main() -> _ { (1, 5, 9) < (1, 6, 0) }"#,
+        true
+    );
+    test_codegen_and_run!(
+        tuple_less_when_equal,
+        r#"fn main() -> _ { (1, 2) < (1, 2) }"#,
+        false
+    );
+    test_codegen_and_run!(
+        tuple_less_eq_when_equal,
+        r#"fn main() -> _ { (1, 2) <= (1, 2) }"#,
+        true
+    );
+    test_codegen_and_run!(tuple_greater, r#"fn main() -> _ { (2, 0) > (1, 9) }"#, true);
+    test_codegen_and_run!(
+        struct_eq,
+        r#"
+struct Foo {x: Int, y: (Bool, Float)}
+fn main() -> _ {
+    let a = Foo {x: 1, y: (true, 1.5)};
+    let b = Foo {x: 1, y: (true, 1.5)};
+    a == b
+}"#,
+        true
+    );
+    test_codegen_and_run!(
+        struct_not_eq,
+        r#"
+struct Foo {x: Int, y: Int}
+fn main() -> _ {
+    let a = Foo {x: 1, y: 2};
+    let b = Foo {y: 3, x: 1};
+    a != b
+}"#,
+        true
+    );
+    test_codegen_and_run!(
+        enum_eq,
+        r#"
+enum Foo {
+    X {x: Int},
+    Y {y: Bool, z: Char},
+}
+fn main() -> _ {
+    let a = Foo::Y {y: true, z: 'a'};
+    let b = Foo::Y {z: 'a', y: true};
+    a == b
+}"#,
+        true
+    );
+    test_codegen_and_run!(
+        enum_eq_different_fields,
+        r#"
+enum Foo {
+    X {x: Int},
+    Y {y: Bool},
+}
+fn main() -> _ {
+    let a = Foo::X {x: 1};
+    let b = Foo::X {x: 2};
+    a == b
+}"#,
+        false
+    );
+    test_codegen_and_run!(
+        enum_eq_different_variants,
+        r#"
+enum Foo {
+    X {x: Int},
+    Y {y: Int},
+}
+fn main() -> _ {
+    let a = Foo::X {x: 1};
+    let b = Foo::Y {y: 1};
+    a == b
+}"#,
+        false
+    );
+    test_codegen_and_run!(
+        match_list_sum,
+        r#"
+enum List {
+    Nil {},
+    Cons { head: Int, tail: List },
+}
+fn main() -> _ {
+    let xs = List::Cons { head: 1, tail: List::Cons { head: 2, tail: List::Nil {} } };
+    let ys = List::Cons { head: 3, tail: xs };
+    sum(ys)
+}
+fn sum(xs: List) -> Int {
+    match xs {
+        List::Nil {} => 0,
+        List::Cons { head: x, tail: xs } => x + sum(xs),
+    }
+}"#,
+        6_i32
+    );
+    test_codegen_and_run!(
+        match_tree,
+        r#"
+enum Tree {
+    Leaf { value: Int },
+    Node { left: Tree, right: Tree },
+}
+fn main() -> _ {
+    let left = Tree::Node { left: Tree::Leaf { value: 1 }, right: Tree::Leaf { value: 2 } };
+    let tree = Tree::Node { left: left, right: Tree::Leaf { value: 3 } };
+    depth(tree) * 10 + sum(tree)
+}
+fn depth(tree: Tree) -> Int {
+    match tree {
+        Tree::Leaf {} => 1,
+        Tree::Node { left: l, right: r } => max(depth(l), depth(r)) + 1,
+    }
+}
+fn max(a: Int, b: Int) -> Int {
+    if a > b { a } else { b }
+}
+fn sum(tree: Tree) -> Int {
+    match tree {
+        Tree::Leaf { value: x } => x,
+        Tree::Node { left: l, right: r } => sum(l) + sum(r),
+    }
+}"#,
+        36_i32
+    );
+    test_codegen_and_run!(
+        match_nested,
+        r#"
+enum Bit { Zero {}, One {} }
+fn main() -> _ {
+    let (zero, one) = (Bit::Zero {}, Bit::One {});
+    to_int(xor(zero, zero)) + to_int(xor(zero, one)) * 10 + to_int(xor(one, one)) * 100
+}
+fn xor(a: Bit, b: Bit) -> Bit {
+    match (a, b) {
+        (Bit::Zero {}, Bit::Zero {}) => Bit::Zero {},
+        (Bit::One {}, Bit::One {}) => Bit::Zero {},
+        _ => Bit::One {},
+    }
+}
+fn to_int(bit: Bit) -> Int {
+    match bit {
+        Bit::Zero {} => 0,
+        Bit::One {} => 1,
+    }
+}"#,
+        10_i32
+    );
+    test_codegen_and_run!(
+        match_diverging_arms,
+        r#"
+enum Bit { Zero {}, One {} }
+fn main() -> _ {
+    return_to_int(Bit::One {}) * 10 + break_to_int(Bit::One {})
+}
+fn return_to_int(bit: Bit) -> Int {
+    match bit {
+        Bit::Zero {} => return 0,
+        Bit::One {} => return 1,
+    }
+}
+fn break_to_int(bit: Bit) -> Int {
+    loop {
+        match bit {
+            Bit::Zero {} => break 0,
+            Bit::One {} => break 1,
+        }
+    }
+}"#,
+        11_i32
+    );
+    test_codegen_and_run!(
+        assign_boxed_field,
+        r#"
+struct Node { value: Int, child: Wrapper }
+struct Wrapper { value: Int, tree: Tree }
+enum Tree { Leaf {}, Branch { node: Node } }
+fn main() -> _ {
+    let a = Node { value: 1, child: Wrapper { value: 2, tree: Tree::Leaf {} } };
+    let mut b = a;
+    b.child.value = 10;
+    a.child.value + b.child.value
+}"#,
+        12_i32
+    );
+    // every write to `b.child.value` mallocs a new box for `b.child`, and the
+    // old one is never freed (see the module docs), so this leaks 100 boxes
+    test_codegen_and_run!(
+        assign_boxed_field_in_loop,
+        r#"
+struct Node { value: Int, child: Wrapper }
+struct Wrapper { value: Int, tree: Tree }
+enum Tree { Leaf {}, Branch { node: Node } }
+fn main() -> _ {
+    let a = Node { value: 1, child: Wrapper { value: 2, tree: Tree::Leaf {} } };
+    let mut b = a;
+    let mut i = 0;
+    while i < 100 {
+        b.child.value += 1;
+        i += 1;
+    }
+    a.child.value + b.child.value
+}"#,
+        104_i32
+    );
+
+    test_codegen_and_run!(
+        const_int,
+        r#"
+const LIMIT: Int = 10 * 10;
+fn main() -> _ { LIMIT - 1 }"#,
+        99_i32
+    );
+    test_codegen_and_run!(
+        const_negative,
+        r#"
+const MIN: I8 = -128;
+fn main() -> _ { MIN + 1 }"#,
+        -127_i8
+    );
+    test_codegen_and_run!(
+        const_tuple_and_struct,
+        r#"
+struct Point { x: Int, y: Int }
+const ORIGIN: Point = Point { y: 2, x: 1 };
+const PAIR: (Bool, Int) = (true, 3);
+fn main() -> _ { if PAIR.0 { ORIGIN.x + ORIGIN.y * PAIR.1 } else { 0 } }"#,
+        7_i32
+    );
+    test_codegen_and_run!(
+        const_refs,
+        r#"
+const AREA: Int = WIDTH * HEIGHT;
+const WIDTH: Int = 4;
+const HEIGHT: Int = WIDTH + 1;
+fn main() -> _ { AREA }"#,
+        20_i32
+    );
+    test_codegen_and_run!(
+        type_alias,
+        r#"
+type Pair = (Id, Id);
+type Id = U8;
+fn sum(p: Pair) -> Id { p.0 + p.1 }
+fn main() -> _ { sum((1, 2)) }"#,
+        3_u8
+    );
+
+    test_codegen_and_run!(fold_negative, r#"fn main() -> _ { 1 - 2 * 3 }"#, -5_i32);
+    test_codegen_and_run!(
+        fold_if,
+        r#"fn main() -> _ { if 1 < 2 && 'a' != 'b' { 10 } else { exit(1) } }"#,
+        10_i32
+    );
+    test_codegen_and_run!(
+        fold_after_return,
+        r#"
+fn main() -> Int {
+    return 3;
+    let x = 1 / 0;
+    x
+}"#,
+        3_i32
+    );
+
+    test_codegen_and_run!(checked_add, r#"fn main() -> _ { 1 + 2 }"#, 3_i32, checked);
+    test_codegen_and_run!(checked_div, r#"fn main() -> _ { -7 / 2 }"#, -3_i32, checked);
+    test_codegen_and_run!(
+        checked_u8_sub,
+        r#"fn main() -> _ {let x: U8 = 5; x - 3}"#,
+        2_u8,
+        checked
+    );
+    test_codegen_and_run!(
+        checked_compound_assign,
+        r#"fn main() -> _ {let mut x = 5; x *= 3; x}"#,
+        15_i32,
+        checked
+    );
+    test_codegen_and_run_in_child!(
+        checked_add_overflow,
+        r#"fn main() -> _ { 2147483647 + 1 }"#,
+        101,
+        "",
+        "test.walrus:1:29: attempt to add with overflow\n",
+        checked
+    );
+    test_codegen_and_run_in_child!(
+        checked_div_by_zero,
+        r#"
+fn main() -> _ {
+    let x = 0;
+    1 / x
+}"#,
+        101,
+        "",
+        "test.walrus:4:7: attempt to divide by zero\n",
+        checked
+    );
+    test_codegen_and_run_in_child!(
+        checked_neg_overflow,
+        r#"
+fn main() -> _ {
+    let x: I8 = -127 - 1;
+    -x
+}"#,
+        101,
+        "",
+        "test.walrus:4:5: attempt to negate with overflow\n",
+        checked
+    );
+    test_codegen_and_run!(
+        checked_shl,
+        r#"
+fn main() -> _ {
+    let x = 31;
+    1 << x
+}"#,
+        i32::MIN,
+        checked
+    );
+    test_codegen_and_run_in_child!(
+        checked_shl_overflow,
+        r#"
+fn main() -> _ {
+    let x = 32;
+    1 << x
+}"#,
+        101,
+        "",
+        "test.walrus:4:7: attempt to shift left with overflow\n",
+        checked
+    );
+    test_codegen_and_run_in_child!(
+        checked_shr_negative,
+        r#"
+fn main() -> _ {
+    let x = -1;
+    1 >> x
+}"#,
+        101,
+        "",
+        "test.walrus:4:7: attempt to shift right with overflow\n",
+        checked
+    );
+
     test_codegen_and_run!(
         int_eq,
         r#"fn main() -> _ {let x = 1; let y = 1; x == y}"#,
@@ -1291,18 +3297,120 @@ capture_x()
     );
 
     test_codegen_and_run!(
-        return_lambda,
-        r#"
-fn const_(k) -> _ { (_) => k }
-
-fn main() -> _ {
-    let const_five = const_(5);
+        lambda_mutate_free_var,
+        r#"fn main() -> _ {
+let mut n = 0;
+let inc = () => n = n + 1;
+inc();
+inc();
+n
+}"#,
+        2_i32
+    );
+
+    test_codegen_and_run!(
+        lambda_read_mutated_free_var,
+        r#"fn main() -> _ {
+let mut n = 0;
+let inc = () => n += 1;
+let get = () => n;
+inc();
+get()
+}"#,
+        1_i32
+    );
+
+    test_codegen_and_run!(
+        lambda_mutate_free_var_field,
+        r#"fn main() -> _ {
+let mut p = (1, 2);
+let set = (x) => p.1 = x;
+set(5);
+p.0 + p.1
+}"#,
+        6_i32
+    );
+
+    test_codegen_and_run!(
+        lambda_read_free_var_is_copied,
+        r#"fn main() -> _ {
+let mut n = 1;
+let get = () => n;
+n = 2;
+get()
+}"#,
+        1_i32
+    );
+
+    test_codegen_and_run!(
+        nested_lambda_mutate_free_var,
+        r#"fn main() -> _ {
+let mut n = 0;
+let f = () => {
+    let g = () => n += 10;
+    g();
+    g()
+};
+f();
+n
+}"#,
+        20_i32
+    );
+
+    test_codegen_and_run!(
+        return_lambda,
+        r#"
+fn const_(k) -> _ { (_) => k }
+
+fn main() -> _ {
+    let const_five = const_(5);
     const_five(0)
 }
 "#,
         5_i32
     );
 
+    test_codegen_and_run!(
+        return_lambda_mutate_free_var,
+        r#"
+fn make_counter() -> _ {
+    let mut n = 0;
+    () => {
+        n += 1;
+        n
+    }
+}
+
+fn main() -> _ {
+    let counter = make_counter();
+    counter();
+    counter()
+}
+"#,
+        2_i32
+    );
+
+    // each iteration mallocs `n` and the env of `inc`, and neither is freed
+    // (see the module docs), so this leaks 100 of each
+    test_codegen_and_run!(
+        lambda_in_loop,
+        r#"
+fn main() -> _ {
+    let mut total = 0;
+    let mut i = 0;
+    while i < 100 {
+        let mut n = i;
+        let inc = () => n += 1;
+        inc();
+        total += n;
+        i += 1;
+    }
+    total
+}
+"#,
+        5050_i32
+    );
+
     test_codegen!(
         builtin_exit,
         r#"
@@ -1310,7 +3418,7 @@ fn main() -> Int {exit(1)}
 "#
     );
 
-    test_codegen_and_run!(
+    test_codegen_and_run_in_child!(
         builtin_putchar,
         r#"
 fn main() -> () {
@@ -1327,9 +3435,109 @@ fn main() -> () {
     putchar('d');
 }
 "#,
-        ()
+        0,
+        "hello world",
+        ""
+    );
+
+    test_codegen_and_run_in_child!(
+        builtin_print_char,
+        r#"
+fn main() -> () {
+    print_char('h');
+    print_char('\u{E9}');
+    print_char('\u{20AC}');
+    print_char('\u{1F600}');
+    print_char('\n');
+}
+"#,
+        0,
+        "h\u{e9}\u{20ac}\u{1f600}\n",
+        ""
+    );
+
+    test_codegen_and_run!(
+        extern_fn_call,
+        r#"
+extern "C" fn abs(x: Int) -> Int;
+fn main() -> _ { abs(-5) }
+"#,
+        5_i32
+    );
+
+    test_codegen_and_run!(
+        extern_fn_as_value,
+        r#"
+extern "C" fn abs(x: Int) -> Int;
+fn main() -> _ {
+    let f = abs;
+    f(-3)
+}
+"#,
+        3_i32
+    );
+
+    test_codegen_and_run_in_child!(
+        extern_fn_shares_builtin_decl,
+        r#"
+extern fn putchar(c: Char) -> Int;
+fn main() -> () {
+    putchar('o');
+    putchar('k');
+    putchar('\n');
+}
+"#,
+        0,
+        "ok\n",
+        ""
     );
 
+    test_codegen!(
+        extern_fn_narrow_ints,
+        r#"
+extern fn f(a: I8, b: U16, c: Bool, d: Int) -> U8;
+fn main() -> () {}
+"#
+    );
+
+    #[test]
+    fn extern_fn_clashes_with_builtin_decl() {
+        let llvm = Context::create();
+        let src = r#"
+extern fn exit(code: I64) -> Never;
+fn main() -> () {}
+"#;
+        let errors = try_compile(&llvm, src, false, true)
+            .map(|_| ())
+            .unwrap_err();
+        assert!(
+            matches!(
+                errors.as_slice(),
+                [CodegenError::ExternFnTypeMismatch { expected, got, .. }]
+                    if expected == "void (i32)" && got == "void (i64)"
+            ),
+            "{:#?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn const_overflow_is_not_compiled() {
+        let llvm = Context::create();
+        let src = r#"
+const X: U8 = 200 + 100;
+fn main() -> U8 { X }
+"#;
+        let errors = try_compile(&llvm, src, false, true)
+            .map(|_| ())
+            .unwrap_err();
+        assert!(
+            matches!(errors.as_slice(), [CodegenError::ConstNotEvaluated(_)]),
+            "{:#?}",
+            errors
+        );
+    }
+
     // this successfully loops forever, which makes the test never complete!
     test_codegen!(
         loop_forever,
@@ -1361,14 +3569,346 @@ fn main() -> _ {
         5_i32
     );
 
-    // TODO: fails
-    #[cfg(FALSE)]
+    test_codegen_and_run!(
+        while_loop,
+        r#"
+fn main() -> _ {
+    let mut x = 1;
+    while x < 100 { x *= 3 }
+    x
+}
+"#,
+        243_i32
+    );
+
+    test_codegen_and_run!(
+        for_loop,
+        r#"
+fn main() -> _ {
+    let mut sum = 0;
+    for i in 1..5 { sum += i }
+    sum
+}
+"#,
+        10_i32
+    );
+
+    test_codegen_and_run!(
+        for_loop_continue,
+        r#"
+fn main() -> _ {
+    let mut sum = 0;
+    for i in 0..10 {
+        if i % 2 == 0 { continue }
+        sum += i;
+    }
+    sum
+}
+"#,
+        25_i32
+    );
+
+    test_codegen_and_run!(
+        nested_for_loop_break,
+        r#"
+fn main() -> _ {
+    let mut count = 0;
+    for i in 0..4 {
+        for j in 0..4 {
+            if j > i { break }
+            count += 1;
+        }
+    }
+    count
+}
+"#,
+        10_i32
+    );
+
+    test_codegen_and_run!(
+        labeled_break,
+        r#"
+fn main() -> _ {
+    let mut count = 0;
+    'outer: for i in 0..4 {
+        for j in 0..4 {
+            if i + j > 4 { break 'outer }
+            count += 1;
+        }
+    }
+    count
+}
+"#,
+        11_i32
+    );
+
+    test_codegen_and_run!(
+        labeled_break_value,
+        r#"
+fn main() -> _ {
+    'outer: loop {
+        loop { break 'outer 5 }
+    }
+}
+"#,
+        5_i32
+    );
+
+    test_codegen_and_run!(
+        labeled_continue,
+        r#"
+fn main() -> _ {
+    let mut count = 0;
+    'outer: for i in 0..4 {
+        for j in 0..4 {
+            if j > i { continue 'outer }
+            count += 1;
+        }
+    }
+    count
+}
+"#,
+        10_i32
+    );
+
+    test_codegen_and_run!(
+        method_call,
+        r#"
+fn main() -> _ {
+    let p = Point::new(3, 4);
+    p.sum()
+}
+struct Point { x: Int, y: Int }
+impl Point {
+    fn new(x: Int, y: Int) -> Point { Point { x: x, y: y } }
+    fn sum(self) -> Int { self.x + self.y }
+}
+"#,
+        7_i32
+    );
+
+    test_codegen_and_run!(
+        method_call_with_args,
+        r#"
+fn main() -> _ {
+    let p = Point { x: 1, y: 2 };
+    let q = Point { x: 5, y: 7 };
+    p.dist(q)
+}
+struct Point { x: Int, y: Int }
+impl Point {
+    fn dist(self, other: Point) -> Int { (other.x - self.x) + (other.y - self.y) }
+}
+"#,
+        9_i32
+    );
+
+    test_codegen_and_run!(
+        enum_method_call,
+        r#"
+fn main() -> _ {
+    let b = Bit::One {};
+    b.to_int()
+}
+enum Bit { Zero {}, One {} }
+impl Bit {
+    fn to_int(self) -> Int { 1 }
+}
+"#,
+        1_i32
+    );
+
+    test_codegen_and_run!(
+        methods_with_same_name,
+        r#"
+fn main() -> _ {
+    let a = A {};
+    let b = B {};
+    a.get() * 10 + b.get() + get()
+}
+fn get() -> Int { 100 }
+struct A {}
+impl A {
+    fn get(self) -> Int { 1 }
+}
+struct B {}
+impl B {
+    fn get(self) -> Int { 2 }
+}
+"#,
+        112_i32
+    );
+
+    test_codegen_and_run!(
+        trait_method_call,
+        r#"
+fn main() -> _ {
+    let p = Point { x: 1, y: 2 };
+    let q = Point { x: 1, y: 3 };
+    if p.eq(p) && !p.eq(q) { 1 } else { 0 }
+}
+struct Point { x: Int, y: Int }
+impl Eq for Point {
+    fn eq(self, other: Point) -> Bool { self.x == other.x && self.y == other.y }
+}
+"#,
+        1_i32
+    );
+    test_codegen_and_run!(
+        trait_impl_for_builtin_type,
+        r#"
+fn main() -> _ {
+    let x: Int = 3;
+    let c = 'c';
+    x.double() + c.double()
+}
+trait Double { fn double(self) -> Int; }
+impl Double for Int {
+    fn double(self) -> Int { self * 2 }
+}
+impl Double for Char {
+    fn double(self) -> Int { self as Int * 2 }
+}
+"#,
+        204_i32
+    );
+    test_codegen_and_run!(
+        overloaded_arithmetic,
+        r#"
+fn main() -> _ {
+    let mut v = V { x: 1, y: 2 };
+    let one = V { x: 1, y: 1 };
+    v += one * v + one;
+    let w = -(v * v - one);
+    w.x * 100 + w.y
+}
+struct V { x: Int, y: Int }
+impl Add for V {
+    fn add(self, other: V) -> V { V { x: self.x + other.x, y: self.y + other.y } }
+}
+impl Sub for V {
+    fn sub(self, other: V) -> V { V { x: self.x - other.x, y: self.y - other.y } }
+}
+impl Mul for V {
+    fn mul(self, other: V) -> V { V { x: self.x * other.x, y: self.y * other.y } }
+}
+impl Neg for V {
+    fn neg(self) -> V { V { x: -self.x, y: -self.y } }
+}
+"#,
+        -824_i32
+    );
+    test_codegen_and_run!(
+        overloaded_cmp,
+        r#"
+fn main() -> _ {
+    let a = V { x: 1, y: 100 };
+    let b = V { x: 2, y: 0 };
+    let c = V { x: 1, y: 0 };
+    let mut n = 0;
+    if a < b { n += 1 }
+    if b > a { n += 10 }
+    if a <= c && c <= a { n += 100 }
+    if b >= a && !(a >= b) { n += 1000 }
+    if a == c && a != b { n += 10000 }
+    n
+}
+struct V { x: Int, y: Int }
+impl Eq for V {
+    fn eq(self, other: V) -> Bool { self.x == other.x }
+}
+impl Ord for V {
+    fn lt(self, other: V) -> Bool { self.x < other.x }
+}
+"#,
+        11111_i32
+    );
+    test_codegen_and_run!(
+        overloaded_cmp_field,
+        r#"
+fn main() -> _ {
+    let a = (V { x: 1, y: 2 }, 1);
+    let b = (V { x: 1, y: 3 }, 1);
+    a == b
+}
+struct V { x: Int, y: Int }
+impl Eq for V {
+    fn eq(self, other: V) -> Bool { self.x == other.x }
+}
+"#,
+        true
+    );
+    test_codegen_and_run!(
+        generic_max,
+        r#"
+fn main() -> _ {
+    let a = max(1, 2);
+    let b = max(2.5, 1.5);
+    let c = max(V { x: 3 }, V { x: 1 });
+    a * 100 + b as Int * 10 + c.x
+}
+fn max<T: Ord>(a: T, b: T) -> T { if a < b { b } else { a } }
+struct V { x: Int }
+impl Ord for V {
+    fn lt(self, other: V) -> Bool { self.x < other.x }
+}
+"#,
+        223_i32
+    );
+    test_codegen_and_run!(
+        generic_nested,
+        r#"
+fn main() -> _ {
+    let x = if 1.lt(2) && 'a'.eq('a') { 1 } else { 0 };
+    x + max3(3, 9, 4) + id((10, 20)).1 + id(id)(100)
+}
+fn max3<T: Ord>(a: T, b: T, c: T) -> T { max(max(a, b), c) }
+fn max<T: Ord>(a: T, b: T) -> T { if a.lt(b) { b } else { a } }
+fn id<T>(x: T) -> T { x }
+"#,
+        130_i32
+    );
+    test_codegen_and_run!(
+        generic_trait_method,
+        r#"
+fn main() -> _ { twice(V { x: 1 }).x + twice(10) }
+fn twice<T: Double>(x: T) -> T { x.double() }
+trait Double { fn double(self) -> Self; }
+struct V { x: Int }
+impl Double for V {
+    fn double(self) -> V { V { x: self.x * 2 } }
+}
+impl Double for Int {
+    fn double(self) -> Int { self * 2 }
+}
+"#,
+        22_i32
+    );
+
+    test_codegen_and_run!(
+        path_fn_value,
+        r#"
+fn main() -> _ {
+    let p = Point { x: 3, y: 4 };
+    let f = Point::sum;
+    f(p)
+}
+struct Point { x: Int, y: Int }
+impl Point {
+    fn sum(self) -> Int { self.x + self.y }
+}
+"#,
+        7_i32
+    );
+
     test_codegen_and_run!(
         loop_and_break_and_continue,
         r#"
 fn main() -> _ {
+    let mut n = 0;
     loop {
-         if true {continue} else {break}
+         n += 1;
+         if n < 3 {continue} else {break}
     }
 }
 "#,
@@ -1378,24 +3918,83 @@ fn main() -> _ {
     test_codegen_and_run!(
         assign_var,
         r#"fn main() -> _{
-        let x = 5;
+        let mut x = 5;
         x = 6;
         x
     }"#,
         6_i32
     );
+    test_codegen_and_run!(
+        compound_assign,
+        r#"fn main() -> _{
+        let mut x = 5;
+        x += 3;
+        x *= 2;
+        x -= 1;
+        x /= 3;
+        x %= 4;
+        x
+    }"#,
+        1_i32
+    );
+    test_codegen_and_run!(
+        compound_assign_bit,
+        r#"fn main() -> _{
+        let mut x: U8 = 0b1100;
+        x |= 0b0011;
+        x &= 0b0110;
+        x ^= 0b1111;
+        x <<= 1;
+        x >>= 2;
+        x
+    }"#,
+        4_u8
+    );
+    test_codegen_and_run!(
+        compound_assign_float,
+        r#"fn main() -> _{
+        let mut x = 1.5;
+        x += 1.0;
+        x
+    }"#,
+        2.5_f32
+    );
 
-    // TODO
-    // test_codegen_and_run!(
-    //         assign_field,
-    //         r#"
-    //         struct S {x: Int}
+    test_codegen_and_run!(
+        assign_field,
+        r#"
+struct S {x: Int}
 
-    //         fn main() -> _{
-    //             let s = S {x: 0};
-    //             s.x = 1;
-    //             s.x
-    //     }"#,
-    //         1_i32
-    //     );
+fn main() -> _{
+    let mut s = S {x: 0};
+    s.x = 1;
+    s.x
+}"#,
+        1_i32
+    );
+    test_codegen_and_run!(
+        assign_tuple_field,
+        r#"
+fn main() -> _{
+    let mut t = (1, 2);
+    t.1 += 5;
+    t.1
+}"#,
+        7_i32
+    );
+    test_codegen_and_run!(
+        assign_nested_field,
+        r#"
+struct A {b: B, c: Int}
+struct B {x: Bool, y: (Int, Int)}
+
+fn main() -> _{
+    let b = B {x: true, y: (1, 2)};
+    let mut a = A {b: b, c: 3};
+    a.b.y.0 = 4;
+    a.c = a.c * 10;
+    a.b.y.0 + a.b.y.1 + a.c
+}"#,
+        36_i32
+    );
 }
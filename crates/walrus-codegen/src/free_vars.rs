@@ -1,11 +1,22 @@
 use crate::codegen::Compiler;
 use arena::ArenaMap;
 use walrus_semantics::{
-    hir::{Expr, ExprId, PatId},
+    hir::{Binop, Expr, ExprId, PatId, VarId},
     scopes::Denotation,
 };
 
-pub type FreeVars = ArenaMap<PatId, ()>;
+pub type FreeVars = ArenaMap<PatId, Capture>;
+
+/// How a lambda uses a variable bound outside of it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Capture {
+    /// The variable is only read, so the lambda captures a copy of its value
+    /// when it is created.
+    Read,
+    /// The variable is assigned to, so the lambda captures its address and
+    /// updates are shared with the enclosing scope.
+    Mutate,
+}
 
 impl<'ctx> Compiler<'ctx> {
     pub fn free_vars(&self, expr: ExprId) -> FreeVars {
@@ -17,18 +28,39 @@ impl<'ctx> Compiler<'ctx> {
     fn free_vars_helper(&self, free_vars: &mut FreeVars, expr_id: ExprId) {
         let expr = &self.hir[expr_id];
         match expr {
-            Expr::Var(var_id) => {
-                let var = &self.hir[*var_id];
-                let usage_scope = self.scopes.scope_of_expr(expr_id);
-                let denotation = self.scopes.lookup_expr(expr_id, var).unwrap();
-                if let Denotation::Local(pat_id) = denotation {
-                    let defining_scope = self.scopes.scope_of_pat(pat_id);
-                    if usage_scope.lambda_depth != defining_scope.lambda_depth {
-                        free_vars.insert(pat_id, ());
-                    }
-                }
+            Expr::Var(var_id) => self.capture(free_vars, expr_id, *var_id, Capture::Read),
+            Expr::Binop {
+                lhs,
+                op: Binop::Assign | Binop::CompoundAssign(_),
+                ..
+            } => {
+                self.free_vars_in_lvalue(free_vars, *lhs);
+                expr.walk_child_exprs(|expr| self.free_vars_helper(free_vars, expr))
             }
             expr => expr.walk_child_exprs(|expr| self.free_vars_helper(free_vars, expr)),
         }
     }
+
+    /// Marks the variable at the base of the lvalue `expr` as mutated.
+    fn free_vars_in_lvalue(&self, free_vars: &mut FreeVars, expr_id: ExprId) {
+        match &self.hir[expr_id] {
+            Expr::Var(var_id) => self.capture(free_vars, expr_id, *var_id, Capture::Mutate),
+            Expr::Field { expr, .. } => self.free_vars_in_lvalue(free_vars, *expr),
+            _ => {}
+        }
+    }
+
+    fn capture(&self, free_vars: &mut FreeVars, expr_id: ExprId, var_id: VarId, capture: Capture) {
+        let var = &self.hir[var_id];
+        let usage_scope = self.scopes.scope_of_expr(expr_id);
+        let denotation = self.scopes.lookup_expr(expr_id, var).unwrap();
+        if let Denotation::Local(pat_id) = denotation {
+            let defining_scope = self.scopes.scope_of_pat(pat_id);
+            if usage_scope.lambda_depth != defining_scope.lambda_depth
+                && free_vars.get(pat_id) != Some(&Capture::Mutate)
+            {
+                free_vars.insert(pat_id, capture);
+            }
+        }
+    }
 }
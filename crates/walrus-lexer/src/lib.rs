@@ -32,6 +32,8 @@ pub struct Token<'a> {
     pub range: TextRange,
     pub kind: TokenKind,
     pub text: &'a str,
+    /// What is wrong with the token, if its kind is `TokenKind::Error`.
+    pub error: Option<LexErrorKind>,
 }
 
 impl fmt::Debug for Token<'_> {
@@ -40,20 +42,153 @@ impl fmt::Debug for Token<'_> {
     }
 }
 
-pub fn lex(src: &str) -> impl Iterator<Item = Token> {
-    const ERROR: &str = "Programmer error: should have ensured that source was less than 4 GiB";
+impl Token<'_> {
+    /// What is wrong with the token, located at the offending part of it.
+    pub fn lex_error(&self) -> Option<LexError> {
+        let kind = self.error?;
+        let range = match kind {
+            LexErrorKind::BadUnicodeEscape => {
+                let quote = TextSize::of('\'');
+                TextRange::new(self.range.start() + quote, self.range.end() - quote)
+            }
+            _ => self.range,
+        };
+        Some(LexError { range, kind })
+    }
+}
+
+/// What is wrong with a token of kind `TokenKind::Error`, or with the source
+/// as a whole.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LexErrorKind {
+    UnknownChar,
+    UnterminatedBlockComment,
+    /// `''`
+    EmptyChar,
+    /// A char literal with more than one char, eg `'ab'`.
+    LongChar,
+    /// A radix prefix with no digits after it, eg `0x`.
+    MissingDigits,
+    /// A `\u` escape without braces around its digits, eg `'\u0a'`.
+    UnbracedUnicodeEscape,
+    /// A `\u{...}` escape without any digits or with more than six, eg
+    /// `'\u{}'`. Its range is that of the escape, without the quotes.
+    BadUnicodeEscape,
+    /// The source is longer than the 4 GiB that spans can address. Only the
+    /// part before `range` is lexed.
+    SourceTooLong,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct LexError {
+    pub range: TextRange,
+    pub kind: LexErrorKind,
+}
 
+pub fn lex(src: &str) -> impl Iterator<Item = Token> {
+    let src = lexable_prefix(src, MAX_LEN);
     let mut lexer = Lexer::new(src);
     iter::from_fn(move || {
-        let kind = lexer.next()?;
+        let mut kind = lexer.next()?;
+        if kind == TokenKind::DecInt && is_trailing_dot(lexer.slice(), lexer.remainder()) {
+            lexer.bump(".".len());
+            kind = TokenKind::Float;
+        }
         let text = lexer.slice();
+        let error = match kind {
+            TokenKind::Error => Some(lexer.extras.take().unwrap_or(LexErrorKind::UnknownChar)),
+            _ => None,
+        };
 
         let range = {
+            // cannot fail, since `src` is no longer than `MAX_LEN`
             let Range { start, end } = lexer.span();
-            let start = TextSize::try_from(start).expect(ERROR);
-            let end = TextSize::try_from(end).expect(ERROR);
+            let start = TextSize::try_from(start).ok()?;
+            let end = TextSize::try_from(end).ok()?;
             TextRange::new(start, end)
         };
-        Some(Token { range, kind, text })
+        Some(Token {
+            range,
+            kind,
+            text,
+            error,
+        })
+    })
+}
+
+/// Whether the `.` at the start of `rest` makes `int` into a float like `1.`.
+/// It does not if `int` has a suffix, or if the `.` begins a `..`, a field
+/// access or a method call.
+fn is_trailing_dot(int: &str, rest: &str) -> bool {
+    let mut rest = rest.chars();
+    int.bytes().all(|b| b.is_ascii_digit() || b == b'_')
+        && rest.next() == Some('.')
+        && !matches!(rest.next(), Some(c) if c == '.' || c == '_' || c.is_alphanumeric())
+}
+
+/// The errors in the tokens of `src`, in source order.
+pub fn lex_errors(src: &str) -> Vec<LexError> {
+    lex(src)
+        .filter_map(|token| token.lex_error())
+        .chain(source_too_long(src))
+        .collect()
+}
+
+/// A `SourceTooLong` error at the end of the prefix of `src` that `lex`
+/// tokenizes, if it does not cover all of `src`.
+pub fn source_too_long(src: &str) -> Option<LexError> {
+    let prefix = lexable_prefix(src, MAX_LEN);
+    (prefix.len() < src.len()).then(|| LexError {
+        range: TextRange::empty(TextSize::of(prefix)),
+        kind: LexErrorKind::SourceTooLong,
     })
 }
+
+/// The longest source that `TextSize` can address.
+const MAX_LEN: usize = u32::MAX as usize;
+
+/// The longest prefix of `src` that is at most `max_len` bytes and ends on a
+/// char boundary.
+fn lexable_prefix(src: &str, max_len: usize) -> &str {
+    if src.len() <= max_len {
+        return src;
+    }
+    let end = (0..=max_len)
+        .rev()
+        .find(|idx| src.is_char_boundary(*idx))
+        .unwrap_or(0);
+    &src[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lex_errors_are_located() {
+        let errors = lex_errors(r"$ '' 'ab' 0x '\u0a' '\u{}' /* a");
+        let errors = errors
+            .iter()
+            .map(|error| (u32::from(error.range.start()), error.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                (0, LexErrorKind::UnknownChar),
+                (2, LexErrorKind::EmptyChar),
+                (5, LexErrorKind::LongChar),
+                (10, LexErrorKind::MissingDigits),
+                (13, LexErrorKind::UnbracedUnicodeEscape),
+                (21, LexErrorKind::BadUnicodeEscape),
+                (27, LexErrorKind::UnterminatedBlockComment),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexable_prefix_ends_on_char_boundary() {
+        assert_eq!(lexable_prefix("abc", 5), "abc");
+        assert_eq!(lexable_prefix("abc", 2), "ab");
+        assert_eq!(lexable_prefix("a\u{e9}", 2), "a");
+    }
+}
@@ -1,3 +1,4 @@
+use crate::LexErrorKind;
 use logos::Logos;
 
 pub type Lexer<'a> = logos::Lexer<'a, TokenKind>;
@@ -10,41 +11,85 @@ pub type Lexer<'a> = logos::Lexer<'a, TokenKind>;
 #[logos(subpattern BinDigit_ = r"[0-1_]")]
 #[logos(subpattern HexDigit  = r"[0-9a-fA-F]")]
 #[logos(subpattern HexDigit_ = r"[0-9a-fA-F_]")]
+#[logos(subpattern MoreHex   = r"([0-9a-fA-F]_*)?")]
+#[logos(subpattern Exponent    = r"[eE][+-]?[0-9][0-9_]*")]
+#[logos(subpattern IntSuffix   = r"[iu](8|16|32|64)")]
+#[logos(subpattern FloatSuffix = r"f(32|64)")]
+#[logos(extras = Option<LexErrorKind>)]
 pub enum TokenKind {
-    #[error] Error,
+    /// Anything else, including these malformed tokens, which are matched
+    /// whole. The callbacks that reject a token record what is wrong with it
+    /// in `extras`: if they have not, the token is an unknown char.
+    #[error]
+    #[regex(r"''", |lexer| lexer.extras = Some(LexErrorKind::EmptyChar))]
+    #[regex(r"0[bBxX]", |lexer| lexer.extras = Some(LexErrorKind::MissingDigits))]
+    #[regex(r"'\\u(?&HexDigit_)+'", |lexer| lexer.extras = Some(LexErrorKind::UnbracedUnicodeEscape))]
+    #[regex(r"'\\u\{(?&HexDigit_)*\}'", |lexer| lexer.extras = Some(LexErrorKind::BadUnicodeEscape))]
+    Error,
     #[regex(r"\s+")] Whitespace,
     #[regex(r"//[^\n]*")] LineComment,
     #[token(r"/*", block_comment)] BlockComment,
 
+    #[token("as")] KwAs,
     #[token("break")] KwBreak,
+    #[token("const")] KwConst,
     #[token("continue")] KwContinue,
     #[token("else")] KwElse,
     #[token("enum")] KwEnum,
+    #[token("extern")] KwExtern,
     #[token("false")] KwFalse,
     #[token("fn")] KwFn,
+    #[token("for")] KwFor,
     #[token("if")] KwIf,
+    #[token("impl")] KwImpl,
     #[token("import")] KwImport,
+    #[token("in")] KwIn,
     #[token("let")] KwLet,
     #[token("loop")] KwLoop,
+    #[token("match")] KwMatch,
+    #[token("mut")] KwMut,
     #[token("return")] KwReturn,
     #[token("struct")] KwStruct,
+    #[token("trait")] KwTrait,
     #[token("true")] KwTrue,
+    #[token("type")] KwType,
+    #[token("while")] KwWhile,
 
     #[regex(r"(\p{XID_Start}|_)\p{XID_Continue}*")] Ident,
-    #[regex(r"(?&DecDigit)(?&DecDigit_)*")]         DecInt,
-    #[regex(r"(0b|0B)(?&BinDigit)(?&BinDigit_)*")]  BinInt,
-    #[regex(r"(0x|0X)(?&HexDigit)(?&HexDigit_)*")]  HexInt,
-    #[regex(r"(?&DecDigit)(?&DecDigit_)*\.(?&DecDigit)(?&DecDigit_)*")] Float,
-    #[regex(r"'[^']'")]                              SimpleChar,
-    #[regex(r"'\\.'")]                               EscapedChar,
-    #[regex(r"'\\(u|U)(?&HexDigit)(?&HexDigit_)*'")] UnicodeChar,
+    #[regex(r"'(\p{XID_Start}|_)\p{XID_Continue}*", label)] Label,
+    #[regex(r"(?&DecDigit)(?&DecDigit_)*(?&IntSuffix)?")]        DecInt,
+    #[regex(r"(0b|0B)(?&BinDigit)(?&BinDigit_)*(?&IntSuffix)?")] BinInt,
+    #[regex(r"(0x|0X)(?&HexDigit)(?&HexDigit_)*(?&IntSuffix)?")] HexInt,
+    /// `1.` is also a `Float`, but is recognised by `lex`, since whether it is
+    /// depends on the char after the `.`
+    #[regex(r"(?&DecDigit)(?&DecDigit_)*\.(?&DecDigit)(?&DecDigit_)*(?&Exponent)?(?&FloatSuffix)?")]
+    #[regex(r"(?&DecDigit)(?&DecDigit_)*(?&Exponent)(?&FloatSuffix)?")]
+    #[regex(r"(?&DecDigit)(?&DecDigit_)*(?&FloatSuffix)")]
+    Float,
+    // Split on `XID_Continue` so that the byte-level graph logos builds
+    // for non-ASCII chars does not collide with the one for `Label`
+    #[regex(r"'\p{XID_Continue}'")]
+    #[regex(r"'[^'\p{XID_Continue}]'")]
+    SimpleChar,
+    #[regex(r"'\\.'")]
+    #[regex(r"'\\x(?&HexDigit)(?&HexDigit)'")]
+    EscapedChar,
+    /// One to six hex digits, spelt out since logos does not support `{1,6}`.
+    /// The priority is over the `Error` regex for any `\u{...}`.
+    #[regex(r"'\\u\{(?&HexDigit)_*(?&MoreHex)(?&MoreHex)(?&MoreHex)(?&MoreHex)(?&MoreHex)\}'", priority = 100)]
+    UnicodeChar,
+    /// Only used for the ABI of an `extern` fn, such as `"C"`.
+    #[regex(r#""[^"]*""#)] Str,
 
     #[token("(")] LParen,
     #[token(")")] RParen,
     #[token("{")] LCurly,
     #[token("}")] RCurly,
+    #[token("[")] LSquare,
+    #[token("]")] RSquare,
 
     #[token(".")] Dot,
+    #[token("..")] DotDot,
     #[token(",")] Comma,
     #[token(";")] Semicolon,
     #[token(":")] Colon,
@@ -52,11 +97,20 @@ pub enum TokenKind {
     #[token("->")] ThinArrow,
     #[token("=>")] FatArrow,
     #[token("_")] Underscore,
+    #[token("#")] Pound,
 
     #[token("+")] Plus,
     #[token("-")] Minus,
     #[token("*")] Star,
     #[token("/")] Slash,
+    #[token("%")] Percent,
+
+    #[token("&")] And,
+    #[token("|")] Or,
+    #[token("^")] Caret,
+    #[token("~")] Tilde,
+    #[token("<<")] LessLess,
+    #[token(">>")] GreaterGreater,
 
     #[token("!")] Bang,
     #[token("&&")] AndAnd,
@@ -69,9 +123,22 @@ pub enum TokenKind {
     #[token("<=")] LessEq,
     #[token(">")] Greater,
     #[token(">=")] GreaterEq,
+
+    #[token("+=")] PlusEq,
+    #[token("-=")] MinusEq,
+    #[token("*=")] StarEq,
+    #[token("/=")] SlashEq,
+    #[token("%=")] PercentEq,
+    #[token("&=")] AndEq,
+    #[token("|=")] OrEq,
+    #[token("^=")] CaretEq,
+    #[token("<<=")] LessLessEq,
+    #[token(">>=")] GreaterGreaterEq,
 }
 
-fn block_comment(lexer: &mut Lexer) {
+/// Block comments can be nested. Returns `false`, making the comment an
+/// `Error` token, if the source ends before the comment does.
+fn block_comment(lexer: &mut Lexer) -> bool {
     const OPEN: &str = "/*";
     const CLOSE: &str = "*/";
 
@@ -89,6 +156,21 @@ fn block_comment(lexer: &mut Lexer) {
             lexer.bump(src.chars().next().unwrap().len_utf8())
         }
     }
+    if level > 0 {
+        lexer.extras = Some(LexErrorKind::UnterminatedBlockComment);
+    }
+    level == 0
+}
+
+/// A label followed by a `'` is really an over-long char literal, such as
+/// `'ab'`. Returns `false` in that case, making the literal an `Error` token.
+fn label(lexer: &mut Lexer) -> bool {
+    if lexer.remainder().starts_with('\'') {
+        lexer.bump(1);
+        lexer.extras = Some(LexErrorKind::LongChar);
+        return false;
+    }
+    true
 }
 
 impl TokenKind {
@@ -129,16 +211,37 @@ mod tests {
     );
     test_lex!(
         keywords,
-        r"break continue else enum false fn if import let loop return struct true"
+        r"as break const continue else enum extern false fn for if impl import in let loop match mut return struct trait true type while"
     );
     test_lex!(idents, "abc_DEF_123");
+    test_lex!(label, "'outer: loop { break 'outer 'a' }");
     test_lex!(dec_int, "123_456_7890");
     test_lex!(bin_int, "0b101");
     test_lex!(hex_int, "0x1234_56789_abc_def");
     test_lex!(float, "123.456");
+    test_lex!(float_exponent, "1e9 2.5e-3 6E+2_0");
+    test_lex!(float_trailing_dot, "1. 2.max 3..4 5.f64");
+    test_lex!(int_suffix, "10u8 0b1i16 0xFFu64 1u 2usize");
+    test_lex!(float_suffix, "3.0f64 1e3f32 2f64");
     test_lex!(simple_char, "'a'");
+    test_lex!(non_ascii_char, "'\u{e9}' '\u{20ac}' '\u{1f600}'");
     test_lex!(escaped_char, r"'\n'");
-    test_lex!(unicode_char, r"'\u0a'");
-    test_lex!(symbols, "() {} . , ; : :: -> => _");
-    test_lex!(operators, "+ - * / ! = == != < <= > >= || &&");
+    test_lex!(byte_escape_char, r"'\x7F' '\x'");
+    test_lex!(
+        unicode_char,
+        r"'\u{1F600}' '\u{10_FFFF}' '\u{}' '\u{_}' '\u{1234567}' '\u0a'"
+    );
+    test_lex!(symbols, "() {} . .. , ; : :: -> => _");
+    test_lex!(attr, "#[allow(unused_var)]");
+    test_lex!(str, r#"extern "C" "" "a'b""#);
+    test_lex!(
+        operators,
+        "+ - * / % ! = == != < <= > >= || && & | ^ ~ << >>"
+    );
+    test_lex!(assign_operators, "= += -= *= /= %= &= |= ^= <<= >>=");
+    test_lex!(unknown_char, "a $ b");
+    test_lex!(unterminated_block_comment, "x /* a /* b */");
+    test_lex!(empty_char, "''");
+    test_lex!(long_char, "'ab' 'outer ");
+    test_lex!(missing_digits, "0x 0b2 0");
 }
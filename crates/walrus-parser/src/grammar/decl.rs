@@ -3,7 +3,10 @@ use nom::multi::many0;
 
 pub fn source_file(input: Input) -> IResult<SourceFile> {
     many0(decl)
-        .map(|decls| SourceFile { decls })
+        .map(|decls| SourceFile {
+            decls,
+            lex_errors: Vec::new(),
+        })
         .all_consuming()
         .parse(input)
 }
@@ -15,20 +18,29 @@ pub fn decl(input: Input) -> IResult<Decl> {
         .map(Decl::Fn)
         .or(struct_decl.map(Decl::Struct))
         .or(enum_decl.map(Decl::Enum))
+        .or(impl_decl.map(Decl::Impl))
+        .or(trait_decl.map(Decl::Trait))
+        .or(type_alias_decl.map(Decl::TypeAlias))
+        .or(const_decl.map(Decl::Const))
+        .or(extern_fn_decl.map(Decl::ExternFn))
         .parse(input)
 }
 
 fn fn_decl(input: Input) -> IResult<FnDef> {
+    let (input, attrs) = many0(attr).parse(input)?;
     let (input, kw_fn) = kw_fn.parse(input)?;
     let (input, name) = var.parse(input)?;
+    let (input, generics) = generic_params.opt().parse(input)?;
     let (input, params) = param_list.parse(input)?;
     let (input, ret) = ret_type.opt().parse(input)?;
     let (input, expr) = block_expr.parse(input)?;
     Ok((
         input,
         FnDef {
+            attrs,
             kw_fn,
             name,
+            generics,
             params,
             ret,
             expr,
@@ -36,6 +48,36 @@ fn fn_decl(input: Input) -> IResult<FnDef> {
     ))
 }
 
+fn generic_params(input: Input) -> IResult<GenericParams> {
+    angle(punctuated0(generic_param, comma))
+        .map(GenericParams)
+        .parse(input)
+}
+
+fn generic_param(input: Input) -> IResult<GenericParam> {
+    let (input, name) = var.parse(input)?;
+    let (input, bounds) = bounds.opt().parse(input)?;
+    Ok((input, GenericParam { name, bounds }))
+}
+
+fn bounds(input: Input) -> IResult<Bounds> {
+    let (input, colon) = colon.parse(input)?;
+    let (input, traits) = punctuated1_no_trail(var, plus).parse(input)?;
+    Ok((input, Bounds { colon, traits }))
+}
+
+fn attr(input: Input) -> IResult<Attr> {
+    let (input, pound) = pound.parse(input)?;
+    let (input, body) = square(attr_body).parse(input)?;
+    Ok((input, Attr { pound, body }))
+}
+
+fn attr_body(input: Input) -> IResult<AttrBody> {
+    let (input, name) = var.parse(input)?;
+    let (input, args) = tuple(var).parse(input)?;
+    Ok((input, AttrBody { name, args }))
+}
+
 fn struct_decl(input: Input) -> IResult<StructDef> {
     let (input, kw_struct) = kw_struct.parse(input)?;
     let (input, name) = var.parse(input)?;
@@ -77,6 +119,112 @@ fn enum_variant(input: Input) -> IResult<EnumVariant> {
     Ok((input, EnumVariant { name, fields }))
 }
 
+fn impl_decl(input: Input) -> IResult<ImplDef> {
+    let (input, kw_impl) = kw_impl.parse(input)?;
+    let (input, trait_ref) = trait_ref.opt().parse(input)?;
+    let (input, ty) = ty.parse(input)?;
+    let (input, fns) = curly(many0(fn_decl)).parse(input)?;
+    Ok((
+        input,
+        ImplDef {
+            kw_impl,
+            trait_ref,
+            ty,
+            fns,
+        },
+    ))
+}
+
+fn trait_ref(input: Input) -> IResult<TraitRef> {
+    let (input, name) = var.parse(input)?;
+    let (input, kw_for) = kw_for.parse(input)?;
+    Ok((input, TraitRef { name, kw_for }))
+}
+
+fn trait_decl(input: Input) -> IResult<TraitDef> {
+    let (input, kw_trait) = kw_trait.parse(input)?;
+    let (input, name) = var.parse(input)?;
+    let (input, fns) = curly(many0(fn_sig)).parse(input)?;
+    Ok((
+        input,
+        TraitDef {
+            kw_trait,
+            name,
+            fns,
+        },
+    ))
+}
+
+fn fn_sig(input: Input) -> IResult<FnSig> {
+    let (input, kw_fn) = kw_fn.parse(input)?;
+    let (input, name) = var.parse(input)?;
+    let (input, params) = param_list.parse(input)?;
+    let (input, ret) = ret_type.opt().parse(input)?;
+    let (input, semicolon) = semicolon.parse(input)?;
+    Ok((
+        input,
+        FnSig {
+            kw_fn,
+            name,
+            params,
+            ret,
+            semicolon,
+        },
+    ))
+}
+
+fn type_alias_decl(input: Input) -> IResult<TypeAliasDef> {
+    let (input, kw_type) = kw_type.parse(input)?;
+    let (input, name) = var.parse(input)?;
+    let (input, eq) = eq.parse(input)?;
+    let (input, ty) = ty.parse(input)?;
+    let (input, semicolon) = semicolon.parse(input)?;
+    Ok((
+        input,
+        TypeAliasDef {
+            kw_type,
+            name,
+            eq,
+            ty,
+            semicolon,
+        },
+    ))
+}
+
+fn const_decl(input: Input) -> IResult<ConstDef> {
+    let (input, kw_const) = kw_const.parse(input)?;
+    let (input, name) = var.parse(input)?;
+    let (input, ascription) = ascription.parse(input)?;
+    let (input, eq) = eq.parse(input)?;
+    let (input, expr) = expr.parse(input)?;
+    let (input, semicolon) = semicolon.parse(input)?;
+    Ok((
+        input,
+        ConstDef {
+            kw_const,
+            name,
+            ascription,
+            eq,
+            expr,
+            semicolon,
+        },
+    ))
+}
+
+fn extern_fn_decl(input: Input) -> IResult<ExternFnDef> {
+    let (input, kw_extern) = kw_extern.parse(input)?;
+    let (input, abi) = str.opt().parse(input)?;
+    let (input, sig) = fn_sig.parse(input)?;
+    Ok((
+        input,
+        ExternFnDef {
+            kw_extern,
+            abi,
+            sig,
+        },
+    ))
+}
+
 pub fn param_list(input: Input) -> IResult<ParamList> {
     paren(punctuated0(param, comma)).map(ParamList).parse(input)
 }
@@ -87,6 +235,11 @@ mod tests {
 
     test_parse!(empty_file, source_file, r#""#);
     test_parse!(empty_fn, source_file, r#"fn f() {}"#);
+    test_parse!(
+        generic_fn,
+        source_file,
+        r#"fn max<T: Ord + Eq, U>(a: T, b: T) -> T {}"#
+    );
     test_parse!(empty_struct, source_file, r#"struct Foo {}"#);
     test_parse!(
         struct_with_fields,
@@ -103,4 +256,38 @@ mod tests {
             Some { val: Int }
         }"#
     );
+
+    test_parse!(empty_impl, source_file, r#"impl Foo {}"#);
+    test_parse!(
+        impl_with_fns,
+        source_file,
+        r#"impl Foo {
+            fn new() -> Foo { Foo {} }
+            fn get(self) -> Int { 0 }
+        }"#
+    );
+
+    test_parse!(empty_trait, source_file, r#"trait Foo {}"#);
+    test_parse!(
+        trait_with_fns,
+        source_file,
+        r#"trait Eq {
+            fn eq(self, other: Self) -> Bool;
+            fn ne(self, other: Self) -> Bool;
+        }"#
+    );
+    test_parse!(impl_trait, source_file, r#"impl Eq for Foo {}"#);
+
+    test_parse!(type_alias, source_file, r#"type Point = (Int, Int);"#);
+    test_parse!(const_def, source_file, r#"const MAX: Int = 1 + 2;"#);
+    test_parse!(
+        extern_fn,
+        source_file,
+        r#"extern "C" fn abs(x: Int) -> Int; extern fn exit(status: Int) -> Never;"#
+    );
+    test_parse!(
+        fn_attrs,
+        source_file,
+        r#"#[allow(unused_var, unused_fn)] fn f() {}"#
+    );
 }
@@ -72,20 +72,36 @@ fn return_expr(input: Input) -> IResult<Expr> {
 }
 fn break_expr(input: Input) -> IResult<Expr> {
     let (input, kw_break) = kw_break.parse(input)?;
+    let (input, label) = label.opt().parse(input)?;
     let (input, expr) = expr.opt().parse(input)?;
     Ok((
         input,
         Expr::Break(BreakExpr {
             kw_break,
+            label,
             expr: expr.map(Box::new),
         }),
     ))
 }
 fn continue_expr(input: Input) -> IResult<Expr> {
     let (input, kw_continue) = kw_continue.parse(input)?;
-    Ok((input, Expr::Continue(ContinueExpr { kw_continue })))
+    let (input, label) = label.opt().parse(input)?;
+    Ok((input, Expr::Continue(ContinueExpr { kw_continue, label })))
+}
+fn assign_op(input: Input) -> IResult<Binop> {
+    (eq.map(Binop::Assign))
+        .or(plus_eq.map(Binop::AddAssign))
+        .or(minus_eq.map(Binop::SubAssign))
+        .or(star_eq.map(Binop::MulAssign))
+        .or(slash_eq.map(Binop::DivAssign))
+        .or(percent_eq.map(Binop::RemAssign))
+        .or(and_eq.map(Binop::BitAndAssign))
+        .or(or_eq.map(Binop::BitOrAssign))
+        .or(caret_eq.map(Binop::BitXorAssign))
+        .or(less_less_eq.map(Binop::ShlAssign))
+        .or(greater_greater_eq.map(Binop::ShrAssign))
+        .parse(input)
 }
-fn assign_op(input: Input) -> IResult<Binop> { eq.map(Binop::Assign).parse(input) }
 fn assign_expr(input: Input) -> IResult<Expr> {
     let (input, lhs) = or_expr.parse(input)?;
     let (input, op) = assign_op.parse(input)?;
@@ -102,7 +118,7 @@ fn assign_expr(input: Input) -> IResult<Expr> {
 fn or_op(input: Input) -> IResult<Binop> { or_or.map(Binop::Or).parse(input) }
 fn or_expr(input: Input) -> IResult<Expr> {
     let (input, init) = and_expr.parse(input)?;
-    fold_many0(pair(or_op, add_expr), init, |lhs, (op, rhs)| {
+    fold_many0(pair(or_op, and_expr), init, |lhs, (op, rhs)| {
         Expr::Binary(BinaryExpr {
             lhs: box lhs,
             op,
@@ -114,7 +130,7 @@ fn or_expr(input: Input) -> IResult<Expr> {
 fn and_op(input: Input) -> IResult<Binop> { and_and.map(Binop::And).parse(input) }
 fn and_expr(input: Input) -> IResult<Expr> {
     let (input, init) = cmp_expr.parse(input)?;
-    fold_many0(pair(and_op, add_expr), init, |lhs, (op, rhs)| {
+    fold_many0(pair(and_op, cmp_expr), init, |lhs, (op, rhs)| {
         Expr::Binary(BinaryExpr {
             lhs: box lhs,
             op,
@@ -133,8 +149,60 @@ fn cmp_op(input: Input) -> IResult<Binop> {
         .parse(input)
 }
 fn cmp_expr(input: Input) -> IResult<Expr> {
+    let (input, init) = bit_or_expr.parse(input)?;
+    fold_many0(pair(cmp_op, bit_or_expr), init, |lhs, (op, rhs)| {
+        Expr::Binary(BinaryExpr {
+            lhs: box lhs,
+            op,
+            rhs: box rhs,
+        })
+    })
+    .parse(input)
+}
+fn bit_or_op(input: Input) -> IResult<Binop> { or.map(Binop::BitOr).parse(input) }
+fn bit_or_expr(input: Input) -> IResult<Expr> {
+    let (input, init) = bit_xor_expr.parse(input)?;
+    fold_many0(pair(bit_or_op, bit_xor_expr), init, |lhs, (op, rhs)| {
+        Expr::Binary(BinaryExpr {
+            lhs: box lhs,
+            op,
+            rhs: box rhs,
+        })
+    })
+    .parse(input)
+}
+fn bit_xor_op(input: Input) -> IResult<Binop> { caret.map(Binop::BitXor).parse(input) }
+fn bit_xor_expr(input: Input) -> IResult<Expr> {
+    let (input, init) = bit_and_expr.parse(input)?;
+    fold_many0(pair(bit_xor_op, bit_and_expr), init, |lhs, (op, rhs)| {
+        Expr::Binary(BinaryExpr {
+            lhs: box lhs,
+            op,
+            rhs: box rhs,
+        })
+    })
+    .parse(input)
+}
+fn bit_and_op(input: Input) -> IResult<Binop> { and.map(Binop::BitAnd).parse(input) }
+fn bit_and_expr(input: Input) -> IResult<Expr> {
+    let (input, init) = shift_expr.parse(input)?;
+    fold_many0(pair(bit_and_op, shift_expr), init, |lhs, (op, rhs)| {
+        Expr::Binary(BinaryExpr {
+            lhs: box lhs,
+            op,
+            rhs: box rhs,
+        })
+    })
+    .parse(input)
+}
+fn shift_op(input: Input) -> IResult<Binop> {
+    (less_less.map(Binop::Shl))
+        .or(greater_greater.map(Binop::Shr))
+        .parse(input)
+}
+fn shift_expr(input: Input) -> IResult<Expr> {
     let (input, init) = add_expr.parse(input)?;
-    fold_many0(pair(cmp_op, add_expr), init, |lhs, (op, rhs)| {
+    fold_many0(pair(shift_op, add_expr), init, |lhs, (op, rhs)| {
         Expr::Binary(BinaryExpr {
             lhs: box lhs,
             op,
@@ -162,11 +230,12 @@ fn add_expr(input: Input) -> IResult<Expr> {
 fn mul_op(input: Input) -> IResult<Binop> {
     (star.map(Binop::Mul))
         .or(slash.map(Binop::Div))
+        .or(percent.map(Binop::Rem))
         .parse(input)
 }
 fn mul_expr(input: Input) -> IResult<Expr> {
-    let (input, init) = unary_expr.parse(input)?;
-    fold_many0(pair(mul_op, unary_expr), init, |lhs, (op, rhs)| {
+    let (input, init) = cast_expr.parse(input)?;
+    fold_many0(pair(mul_op, cast_expr), init, |lhs, (op, rhs)| {
         Expr::Binary(BinaryExpr {
             lhs: box lhs,
             op,
@@ -175,10 +244,22 @@ fn mul_expr(input: Input) -> IResult<Expr> {
     })
     .parse(input)
 }
+fn cast_expr(input: Input) -> IResult<Expr> {
+    let (input, init) = unary_expr.parse(input)?;
+    fold_many0(pair(kw_as, ty), init, |expr, (kw_as, ty)| {
+        Expr::Cast(CastExpr {
+            expr: box expr,
+            kw_as,
+            ty,
+        })
+    })
+    .parse(input)
+}
 fn unary_op(input: Input) -> IResult<Unop> {
     bang.map(Unop::Not)
         .or(plus.map(Unop::Add))
         .or(minus.map(Unop::Sub))
+        .or(tilde.map(Unop::BitNot))
         .parse(input)
 }
 fn unary_expr(input: Input) -> IResult<Expr> {
@@ -189,6 +270,7 @@ fn unary_expr(input: Input) -> IResult<Expr> {
 }
 enum Suffix {
     Call(ArgList),
+    Method(Dot, Var, ArgList),
     Field(Dot, Field),
 }
 fn arg_list(input: Input) -> IResult<ArgList> {
@@ -199,8 +281,15 @@ fn field(input: Input) -> IResult<Field> {
         .or(var.map(Field::Named))
         .parse(input)
 }
+fn method_suffix(input: Input) -> IResult<Suffix> {
+    let (input, dot) = dot.parse(input)?;
+    let (input, method) = var.parse(input)?;
+    let (input, args) = arg_list.parse(input)?;
+    Ok((input, Suffix::Method(dot, method, args)))
+}
 fn suffix(input: Input) -> IResult<Suffix> {
     (arg_list.map(Suffix::Call))
+        .or(method_suffix)
         .or(pair(dot, field).map(|(dot, field)| Suffix::Field(dot, field)))
         .parse(input)
 }
@@ -211,6 +300,12 @@ fn suffix_expr(input: Input) -> IResult<Expr> {
             func: box expr,
             args,
         }),
+        Suffix::Method(dot, method, args) => Expr::MethodCall(MethodCallExpr {
+            receiver: box expr,
+            dot,
+            method,
+            args,
+        }),
         Suffix::Field(dot, field) => Expr::Field(FieldExpr {
             base: box expr,
             dot,
@@ -221,16 +316,33 @@ fn suffix_expr(input: Input) -> IResult<Expr> {
 }
 fn atom_expr(input: Input) -> IResult<Expr> {
     lit_expr
+        .or(path_expr)
         .or(var_expr)
         .or(paren_expr)
         .or(tuple_expr)
         .or(loop_expr)
+        .or(while_expr)
+        .or(for_expr)
         .or(if_expr)
+        .or(match_expr)
         .or(block_expr)
         .parse(input)
 }
 fn lit_expr(input: Input) -> IResult<Expr> { lit.map(Expr::Lit).parse(input) }
 fn var_expr(input: Input) -> IResult<Expr> { var.map(Expr::Var).parse(input) }
+fn path_expr(input: Input) -> IResult<Expr> {
+    let (input, ty) = var.parse(input)?;
+    let (input, colon_colon) = colon_colon.parse(input)?;
+    let (input, name) = var.parse(input)?;
+    Ok((
+        input,
+        Expr::Path(PathExpr {
+            ty,
+            colon_colon,
+            name,
+        }),
+    ))
+}
 fn paren_expr(input: Input) -> IResult<Expr> { paren(expr).map(Expr::Paren).parse(input) }
 fn tuple_expr(input: Input) -> IResult<Expr> { tuple(expr).map(Expr::Tuple).parse(input) }
 fn if_expr(input: Input) -> IResult<Expr> {
@@ -260,17 +372,88 @@ fn else_expr(input: Input) -> IResult<ElseExpr> {
     }))
     .parse(input)
 }
+fn match_expr(input: Input) -> IResult<Expr> {
+    let (input, kw_match) = kw_match.parse(input)?;
+    let (input, test_expr) = expr_no_struct.parse(input)?;
+    let (input, arms) = curly(punctuated0(match_arm, comma)).parse(input)?;
+    Ok((
+        input,
+        Expr::Match(MatchExpr {
+            kw_match,
+            test_expr: box test_expr,
+            arms,
+        }),
+    ))
+}
+fn match_arm(input: Input) -> IResult<MatchArm> {
+    let (input, pat) = pat.parse(input)?;
+    let (input, fat_arrow) = fat_arrow.parse(input)?;
+    let (input, expr) = expr.parse(input)?;
+    Ok((
+        input,
+        MatchArm {
+            pat,
+            fat_arrow,
+            expr,
+        },
+    ))
+}
+fn label_decl(input: Input) -> IResult<LabelDecl> {
+    let (input, label) = label.parse(input)?;
+    let (input, colon) = colon.parse(input)?;
+    Ok((input, LabelDecl { label, colon }))
+}
 fn loop_expr(input: Input) -> IResult<Expr> {
+    let (input, label) = label_decl.opt().parse(input)?;
     let (input, kw_loop) = kw_loop.parse(input)?;
     let (input, expr) = block_expr.parse(input)?;
     Ok((
         input,
         Expr::Loop(LoopExpr {
+            label,
             kw_loop,
             expr: box expr,
         }),
     ))
 }
+fn while_expr(input: Input) -> IResult<Expr> {
+    let (input, label) = label_decl.opt().parse(input)?;
+    let (input, kw_while) = kw_while.parse(input)?;
+    let (input, test_expr) = expr_no_struct.parse(input)?;
+    let (input, expr) = block_expr.parse(input)?;
+    Ok((
+        input,
+        Expr::While(WhileExpr {
+            label,
+            kw_while,
+            test_expr: box test_expr,
+            expr: box expr,
+        }),
+    ))
+}
+fn for_expr(input: Input) -> IResult<Expr> {
+    let (input, label) = label_decl.opt().parse(input)?;
+    let (input, kw_for) = kw_for.parse(input)?;
+    let (input, pat) = pat.parse(input)?;
+    let (input, kw_in) = kw_in.parse(input)?;
+    let (input, start) = expr_no_struct.parse(input)?;
+    let (input, dot_dot) = dot_dot.parse(input)?;
+    let (input, end) = expr_no_struct.parse(input)?;
+    let (input, expr) = block_expr.parse(input)?;
+    Ok((
+        input,
+        Expr::For(ForExpr {
+            label,
+            kw_for,
+            pat,
+            kw_in,
+            start: box start,
+            dot_dot,
+            end: box end,
+            expr: box expr,
+        }),
+    ))
+}
 pub fn block(input: Input) -> IResult<Block> {
     let (input, lcurly) = lcurly.parse(input)?;
     let (input, stmts) = many0(stmt).parse(input)?;
@@ -306,7 +489,13 @@ fn stmt(input: Input) -> IResult<Stmt> {
         .parse(input)
 }
 fn blocklike_expr_stmt(input: Input) -> IResult<Stmt> {
-    let (input, expr) = if_expr.or(loop_expr).or(block_expr).parse(input)?;
+    let (input, expr) = if_expr
+        .or(match_expr)
+        .or(loop_expr)
+        .or(while_expr)
+        .or(for_expr)
+        .or(block_expr)
+        .parse(input)?;
     Ok((
         input,
         Stmt::Expr {
@@ -356,10 +545,17 @@ mod tests {
     test_parse!(int_expr, expr, r#"123"#);
     test_parse!(assign_expr, expr, r#"1=2"#);
     test_parse!(nested_assign_expr, expr, r#"1=2=3"#);
+    test_parse!(compound_assign_expr, expr, r#"x += 1 + 2"#);
+    test_parse!(nested_compound_assign_expr, expr, r#"x <<= y -= 3"#);
     test_parse!(add_expr, expr, r#"1+2"#);
     test_parse!(nested_add_expr, expr, r#"1+2+3"#);
     test_parse!(mul_expr, expr, r#"1*2"#);
     test_parse!(nested_mul_expr, expr, r#"1*2*3"#);
+    test_parse!(rem_expr, expr, r#"1%2"#);
+    test_parse!(bit_not_expr, expr, r#"~1"#);
+    test_parse!(bit_expr, expr, r#"1 | 2 ^ 3 & 4 << 5 + 6"#);
+    test_parse!(shift_expr, expr, r#"1 >> 2 << 3"#);
+    test_parse!(lazy_expr, expr, r#"a || b && c == d"#);
     test_parse!(prefix_expr, expr, r#"-2"#);
     test_parse!(nested_prefix_expr, expr, r#"--2"#);
     test_parse!(call_expr, expr, r#"f()"#);
@@ -367,13 +563,32 @@ mod tests {
     test_parse!(tuple_field_expr, expr, r#"x.0"#);
     test_parse!(named_field_expr, expr, r#"x.y"#);
     test_parse!(nested_field_expr, expr, r#"x._0._0"#);
+    test_parse!(method_call_expr, expr, r#"x.f(1, 2)"#);
+    test_parse!(tuple_field_call_expr, expr, r#"x.0()"#);
+    test_parse!(paren_field_call_expr, expr, r#"(x.f)()"#);
+    test_parse!(path_expr, expr, r#"Foo::new"#);
+    test_parse!(path_call_expr, expr, r#"Foo::new(1)"#);
     test_parse!(mixed_expr, expr, r#"-1+2"#);
+    test_parse!(cast_expr, expr, r#"x as F64"#);
+    test_parse!(nested_cast_expr, expr, r#"-x as Int as Float * 2"#);
     test_parse!(lambda_expr, expr, r#"() => 1"#);
     test_parse!(nested_lambda_expr, expr, r#"(x) => (_) => x"#);
     test_parse!(if_expr, expr, r#"if true {}"#);
     test_parse!(if_else_expr, expr, r#"if true {} else {}"#);
     test_parse!(if_else_if_expr, expr, r#"if true {} else if false {}"#);
+    test_parse!(match_expr, expr, r#"match x {}"#);
+    test_parse!(
+        match_arms_expr,
+        expr,
+        r#"match xs { List::Nil {} => 0, List::Cons {head: x, tail: _} => x, }"#
+    );
     test_parse!(loop_expr, expr, r#"loop {}"#);
+    test_parse!(while_expr, expr, r#"while x < 10 {x += 1}"#);
+    test_parse!(for_expr, expr, r#"for i in 0..n {f(i)}"#);
+    test_parse!(for_mut_expr, expr, r#"for mut i in 0..10 {}"#);
+    test_parse!(labeled_loop_expr, expr, r#"'a: loop { break 'a 5 }"#);
+    test_parse!(labeled_while_expr, expr, r#"'a: while true { continue 'a }"#);
+    test_parse!(labeled_for_expr, expr, r#"'a: for i in 0..1 { break 'a }"#);
     test_parse!(return_expr, expr, r#"return 5"#);
     test_parse!(break_expr, expr, r#"break 5"#);
     test_parse!(continue_expr, expr, r#"continue"#);
@@ -6,13 +6,18 @@ use super::*;
 /// BinInt_ ::= '[0-1_]'
 /// HexInt ::= '[0-9a-fA-F]'
 /// HexInt_ ::= '[0-9a-fA-F_]'
-/// DecInt ::= DecDigit DecDigit_*
-/// BinInt ::= BinDigit BinDigit_*
-/// HexInt ::= HexDigit HexDigit_*
-/// Float  ::= DecInt "." DecInt
+/// IntSuffix ::= '[iu](8|16|32|64)'
+/// FloatSuffix ::= 'f(32|64)'
+/// Exponent ::= '[eE][+-]?' DecInt
+/// DecInt ::= DecDigit DecDigit_* IntSuffix?
+/// BinInt ::= BinDigit BinDigit_* IntSuffix?
+/// HexInt ::= HexDigit HexDigit_* IntSuffix?
+/// Float  ::= DecInt "." DecInt? Exponent? FloatSuffix?
+///          | DecInt Exponent FloatSuffix?
+///          | DecInt FloatSuffix
 /// SimpleChar ::= '[^']'
-/// EscapedChar ::= '\\.'
-/// UnicodeChar ::= '\\u HexInt HexInt? HexInt? HexInt?'
+/// EscapedChar ::= '\\.' | '\\x HexDigit HexDigit'
+/// UnicodeChar ::= '\\u{ HexDigit (_* HexDigit){0,5} _* }'
 pub fn lit(input: Input) -> IResult<Lit> {
     bool_lit
         .map(Lit::Bool)
@@ -50,7 +55,9 @@ mod tests {
     test_parse!(bin_int_lit, lit, "0b1010");
     test_parse!(hex_int_lit, lit, "0x123abcdef");
     test_parse!(float_lit, lit, "1234.56789");
+    test_parse!(suffixed_int_lit, lit, "0xFFu8");
+    test_parse!(exponent_float_lit, lit, "2.5e-3f64");
     test_parse!(simple_char_lit, lit, "'a'");
     test_parse!(escaped_char_lit, lit, r"'\a'");
-    test_parse!(unicode_char_lit, lit, r"'\u0a'");
+    test_parse!(unicode_char_lit, lit, r"'\u{1F600}'");
 }
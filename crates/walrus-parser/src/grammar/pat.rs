@@ -1,7 +1,8 @@
 use super::*;
 
 pub fn pat(input: Input) -> IResult<Pat> {
-    var_pat
+    enum_pat
+        .or(var_pat)
         .or(ignore_pat)
         .or(paren_pat)
         .or(tuple_pat)
@@ -14,7 +15,32 @@ pub fn param(input: Input) -> IResult<Param> {
     Ok((input, Param { pat, ascription }))
 }
 
-fn var_pat(input: Input) -> IResult<Pat> { var.map(Pat::Var).parse(input) }
+fn var_pat(input: Input) -> IResult<Pat> {
+    let (input, kw_mut) = kw_mut.opt().parse(input)?;
+    let (input, var) = var.parse(input)?;
+    Ok((input, Pat::Var { kw_mut, var }))
+}
+fn enum_pat(input: Input) -> IResult<Pat> {
+    let (input, name) = var.parse(input)?;
+    let (input, colon_colon) = colon_colon.parse(input)?;
+    let (input, variant) = var.parse(input)?;
+    let (input, fields) = curly(punctuated0(field_pat, comma)).parse(input)?;
+    Ok((
+        input,
+        Pat::Enum(EnumPat {
+            name,
+            colon_colon,
+            variant,
+            fields,
+        }),
+    ))
+}
+fn field_pat(input: Input) -> IResult<FieldPat> {
+    let (input, name) = var.parse(input)?;
+    let (input, colon) = colon.parse(input)?;
+    let (input, pat) = pat.parse(input)?;
+    Ok((input, FieldPat { name, colon, pat }))
+}
 fn ignore_pat(input: Input) -> IResult<Pat> { underscore.map(Pat::Ignore).parse(input) }
 fn paren_pat(input: Input) -> IResult<Pat> { paren(pat).map(Pat::Paren).parse(input) }
 fn tuple_pat(input: Input) -> IResult<Pat> { tuple(pat).map(Pat::Tuple).parse(input) }
@@ -24,9 +50,13 @@ mod tests {
     use super::*;
 
     test_parse!(var_pat, pat, r#"a"#);
+    test_parse!(mut_var_pat, pat, r#"mut a"#);
     test_parse!(ignore_pat, pat, r#"_"#);
     test_parse!(tuple0_pat, pat, r#"()"#);
     test_parse!(tuple1_pat, pat, r#"(x,)"#);
     test_parse!(tuple2_pat, pat, r#"(x,y)"#);
+    test_parse!(mut_tuple_pat, pat, r#"(mut x, y)"#);
     test_parse!(paren_pat, pat, r#"(x)"#);
+    test_parse!(enum_pat, pat, r#"List::Cons {head: x, tail: _}"#);
+    test_parse!(nested_enum_pat, pat, r#"Foo::Bar {x: Foo::Baz {}}"#);
 }
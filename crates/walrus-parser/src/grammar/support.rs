@@ -22,6 +22,20 @@ where
     delimited(lcurly, inner, rcurly)
 }
 
+pub fn square<'a, InnerP, Inner>(inner: InnerP) -> impl Parser<Input<'a>, Square<Inner>, Err>
+where
+    InnerP: Parser<Input<'a>, Inner, Err>,
+{
+    delimited(lsquare, inner, rsquare)
+}
+
+pub fn angle<'a, InnerP, Inner>(inner: InnerP) -> impl Parser<Input<'a>, Angle<Inner>, Err>
+where
+    InnerP: Parser<Input<'a>, Inner, Err>,
+{
+    delimited(less, inner, greater)
+}
+
 pub const fn delimited<Open, Inner, Close>(
     open: Open,
     inner: Inner,
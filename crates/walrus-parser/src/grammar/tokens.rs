@@ -17,20 +17,32 @@ macro_rules! token_parser {
     };
 }
 
+token_parser!(kw_as, KwAs);
 token_parser!(kw_break, KwBreak);
+token_parser!(kw_const, KwConst);
 token_parser!(kw_continue, KwContinue);
 token_parser!(kw_else, KwElse);
 token_parser!(kw_enum, KwEnum);
+token_parser!(kw_extern, KwExtern);
 token_parser!(kw_false, KwFalse);
 token_parser!(kw_fn, KwFn);
+token_parser!(kw_for, KwFor);
 token_parser!(kw_if, KwIf);
+token_parser!(kw_impl, KwImpl);
+token_parser!(kw_in, KwIn);
 token_parser!(kw_let, KwLet);
 token_parser!(kw_loop, KwLoop);
+token_parser!(kw_match, KwMatch);
+token_parser!(kw_mut, KwMut);
 token_parser!(kw_return, KwReturn);
 token_parser!(kw_struct, KwStruct);
+token_parser!(kw_trait, KwTrait);
 token_parser!(kw_true, KwTrue);
+token_parser!(kw_type, KwType);
+token_parser!(kw_while, KwWhile);
 
 token_parser!(ident, Ident);
+token_parser!(label, Label);
 token_parser!(dec_int, DecInt);
 token_parser!(bin_int, BinInt);
 token_parser!(hex_int, HexInt);
@@ -39,20 +51,25 @@ token_parser!(float, Float);
 token_parser!(simple_char, SimpleChar);
 token_parser!(escaped_char, EscapedChar);
 token_parser!(unicode_char, UnicodeChar);
+token_parser!(str, Str);
 
 token_parser!(lparen, LParen);
 token_parser!(rparen, RParen);
 token_parser!(lcurly, LCurly);
 token_parser!(rcurly, RCurly);
+token_parser!(lsquare, LSquare);
+token_parser!(rsquare, RSquare);
 token_parser!(colon, Colon);
 token_parser!(colon_colon, ColonColon);
 token_parser!(comma, Comma);
 token_parser!(dot, Dot);
+token_parser!(dot_dot, DotDot);
 token_parser!(eq, Eq);
 token_parser!(fat_arrow, FatArrow);
 token_parser!(semicolon, Semicolon);
 token_parser!(thin_arrow, ThinArrow);
 token_parser!(underscore, Underscore);
+token_parser!(pound, Pound);
 
 token_parser!(bang, Bang);
 token_parser!(and_and, AndAnd);
@@ -62,9 +79,26 @@ token_parser!(plus, Plus);
 token_parser!(minus, Minus);
 token_parser!(star, Star);
 token_parser!(slash, Slash);
+token_parser!(percent, Percent);
+token_parser!(and, And);
+token_parser!(or, Or);
+token_parser!(caret, Caret);
+token_parser!(tilde, Tilde);
+token_parser!(less_less, LessLess);
+token_parser!(greater_greater, GreaterGreater);
 token_parser!(eq_eq, EqEq);
 token_parser!(bang_eq, BangEq);
 token_parser!(less, Less);
 token_parser!(less_eq, LessEq);
 token_parser!(greater, Greater);
 token_parser!(greater_eq, GreaterEq);
+token_parser!(plus_eq, PlusEq);
+token_parser!(minus_eq, MinusEq);
+token_parser!(star_eq, StarEq);
+token_parser!(slash_eq, SlashEq);
+token_parser!(percent_eq, PercentEq);
+token_parser!(and_eq, AndEq);
+token_parser!(or_eq, OrEq);
+token_parser!(caret_eq, CaretEq);
+token_parser!(less_less_eq, LessLessEq);
+token_parser!(greater_greater_eq, GreaterGreaterEq);
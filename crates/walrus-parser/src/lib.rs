@@ -23,16 +23,23 @@
 mod grammar;
 
 use nom::Parser;
+use walrus_lexer::TokenKind;
 use walrus_syntax::SourceFile;
 
 pub fn parse(src: &str) -> SourceFile {
+    let mut lex_errors = Vec::new();
     let tokens = walrus_lexer::lex(src)
-        .filter(|token| !token.kind.is_trivia())
-        .collect::<Vec<_>>()
-        .clone();
+        .filter(|token| {
+            // `Error` tokens are reported in `SourceFile::lex_errors`
+            lex_errors.extend(token.lex_error());
+            !token.kind.is_trivia() && token.kind != TokenKind::Error
+        })
+        .collect::<Vec<_>>();
+    lex_errors.extend(walrus_lexer::source_too_long(src));
 
-    let (_input, source_file) = crate::grammar::source_file
+    let (_input, mut source_file) = crate::grammar::source_file
         .parse(&tokens)
         .expect("Parser should not fail");
+    source_file.lex_errors = lex_errors;
     source_file
 }
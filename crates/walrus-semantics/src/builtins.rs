@@ -1,10 +1,14 @@
-use crate::{hir::Var, ty::Type};
+use crate::{
+    hir::{CmpBinop, Var},
+    ty::{FnType, Type},
+};
 use std::fmt;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum BuiltinKind {
     Type,
     Value,
+    Trait,
 }
 
 macro_rules! builtins {
@@ -67,11 +71,51 @@ builtins! {
         kind: BuiltinKind::Type,
         ty: Type::INT,
     },
+    I8 {
+        name: "I8",
+        kind: BuiltinKind::Type,
+        ty: Type::I8,
+    },
+    I16 {
+        name: "I16",
+        kind: BuiltinKind::Type,
+        ty: Type::I16,
+    },
+    I64 {
+        name: "I64",
+        kind: BuiltinKind::Type,
+        ty: Type::I64,
+    },
+    U8 {
+        name: "U8",
+        kind: BuiltinKind::Type,
+        ty: Type::U8,
+    },
+    U16 {
+        name: "U16",
+        kind: BuiltinKind::Type,
+        ty: Type::U16,
+    },
+    U32 {
+        name: "U32",
+        kind: BuiltinKind::Type,
+        ty: Type::U32,
+    },
+    U64 {
+        name: "U64",
+        kind: BuiltinKind::Type,
+        ty: Type::U64,
+    },
     Float {
         name: "Float",
         kind: BuiltinKind::Type,
         ty: Type::FLOAT,
     },
+    F64 {
+        name: "F64",
+        kind: BuiltinKind::Type,
+        ty: Type::F64,
+    },
     Char {
         name: "Char",
         kind: BuiltinKind::Type,
@@ -91,6 +135,102 @@ builtins! {
         name: "putchar",
         kind: BuiltinKind::Value,
         ty: Type::function(vec![Type::CHAR], Type::CHAR),
+    },
+    PrintChar {
+        name: "print_char",
+        kind: BuiltinKind::Value,
+        ty: Type::function(vec![Type::CHAR], Type::UNIT),
+    },
+    Eq {
+        name: "Eq",
+        kind: BuiltinKind::Trait,
+        ty: Type::Unknown,
+    },
+    Ord {
+        name: "Ord",
+        kind: BuiltinKind::Trait,
+        ty: Type::Unknown,
+    },
+    Add {
+        name: "Add",
+        kind: BuiltinKind::Trait,
+        ty: Type::Unknown,
+    },
+    Sub {
+        name: "Sub",
+        kind: BuiltinKind::Trait,
+        ty: Type::Unknown,
+    },
+    Mul {
+        name: "Mul",
+        kind: BuiltinKind::Trait,
+        ty: Type::Unknown,
+    },
+    Div {
+        name: "Div",
+        kind: BuiltinKind::Trait,
+        ty: Type::Unknown,
+    },
+    Rem {
+        name: "Rem",
+        kind: BuiltinKind::Trait,
+        ty: Type::Unknown,
+    },
+    Neg {
+        name: "Neg",
+        kind: BuiltinKind::Trait,
+        ty: Type::Unknown,
+    }
+}
+
+impl Builtin {
+    /// The builtin type that `ty` is, if any.
+    pub fn of_type(ty: &Type) -> Option<Self> {
+        Self::all()
+            .iter()
+            .copied()
+            .find(|builtin| builtin.kind() == BuiltinKind::Type && &builtin.ty() == ty)
+    }
+
+    /// The name of the only method of a builtin trait.
+    pub const fn trait_method(self) -> Option<&'static str> {
+        let name = match self {
+            Self::Eq => "eq",
+            Self::Ord => "lt",
+            Self::Add => "add",
+            Self::Sub => "sub",
+            Self::Mul => "mul",
+            Self::Div => "div",
+            Self::Rem => "rem",
+            Self::Neg => "neg",
+            _ => return None,
+        };
+        Some(name)
+    }
+
+    /// The type of the only method of a builtin trait implemented for
+    /// `self_ty`: `fn(self, other: Self) -> Bool` for the comparisons,
+    /// `fn(self, other: Self) -> Self` for the binary arithmetic operators and
+    /// `fn(self) -> Self` for negation.
+    pub fn trait_method_type(self, self_ty: &Type) -> Option<FnType> {
+        let (params, ret) = match self {
+            Self::Eq | Self::Ord => (vec![self_ty.clone(), self_ty.clone()], Type::BOOL),
+            Self::Add | Self::Sub | Self::Mul | Self::Div | Self::Rem => {
+                (vec![self_ty.clone(), self_ty.clone()], self_ty.clone())
+            }
+            Self::Neg => (vec![self_ty.clone()], self_ty.clone()),
+            _ => return None,
+        };
+        Some(FnType { params, ret })
+    }
+
+    /// The comparison that the scalar types implement `Eq` and `Ord` with.
+    pub const fn builtin_cmp(self) -> Option<CmpBinop> {
+        match self {
+            Self::Eq => Some(CmpBinop::Eq),
+            Self::Ord => Some(CmpBinop::Less),
+            _ => None,
+        }
     }
 }
 
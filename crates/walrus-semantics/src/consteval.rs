@@ -0,0 +1,447 @@
+//! Evaluating `const` items at compile time. Only literals, other consts,
+//! tuples, struct literals, field accesses and operators on numbers, bools
+//! and chars are allowed in a `const`.
+
+#![allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_possible_wrap,
+    clippy::cast_sign_loss
+)]
+
+use crate::{
+    diagnostic::Diagnostic,
+    hir::*,
+    scopes::{Denotation, Scopes},
+    ty::{Ctor, InferenceResult, Type},
+};
+use arena::ArenaMap;
+use ordered_float::OrderedFloat;
+use std::{cmp::Ordering, ops::Index};
+
+pub fn eval_consts(module: &Module, scopes: &Scopes, types: &InferenceResult) -> ConstValues {
+    let mut ctx = Ctx {
+        module,
+        scopes,
+        types,
+        values: ArenaMap::default(),
+        in_progress: Vec::new(),
+        diagnostics: Vec::new(),
+    };
+    for (id, _) in module.data.const_defs.iter() {
+        ctx.eval_const(id);
+    }
+    let mut values = ArenaMap::default();
+    for (id, value) in ctx.values.iter() {
+        if let Some(value) = value {
+            values.insert(id, value.clone());
+        }
+    }
+    ConstValues {
+        values,
+        diagnostics: ctx.diagnostics,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ConstValues {
+    /// The value of every `const` that could be evaluated.
+    pub values: ArenaMap<ConstDefId, ConstValue>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Index<ConstDefId> for ConstValues {
+    type Output = ConstValue;
+    fn index(&self, id: ConstDefId) -> &Self::Output { &self.values[id] }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstValue {
+    Bool(bool),
+    /// An integer of any width, already checked to fit in its type.
+    Int(i128),
+    /// A `Float` is rounded to `f32` after every operation.
+    Float(OrderedFloat<f64>),
+    Char(char),
+    Tuple(Vec<Self>),
+    /// The fields are in the order they are defined in the struct.
+    Struct(StructDefId, Vec<Self>),
+}
+
+struct Ctx<'a> {
+    module: &'a Module,
+    scopes: &'a Scopes,
+    types: &'a InferenceResult,
+    /// `None` for consts that could not be evaluated, which have already
+    /// been reported.
+    values: ArenaMap<ConstDefId, Option<ConstValue>>,
+    /// The consts being evaluated, innermost last.
+    in_progress: Vec<ConstDefId>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Ctx<'_> {
+    fn eval_const(&mut self, id: ConstDefId) -> Option<ConstValue> {
+        if let Some(value) = self.values.get(id) {
+            return value.clone();
+        }
+        if self.in_progress.contains(&id) {
+            self.diagnostics.push(Diagnostic::CyclicConst(id));
+            return None;
+        }
+        self.in_progress.push(id);
+        let value = self.eval_expr(self.module.data[id].expr);
+        self.in_progress.pop();
+        self.values.insert(id, value.clone());
+        value
+    }
+
+    fn eval_expr(&mut self, id: ExprId) -> Option<ConstValue> {
+        let ctor = *self.types[id].ctor()?;
+        match &self.module.data[id] {
+            Expr::Lit(lit) => self.report_lit(id, eval_lit(ctor, *lit)),
+            Expr::Var(var) => match self.scopes.lookup_expr(id, &self.module.data[*var]) {
+                Some(Denotation::Const(const_id)) => self.eval_const(const_id),
+                _ => self.report(id, Err(EvalError::NotConst)),
+            },
+            Expr::Tuple(exprs) => exprs
+                .iter()
+                .map(|expr| self.eval_expr(*expr))
+                .collect::<Option<_>>()
+                .map(ConstValue::Tuple),
+            Expr::Struct { fields, .. } => {
+                let struct_id = self.types[id].as_struct()?;
+                let struct_def = &self.module.data[struct_id];
+                struct_def
+                    .fields
+                    .iter()
+                    .map(|field_def| {
+                        let name = &self.module.data[field_def.name];
+                        let field = fields
+                            .iter()
+                            .find(|field| &self.module.data[field.name] == name)?;
+                        self.eval_expr(field.val)
+                    })
+                    .collect::<Option<_>>()
+                    .map(|fields| ConstValue::Struct(struct_id, fields))
+            }
+            Expr::Field { expr, field } => {
+                let idx = field_index(&self.module.data, &self.types[*expr], *field)?;
+                match self.eval_expr(*expr)? {
+                    ConstValue::Tuple(mut fields) | ConstValue::Struct(_, mut fields) => {
+                        Some(fields.swap_remove(idx))
+                    }
+                    _ => None,
+                }
+            }
+            Expr::Unop { op, expr, .. } => match (op, &self.module.data[*expr]) {
+                (Unop::Sub, Expr::Lit(Lit::Int(i, _))) => {
+                    self.report_lit(id, eval_neg_lit(ctor, *i))
+                }
+                _ => {
+                    let value = self.eval_expr(*expr)?;
+                    self.report(id, eval_unop(ctor, *op, value))
+                }
+            },
+            Expr::Binop {
+                lhs,
+                op: Binop::Lazy(op),
+                rhs,
+                ..
+            } => match (self.eval_expr(*lhs)?, op) {
+                (ConstValue::Bool(true), LazyBinop::Or) => Some(ConstValue::Bool(true)),
+                (ConstValue::Bool(false), LazyBinop::And) => Some(ConstValue::Bool(false)),
+                (ConstValue::Bool(_), _) => self.eval_expr(*rhs),
+                _ => None,
+            },
+            Expr::Binop { lhs, op, rhs, .. } => {
+                let lhs_value = self.eval_expr(*lhs)?;
+                let rhs_value = self.eval_expr(*rhs)?;
+                self.report(id, eval_binop(ctor, lhs_value, *op, rhs_value))
+            }
+            Expr::Block {
+                stmts,
+                expr: Some(expr),
+            } if stmts.is_empty() => self.eval_expr(*expr),
+            _ => self.report(id, Err(EvalError::NotConst)),
+        }
+    }
+
+    /// Like `report`, but a literal out of range for its type has already
+    /// been reported by inference.
+    fn report_lit(
+        &mut self,
+        id: ExprId,
+        result: Result<ConstValue, EvalError>,
+    ) -> Option<ConstValue> {
+        match result {
+            Err(EvalError::Overflow) => None,
+            result => self.report(id, result),
+        }
+    }
+
+    /// Records the diagnostic for a failed evaluation of `id`.
+    fn report(&mut self, id: ExprId, result: Result<ConstValue, EvalError>) -> Option<ConstValue> {
+        match result {
+            Ok(value) => Some(value),
+            Err(error) => {
+                self.diagnostics.push(match error {
+                    EvalError::NotConst => Diagnostic::NotConst(id),
+                    EvalError::Overflow => Diagnostic::ConstOverflow(id),
+                    EvalError::DivByZero => Diagnostic::ConstDivByZero(id),
+                });
+                None
+            }
+        }
+    }
+}
+
+/// The position of `field` in the values of a tuple or struct of type `ty`.
+pub(crate) fn field_index(module: &ModuleData, ty: &Type, field: Field) -> Option<usize> {
+    match field {
+        Field::Tuple(idx) => Some(idx as usize),
+        Field::Named(name) => {
+            let name = &module[name];
+            (module[ty.as_struct()?].fields.iter()).position(|field| &module[field.name] == name)
+        }
+    }
+}
+
+/// Why an expression could not be evaluated at compile time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum EvalError {
+    NotConst,
+    Overflow,
+    DivByZero,
+}
+
+/// The value of a literal of type `ctor`.
+pub(crate) fn eval_lit(ctor: Ctor, lit: Lit) -> Result<ConstValue, EvalError> {
+    match lit {
+        Lit::Bool(b) => Ok(ConstValue::Bool(b)),
+        Lit::Int(i, _) if ctor.is_int() => check_int(ctor, i128::from(i)),
+        Lit::Float(f, _) if ctor == Ctor::Float && f.0.abs() > f64::from(f32::MAX) => {
+            Err(EvalError::Overflow)
+        }
+        Lit::Float(f, _) => Ok(round_float(ctor, f.0)),
+        Lit::Char(c) => Ok(ConstValue::Char(c)),
+        Lit::Int(..) => Err(EvalError::NotConst),
+    }
+}
+
+/// The value of `-lit`. `-128` is a valid `I8`, even though `128` is not.
+pub(crate) fn eval_neg_lit(ctor: Ctor, lit: u64) -> Result<ConstValue, EvalError> {
+    if ctor.is_int() {
+        check_int(ctor, -i128::from(lit))
+    } else {
+        Err(EvalError::NotConst)
+    }
+}
+
+/// The result of the builtin operator `op` of type `ctor`. Overloaded
+/// operators are never constant.
+pub(crate) fn eval_unop(ctor: Ctor, op: Unop, value: ConstValue) -> Result<ConstValue, EvalError> {
+    use ConstValue::*;
+
+    match (op, value) {
+        (Unop::Add, value @ (Int(_) | Float(_))) => Ok(value),
+        (Unop::Sub, Int(i)) if ctor.is_int() => check_int(ctor, -i),
+        (Unop::Sub, Float(f)) => Ok(Float(-f)),
+        (Unop::Not, Bool(b)) => Ok(Bool(!b)),
+        (Unop::BitNot, Int(i)) if ctor.is_int() => Ok(Int(wrap_int(ctor, !i))),
+        _ => Err(EvalError::NotConst),
+    }
+}
+
+/// The result of the builtin operator `op` of type `ctor`. Lazy operators
+/// are left to the caller, since they may not evaluate `rhs`.
+pub(crate) fn eval_binop(
+    ctor: Ctor,
+    lhs: ConstValue,
+    op: Binop,
+    rhs: ConstValue,
+) -> Result<ConstValue, EvalError> {
+    use ConstValue::*;
+
+    match (op, lhs, rhs) {
+        (Binop::Arithmetic(op), Int(lhs), Int(rhs)) if ctor.is_int() => {
+            if matches!(op, ArithmeticBinop::Div | ArithmeticBinop::Rem) && rhs == 0 {
+                return Err(EvalError::DivByZero);
+            }
+            // the operands fit in 64 bits, but `U64::MAX * U64::MAX` still
+            // overflows an `i128`
+            let value = match op {
+                ArithmeticBinop::Add => lhs.checked_add(rhs),
+                ArithmeticBinop::Sub => lhs.checked_sub(rhs),
+                ArithmeticBinop::Mul => lhs.checked_mul(rhs),
+                ArithmeticBinop::Div => lhs.checked_div(rhs),
+                ArithmeticBinop::Rem => lhs.checked_rem(rhs),
+            };
+            check_int(ctor, value.ok_or(EvalError::Overflow)?)
+        }
+        (Binop::Arithmetic(op), Float(lhs), Float(rhs)) => {
+            let value = match op {
+                ArithmeticBinop::Add => lhs.0 + rhs.0,
+                ArithmeticBinop::Sub => lhs.0 - rhs.0,
+                ArithmeticBinop::Mul => lhs.0 * rhs.0,
+                ArithmeticBinop::Div => lhs.0 / rhs.0,
+                ArithmeticBinop::Rem => lhs.0 % rhs.0,
+            };
+            Ok(round_float(ctor, value))
+        }
+        (Binop::Bit(op), Int(lhs), Int(rhs)) if ctor.is_int() => {
+            let value = match op {
+                BitBinop::And => lhs & rhs,
+                BitBinop::Or => lhs | rhs,
+                BitBinop::Xor => lhs ^ rhs,
+                BitBinop::Shl | BitBinop::Shr if rhs < 0 || rhs >= int_bits(ctor).into() => {
+                    return Err(EvalError::Overflow)
+                }
+                BitBinop::Shl => wrap_int(ctor, lhs << rhs),
+                BitBinop::Shr => lhs >> rhs,
+            };
+            Ok(Int(value))
+        }
+        (Binop::Bit(op), Bool(lhs), Bool(rhs)) => match op {
+            BitBinop::And => Ok(Bool(lhs & rhs)),
+            BitBinop::Or => Ok(Bool(lhs | rhs)),
+            BitBinop::Xor => Ok(Bool(lhs ^ rhs)),
+            BitBinop::Shl | BitBinop::Shr => Err(EvalError::NotConst),
+        },
+        (Binop::Cmp(op), lhs, rhs) => {
+            let ordering = match (lhs, rhs) {
+                (Bool(lhs), Bool(rhs)) => lhs.cmp(&rhs),
+                (Int(lhs), Int(rhs)) => lhs.cmp(&rhs),
+                (Float(lhs), Float(rhs)) => match lhs.0.partial_cmp(&rhs.0) {
+                    Some(ordering) => ordering,
+                    // every comparison with NaN is false, except `!=`
+                    None => return Ok(Bool(op == CmpBinop::NotEq)),
+                },
+                (Char(lhs), Char(rhs)) => lhs.cmp(&rhs),
+                _ => return Err(EvalError::NotConst),
+            };
+            Ok(Bool(match op {
+                CmpBinop::Eq => ordering == Ordering::Equal,
+                CmpBinop::NotEq => ordering != Ordering::Equal,
+                CmpBinop::Less => ordering == Ordering::Less,
+                CmpBinop::LessEq => ordering != Ordering::Greater,
+                CmpBinop::Greater => ordering == Ordering::Greater,
+                CmpBinop::GreaterEq => ordering != Ordering::Less,
+            }))
+        }
+        _ => Err(EvalError::NotConst),
+    }
+}
+
+/// Fails if `value` does not fit in the integer type `ctor`.
+fn check_int(ctor: Ctor, value: i128) -> Result<ConstValue, EvalError> {
+    if wrap_int(ctor, value) == value {
+        Ok(ConstValue::Int(value))
+    } else {
+        Err(EvalError::Overflow)
+    }
+}
+
+fn int_bits(ctor: Ctor) -> u32 {
+    match ctor {
+        Ctor::I8 | Ctor::U8 => 8,
+        Ctor::I16 | Ctor::U16 => 16,
+        Ctor::Int | Ctor::U32 => 32,
+        Ctor::I64 | Ctor::U64 => 64,
+        _ => unreachable!("{:?} is not an integer type", ctor),
+    }
+}
+
+/// Truncates `value` to the width of the integer type `ctor`, as the
+/// generated code would.
+fn wrap_int(ctor: Ctor, value: i128) -> i128 {
+    let unused_bits = 128 - int_bits(ctor);
+    if ctor.is_signed_int() {
+        (value << unused_bits) >> unused_bits
+    } else {
+        ((value as u128) << unused_bits >> unused_bits) as i128
+    }
+}
+
+fn round_float(ctor: Ctor, value: f64) -> ConstValue {
+    let value = match ctor {
+        Ctor::Float => f64::from(value as f32),
+        _ => value,
+    };
+    ConstValue::Float(OrderedFloat(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use insta::*;
+
+    macro_rules! test_consteval {
+        ($name:ident, $src:expr) => {
+            #[test]
+            fn $name() { test_consteval($src) }
+        };
+    }
+
+    fn test_consteval(src: &str) {
+        let syntax = walrus_parser::parse(src);
+        let hir = lower(&syntax);
+        let scopes = crate::scopes::scopes(&hir);
+        let types = crate::ty::infer(hir.clone(), scopes.clone());
+        let consts = eval_consts(&hir, &scopes, &types);
+
+        let mut settings = insta::Settings::new();
+        settings.set_snapshot_path("../snapshots/consteval");
+        settings.set_prepend_module_to_snapshot(false);
+        settings.bind(|| assert_debug_snapshot!(consts));
+    }
+
+    test_consteval!(
+        const_arithmetic,
+        r#"const X: Int = 1 + 2 * 3 - 10 / 4 % 2;"#
+    );
+    test_consteval!(
+        const_float,
+        r#"const X: Float = 0.1 + 0.2; const Y: F64 = 0.1 + 0.2;"#
+    );
+    test_consteval!(
+        const_bits,
+        r#"const X: U8 = 1 << 7 | 3; const Y: I8 = ~0 ^ 1;"#
+    );
+    test_consteval!(const_cmp, r#"const X: Bool = 'a' < 'b' && !(1 == 2);"#);
+    test_consteval!(const_lazy, r#"const X: Bool = false && 1 / 0 == 0;"#);
+    test_consteval!(
+        const_refs,
+        r#"
+const PAIR: (Int, Bool) = (LIMIT - 1, true);
+const LIMIT: Int = 10;
+const FIRST: Int = PAIR.0;
+"#
+    );
+    test_consteval!(
+        const_struct,
+        r#"
+struct Point { x: Int, y: Int }
+const ORIGIN: Point = Point { y: 2, x: 1 };
+const Y: Int = ORIGIN.y;
+"#
+    );
+    test_consteval!(const_overflow, r#"const X: U8 = 200 + 100;"#);
+    test_consteval!(
+        const_overflow_i128,
+        r#"const X: U64 = 18446744073709551615 * 18446744073709551615;"#
+    );
+    test_consteval!(
+        const_negative_lit,
+        r#"const X: I8 = -128; const Y: I8 = -(127 + 1);"#
+    );
+    test_consteval!(const_shift_overflow, r#"const X: Int = 1 << 32;"#);
+    test_consteval!(const_div_by_zero, r#"const X: Int = 1 % (2 - 2);"#);
+    test_consteval!(const_cycle, r#"const X: Int = Y; const Y: Int = X + 1;"#);
+    test_consteval!(
+        const_not_const,
+        r#"
+const X: Int = f();
+fn f() -> Int { 1 }
+"#
+    );
+}
@@ -1,26 +1,38 @@
 use crate::{
+    fix::FixIt,
     hir::{
-        Binop, EnumDefId, EnumVariant, ExprId, Field, PatId, StructDefId, StructField, TypeId,
-        VarId,
+        Binop, ConstDefId, EnumDefId, EnumVariant, ExprId, Field, FnDefId, ImplDefId, Label,
+        NumSuffix, PatId, StructDefId, StructField, TypeAliasDefId, TypeId, Unop, Var, VarId,
     },
-    scopes::Denotation,
+    scopes::{Denotation, TraitId},
     ty::{InferenceId, Type},
 };
 use either::Either;
 use std::num::{ParseFloatError, ParseIntError};
+use text_size::TextRange;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum LitError {
     Int(ParseIntError),
     Float(ParseFloatError),
     EscapeChar(char),
+    /// A `\x` escape above `\x7F`.
+    ByteEscape(u8),
+    /// A `\u{...}` escape that is a surrogate or is above `\u{10FFFF}`.
     UnicodeChar(u32),
+    /// A numeric literal too large for the type given by its suffix, or for
+    /// an `F64` if it has none.
+    OutOfRange(NumSuffix),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Diagnostic {
+    LexError(crate::syntax::LexError),
     UnnecessarySemicolon(crate::syntax::Semicolon),
-    BadLit(LitError),
+    BadLit {
+        span: TextRange,
+        error: LitError,
+    },
     DuplicateVar {
         first: VarId,
         second: VarId,
@@ -29,12 +41,19 @@ pub enum Diagnostic {
         var: VarId,
         id: Either<ExprId, TypeId>,
         denotation: Option<Denotation>,
+        fix_its: Vec<FixIt>,
+        suggestions: Vec<Var>,
     },
     TypeMismatch {
         id: Either<ExprId, PatId>,
         expected: Type,
         got: Type,
     },
+    InfiniteType {
+        id: Either<ExprId, PatId>,
+        expected: Type,
+        got: Type,
+    },
     InferenceFail(InferenceId),
     IfBranchMismatch {
         then_branch: ExprId,
@@ -44,28 +63,95 @@ pub enum Diagnostic {
     },
     ReturnNotInFn(ExprId),
     BreakNotInLoop(ExprId),
+    /// The start or end of the range of a `for` loop, which is not an
+    /// integer.
+    ForRangeNotInt {
+        expr: ExprId,
+        ty: Type,
+    },
+    UnknownLabel {
+        expr: ExprId,
+        label: Label,
+    },
     CalledNonFn {
         expr: ExprId,
         ty: Type,
     },
+    NoSuchMethod {
+        expr: ExprId,
+        ty: Type,
+        method: VarId,
+    },
+    BadImplType {
+        id: TypeId,
+        ty: Type,
+    },
+    ConflictingImpls {
+        first: ImplDefId,
+        second: ImplDefId,
+    },
+    MissingTraitMethod {
+        impl_def: ImplDefId,
+        method: Var,
+    },
+    NotATraitMethod {
+        fn_def: FnDefId,
+        trait_id: TraitId,
+    },
+    TraitMethodMismatch {
+        fn_def: FnDefId,
+        expected: Type,
+        got: Type,
+    },
+    /// An `impl` of `Eq` or `Ord` for a scalar type, which already has a
+    /// built-in one.
+    ConflictsWithBuiltinImpl {
+        impl_def: ImplDefId,
+        ty: Type,
+    },
+    /// A generic fn whose parameter or return types are not all written out.
+    UnannotatedGenericFn(FnDefId),
+    /// A generic fn used at `expr` with a type argument that does not
+    /// implement one of the traits its type parameter is bounded by.
+    UnsatisfiedBound {
+        expr: ExprId,
+        ty: Type,
+        trait_id: TraitId,
+    },
     ArgCountMismatch {
         expr: ExprId,
         ty: Type,
         expected: usize,
         got: usize,
+        fix_its: Vec<FixIt>,
     },
     CannotApplyBinop {
         lhs_type: Type,
         op: Binop,
         rhs_type: Type,
     },
+    CannotApplyUnop {
+        lhs_type: Type,
+        op: Unop,
+    },
     NotLValue {
         lhs: ExprId,
     },
+    AssignToImmutable {
+        lhs: ExprId,
+        binding: PatId,
+    },
+    InvalidCast {
+        expr: ExprId,
+        from: Type,
+        to: Type,
+    },
     NoSuchField {
         expr: ExprId,
         field: Field,
         possible_fields: Either<Vec<StructField>, u32>,
+        fix_its: Vec<FixIt>,
+        suggestions: Vec<Var>,
     },
     NoFields {
         expr: ExprId,
@@ -74,5 +160,145 @@ pub enum Diagnostic {
     MissingField {
         expr: ExprId,
         field: Field,
+        fix_its: Vec<FixIt>,
+    },
+    NoSuchVariant {
+        id: Either<ExprId, PatId>,
+        enum_def: EnumDefId,
+        variant: VarId,
+    },
+    NotAnEnum {
+        pat: PatId,
+        var: VarId,
+        denotation: Option<Denotation>,
+    },
+    NoSuchPatField {
+        pat: PatId,
+        field: VarId,
+        possible_fields: Vec<StructField>,
+        fix_its: Vec<FixIt>,
+        suggestions: Vec<Var>,
+    },
+    NonExhaustiveMatch {
+        expr: ExprId,
+        missing_variants: Vec<VarId>,
+    },
+    RefutablePat {
+        pat: PatId,
+    },
+    CyclicTypeAlias(TypeAliasDefId),
+    CyclicConst(ConstDefId),
+    /// An ABI other than `"C"` on an `extern fn`.
+    UnknownAbi(crate::syntax::Str),
+    /// A parameter of an `extern fn` without a type annotation.
+    ExternParamNeedsType(PatId),
+    /// A parameter or return type of an `extern fn` that cannot be passed to
+    /// or from C.
+    NotFfiSafe {
+        id: TypeId,
+        ty: Type,
     },
+    /// An expression in a `const` that cannot be evaluated at compile time.
+    NotConst(ExprId),
+    ConstOverflow(ExprId),
+    ConstDivByZero(ExprId),
+    /// An attribute other than `#[allow(...)]`.
+    UnknownAttr(VarId),
+    /// A name in `#[allow(...)]` that is not a lint.
+    UnknownLint(VarId),
+    UnusedVar(PatId),
+    /// A fn that cannot be reached from `main`.
+    UnusedFn(FnDefId),
+    /// The first statement or tail of a block after a `Never`-typed statement.
+    UnreachableCode(ExprId),
+    /// The initializer of `let _ = ...` has a type other than `()`.
+    UnusedResult(ExprId),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A class of warning, which can be suppressed on a fn with
+/// `#[allow(name)]`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnusedVar,
+    UnusedFn,
+    UnreachableCode,
+    UnusedResult,
+}
+
+impl Lint {
+    pub const ALL: [Self; 4] = [
+        Self::UnusedVar,
+        Self::UnusedFn,
+        Self::UnreachableCode,
+        Self::UnusedResult,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::UnusedVar => "unused_var",
+            Self::UnusedFn => "unused_fn",
+            Self::UnreachableCode => "unreachable_code",
+            Self::UnusedResult => "unused_result",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|lint| lint.name() == name)
+    }
+}
+
+impl Diagnostic {
+    pub const fn severity(&self) -> Severity {
+        match self.lint() {
+            Some(_) => Severity::Warning,
+            None => Severity::Error,
+        }
+    }
+
+    /// The lint that produced this diagnostic, if it is a warning.
+    pub const fn lint(&self) -> Option<Lint> {
+        match self {
+            Self::UnusedVar(_) => Some(Lint::UnusedVar),
+            Self::UnusedFn(_) => Some(Lint::UnusedFn),
+            Self::UnreachableCode(_) => Some(Lint::UnreachableCode),
+            Self::UnusedResult(_) => Some(Lint::UnusedResult),
+            _ => None,
+        }
+    }
+}
+
+impl Diagnostic {
+    /// The types this diagnostic mentions, so that inference can replace the
+    /// type variables in them once it knows what they are.
+    pub(crate) fn types_mut(&mut self) -> Vec<&mut Type> {
+        match self {
+            Self::TypeMismatch { expected, got, .. }
+            | Self::InfiniteType { expected, got, .. }
+            | Self::TraitMethodMismatch { expected, got, .. } => vec![expected, got],
+            Self::IfBranchMismatch {
+                then_ty, else_ty, ..
+            } => vec![then_ty, else_ty],
+            Self::CannotApplyBinop {
+                lhs_type, rhs_type, ..
+            } => vec![lhs_type, rhs_type],
+            Self::CannotApplyUnop { lhs_type, .. } => vec![lhs_type],
+            Self::InvalidCast { from, to, .. } => vec![from, to],
+            Self::CalledNonFn { ty, .. }
+            | Self::NoSuchMethod { ty, .. }
+            | Self::BadImplType { ty, .. }
+            | Self::ConflictsWithBuiltinImpl { ty, .. }
+            | Self::UnsatisfiedBound { ty, .. }
+            | Self::ForRangeNotInt { ty, .. }
+            | Self::ArgCountMismatch { ty, .. }
+            | Self::NoFields { ty, .. }
+            | Self::NotFfiSafe { ty, .. } => vec![ty],
+            _ => Vec::new(),
+        }
+    }
 }
@@ -0,0 +1,489 @@
+//! Fix-its: changes to the source that fix a diagnostic, and are safe to
+//! apply without a human choosing between alternatives. Each fix-it is
+//! turned into plain text edits against the source it was lowered from, so
+//! that a command line `--fix` or a language server can apply them without
+//! knowing about the HIR. Some fix-its leave `_` placeholders for a human to
+//! fill in; see [`FixIt::needs_human`].
+
+use crate::{
+    builtins::{Builtin, BuiltinKind},
+    diagnostic::Diagnostic,
+    hir::{ExprId, Module, Var, VarId},
+    syntax,
+    ty::Type,
+};
+use either::Either::Left;
+use text_size::{TextRange, TextSize};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FixIt {
+    /// Replace the name `var` with `name`.
+    Rename {
+        var: VarId,
+        name: Var,
+    },
+    RemoveSemicolon(syntax::Semicolon),
+    /// Add `field: _` for each of `fields` to the struct or enum literal
+    /// `expr`, as placeholders for the user to fill in.
+    AddFields {
+        expr: ExprId,
+        fields: Vec<VarId>,
+    },
+    /// Add `count` placeholder `_` arguments to the end of the call or method
+    /// call `expr`.
+    AddArgs {
+        expr: ExprId,
+        count: usize,
+    },
+    /// Remove the arguments of the call or method call `expr` after the
+    /// first `keep`.
+    RemoveArgs {
+        expr: ExprId,
+        keep: usize,
+    },
+    /// Convert `expr` to the numeric type `to` with `as`.
+    Cast {
+        expr: ExprId,
+        to: Builtin,
+    },
+}
+
+/// Replace the text at `span` with `text`. An insertion has an empty `span`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub span: TextRange,
+    pub text: String,
+}
+
+impl TextEdit {
+    fn insert(offset: TextSize, text: impl Into<String>) -> Self {
+        Self {
+            span: TextRange::empty(offset),
+            text: text.into(),
+        }
+    }
+
+    const fn delete(span: TextRange) -> Self {
+        Self {
+            span,
+            text: String::new(),
+        }
+    }
+}
+
+impl Diagnostic {
+    pub fn fix_its(&self) -> Vec<FixIt> {
+        match self {
+            Self::UnboundVar { fix_its, .. }
+            | Self::NoSuchField { fix_its, .. }
+            | Self::NoSuchPatField { fix_its, .. }
+            | Self::ArgCountMismatch { fix_its, .. }
+            | Self::MissingField { fix_its, .. } => fix_its.clone(),
+            Self::UnnecessarySemicolon(semicolon) => vec![FixIt::RemoveSemicolon(*semicolon)],
+            Self::TypeMismatch {
+                id: Left(expr),
+                expected,
+                got,
+            } => numeric_conversion(*expr, expected, got)
+                .into_iter()
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Adds or removes arguments of the call or method call `expr`, so that it
+/// has `expected` rather than `got`.
+pub(crate) const fn arg_count_fix(expr: ExprId, expected: usize, got: usize) -> FixIt {
+    if got < expected {
+        FixIt::AddArgs {
+            expr,
+            count: expected - got,
+        }
+    } else {
+        FixIt::RemoveArgs {
+            expr,
+            keep: expected,
+        }
+    }
+}
+
+/// A cast of `expr` from `got` to `expected`, if one is an int type and the
+/// other a float type.
+fn numeric_conversion(expr: ExprId, expected: &Type, got: &Type) -> Option<FixIt> {
+    let (expected_ctor, got_ctor) = (expected.ctor()?, got.ctor()?);
+    let convertible = (expected_ctor.is_int() && got_ctor.is_float())
+        || (expected_ctor.is_float() && got_ctor.is_int());
+    if !convertible {
+        return None;
+    }
+    let to = (Builtin::all().iter())
+        .find(|builtin| builtin.kind() == BuiltinKind::Type && builtin.ty() == *expected)?;
+    Some(FixIt::Cast { expr, to: *to })
+}
+
+impl FixIt {
+    /// Whether applying this fix-it leaves `_` placeholders, which are not
+    /// valid expressions. A `--fix` that runs unattended should skip these,
+    /// and a language server should offer them as a starting point only.
+    pub const fn needs_human(&self) -> bool {
+        matches!(self, Self::AddFields { .. } | Self::AddArgs { .. })
+    }
+
+    /// The edits to the source of `module` that apply this fix-it.
+    pub fn edits(&self, module: &Module) -> Vec<TextEdit> {
+        match self {
+            Self::Rename { var, name } => vec![TextEdit {
+                span: module.source[*var].span(),
+                text: name.to_string(),
+            }],
+            Self::RemoveSemicolon(semicolon) => vec![TextEdit::delete(semicolon.span)],
+            Self::AddFields {
+                expr,
+                fields: missing,
+            } => {
+                let fields = match &module.source[*expr] {
+                    syntax::Expr::Struct(syntax::StructExpr { fields, .. })
+                    | syntax::Expr::Enum(syntax::EnumExpr { fields, .. }) => fields,
+                    _ => return Vec::new(),
+                };
+                let inits = (missing.iter())
+                    .map(|field| format!("{}: _", module.data[*field]))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let edit = match (&fields.inner.trail, fields.inner.iter().last()) {
+                    (Some(comma), _) => TextEdit::insert(comma.span.end(), format!(" {inits},")),
+                    (None, Some(last)) => {
+                        TextEdit::insert(last.val.span().end(), format!(", {inits}"))
+                    }
+                    // replace any whitespace between the braces
+                    (None, None) => TextEdit {
+                        span: TextRange::new(fields.open.span.end(), fields.close.span.start()),
+                        text: format!(" {inits} "),
+                    },
+                };
+                vec![edit]
+            }
+            Self::AddArgs { expr, count } => {
+                let args = match call_args(module, *expr) {
+                    Some(args) => args,
+                    None => return Vec::new(),
+                };
+                let placeholders = vec!["_"; *count].join(", ");
+                let edit = match (&args.inner.trail, args.inner.iter().last()) {
+                    (Some(comma), _) => {
+                        TextEdit::insert(comma.span.end(), format!(" {placeholders},"))
+                    }
+                    (None, Some(last)) => {
+                        TextEdit::insert(last.span().end(), format!(", {placeholders}"))
+                    }
+                    (None, None) => TextEdit::insert(args.open.span.end(), placeholders),
+                };
+                vec![edit]
+            }
+            Self::RemoveArgs { expr, keep } => {
+                let args = match call_args(module, *expr) {
+                    Some(args) => &args.inner,
+                    None => return Vec::new(),
+                };
+                let last = match args.iter().last() {
+                    Some(last) => last.span(),
+                    None => return Vec::new(),
+                };
+                let start = match (keep.checked_sub(1), &args.first) {
+                    // remove the comma before the first removed argument
+                    (Some(idx), _) => match args.tail.get(idx) {
+                        Some((comma, _)) => comma.span.start(),
+                        None => return Vec::new(),
+                    },
+                    (None, Some(first)) => first.span().start(),
+                    (None, None) => return Vec::new(),
+                };
+                // with no arguments left, a trailing comma must go too
+                let end = match (&args.trail, keep) {
+                    (Some(comma), 0) => comma.span.end(),
+                    _ => last.end(),
+                };
+                vec![TextEdit::delete(TextRange::new(start, end))]
+            }
+            Self::Cast { expr, to } => {
+                // a block is converted by converting its tail
+                let mut syntax = &module.source[*expr];
+                while let syntax::Expr::Block(syntax::Block { expr: tail, .. }) = syntax {
+                    match &**tail {
+                        Some(tail) => syntax = tail,
+                        None => break,
+                    }
+                }
+                let span = syntax.span();
+                let cast = format!(" as {}", to.name());
+                if binds_tighter_than_cast(syntax) {
+                    vec![TextEdit::insert(span.end(), cast)]
+                } else {
+                    vec![
+                        TextEdit::insert(span.start(), "("),
+                        TextEdit::insert(span.end(), format!("){cast}")),
+                    ]
+                }
+            }
+        }
+    }
+}
+
+fn call_args(
+    module: &Module,
+    expr: ExprId,
+) -> Option<&syntax::Paren<syntax::Punctuated0<syntax::Expr, syntax::Comma>>> {
+    match &module.source[expr] {
+        syntax::Expr::Call(syntax::CallExpr { args, .. })
+        | syntax::Expr::MethodCall(syntax::MethodCallExpr { args, .. }) => Some(&args.0),
+        _ => None,
+    }
+}
+
+/// Whether `expr as T` casts the whole of `expr`, without parentheses.
+const fn binds_tighter_than_cast(expr: &syntax::Expr) -> bool {
+    matches!(
+        expr,
+        syntax::Expr::Lit(_)
+            | syntax::Expr::Var(_)
+            | syntax::Expr::Paren(_)
+            | syntax::Expr::Tuple(_)
+            | syntax::Expr::Unary(_)
+            | syntax::Expr::Cast(_)
+            | syntax::Expr::Call(_)
+            | syntax::Expr::MethodCall(_)
+            | syntax::Expr::Field(_)
+            | syntax::Expr::Struct(_)
+            | syntax::Expr::Enum(_)
+            | syntax::Expr::Path(_)
+    )
+}
+
+/// Applies `edits` to `src`, in order of where they start. Insertions at the
+/// same offset are made in the order they are given. An edit that overlaps
+/// one already made is skipped, so every fix-it for a file can be applied at
+/// once, and only the first of several alternatives takes effect.
+pub fn apply_edits(src: &str, edits: &[TextEdit]) -> String {
+    let mut edits = edits.iter().collect::<Vec<_>>();
+    edits.sort_by_key(|edit| edit.span.start());
+
+    let mut out = String::with_capacity(src.len());
+    let mut pos = TextSize::from(0);
+    for edit in edits {
+        if edit.span.start() < pos {
+            continue;
+        }
+        out.push_str(&src[TextRange::new(pos, edit.span.start())]);
+        out.push_str(&edit.text);
+        pos = edit.span.end();
+    }
+    out.push_str(&src[TextRange::new(pos, TextSize::of(src))]);
+    out
+}
+
+/// Names among `candidates` close enough to `name` to be what was meant. A
+/// rename of `var` to the closest is returned as a fix-it when no other
+/// candidate is as close; the other candidates are returned separately,
+/// closest first, for a human to choose between.
+pub fn rename_suggestions(
+    var: VarId,
+    name: &Var,
+    candidates: impl IntoIterator<Item = Var>,
+) -> (Vec<FixIt>, Vec<Var>) {
+    const MAX_SUGGESTIONS: usize = 3;
+
+    let name = name.as_str();
+    let max_distance = std::cmp::max(name.chars().count(), 3) / 3;
+    let mut suggestions = candidates
+        .into_iter()
+        .map(|candidate| (edit_distance(name, candidate.as_str()), candidate))
+        .filter(|(distance, _)| (1..=max_distance).contains(distance))
+        .collect::<Vec<_>>();
+    suggestions.sort_by(|(d1, var1), (d2, var2)| (d1, var1.as_str()).cmp(&(d2, var2.as_str())));
+    suggestions.dedup_by(|(_, var1), (_, var2)| var1 == var2);
+    suggestions.truncate(MAX_SUGGESTIONS);
+
+    let unique = match suggestions.as_slice() {
+        [_] => true,
+        [(d1, _), (d2, _), ..] => d1 < d2,
+        [] => false,
+    };
+    let mut names = suggestions.into_iter().map(|(_, name)| name);
+    let fix_its = if unique {
+        names
+            .next()
+            .map(|name| FixIt::Rename { var, name })
+            .into_iter()
+            .collect()
+    } else {
+        Vec::new()
+    };
+    (fix_its, names.collect())
+}
+
+/// The number of single char insertions, deletions, substitutions and
+/// transpositions of adjacent chars needed to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+    // `rows[i][j]` is the distance between the first `i` chars of `a` and
+    // the first `j` chars of `b`
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! test_fix {
+        ($name:ident, $src:expr, $expected:expr) => {
+            #[test]
+            fn $name() { test_fix($src, $expected) }
+        };
+    }
+
+    /// Applies every fix-it for the diagnostics of `src`, and compares the
+    /// result with `expected`.
+    fn test_fix(src: &str, expected: &str) {
+        let syntax = walrus_parser::parse(src);
+        let hir = crate::hir::lower(&syntax);
+        let scopes = crate::scopes::scopes(&hir);
+        let types = crate::ty::infer(hir.clone(), scopes.clone());
+
+        let edits = (hir.diagnostics.iter())
+            .chain(&scopes.diagnostics)
+            .chain(&types.diagnostics)
+            .flat_map(Diagnostic::fix_its)
+            .flat_map(|fix_it| fix_it.edits(&hir))
+            .collect::<Vec<_>>();
+        assert_eq!(apply_edits(src, &edits), expected);
+    }
+
+    test_fix!(no_fixes, r#"fn f() -> Int { 1 }"#, r#"fn f() -> Int { 1 }"#);
+    test_fix!(remove_semicolon, r#"fn f() { 1;; }"#, r#"fn f() { 1; }"#);
+    test_fix!(
+        rename_var,
+        r#"fn f() -> Int { let count = 1; cuont }"#,
+        r#"fn f() -> Int { let count = 1; count }"#
+    );
+    test_fix!(
+        rename_closest,
+        r#"fn f() -> Int { let counter = 1; let counts = 2; cuonter }"#,
+        r#"fn f() -> Int { let counter = 1; let counts = 2; counter }"#
+    );
+    test_fix!(
+        no_rename_if_ambiguous,
+        r#"fn f() -> Int { let cont = 1; let count = 2; cuont }"#,
+        r#"fn f() -> Int { let cont = 1; let count = 2; cuont }"#
+    );
+    test_fix!(
+        rename_field,
+        r#"struct P { xpos: Int } fn f(p: P) -> Int { p.xpso }"#,
+        r#"struct P { xpos: Int } fn f(p: P) -> Int { p.xpos }"#
+    );
+    test_fix!(
+        add_fields,
+        r#"struct P { x: Int, y: Int, z: Int } fn f() -> P { P { x: 1 } }"#,
+        r#"struct P { x: Int, y: Int, z: Int } fn f() -> P { P { x: 1, y: _, z: _ } }"#
+    );
+    test_fix!(
+        add_fields_after_trailing_comma,
+        r#"struct P { x: Int, y: Int } fn f() -> P { P { x: 1, } }"#,
+        r#"struct P { x: Int, y: Int } fn f() -> P { P { x: 1, y: _, } }"#
+    );
+    test_fix!(
+        add_fields_to_empty,
+        r#"enum E { A { x: Int } } fn f() -> E { E::A {} }"#,
+        r#"enum E { A { x: Int } } fn f() -> E { E::A { x: _ } }"#
+    );
+    test_fix!(
+        add_fields_to_empty_with_space,
+        r#"struct P { x: Int, y: Int } fn f() -> P { P {  } }"#,
+        r#"struct P { x: Int, y: Int } fn f() -> P { P { x: _, y: _ } }"#
+    );
+    test_fix!(
+        add_args,
+        r#"fn f() { g(1) } fn g(x: Int, y: Int, z: Int) {}"#,
+        r#"fn f() { g(1, _, _) } fn g(x: Int, y: Int, z: Int) {}"#
+    );
+    test_fix!(
+        add_args_to_empty,
+        r#"fn f() { g() } fn g(x: Int) {}"#,
+        r#"fn f() { g(_) } fn g(x: Int) {}"#
+    );
+    test_fix!(
+        remove_args,
+        r#"fn f() { g(1, 2, 3) } fn g(x: Int) {}"#,
+        r#"fn f() { g(1) } fn g(x: Int) {}"#
+    );
+    test_fix!(
+        remove_all_args,
+        r#"fn f() { g(1, 2,) } fn g() {}"#,
+        r#"fn f() { g() } fn g() {}"#
+    );
+    test_fix!(
+        remove_method_args,
+        r#"struct S {} impl S { fn m(self) {} } fn f(s: S) { s.m(1) }"#,
+        r#"struct S {} impl S { fn m(self) {} } fn f(s: S) { s.m() }"#
+    );
+    test_fix!(
+        int_to_float,
+        r#"fn f(x: Int) -> Float { x }"#,
+        r#"fn f(x: Int) -> Float { x as Float }"#
+    );
+    test_fix!(
+        float_to_int_parenthesized,
+        r#"fn f(x: F64) { let y: Int = if true { x } else { x }; }"#,
+        r#"fn f(x: F64) { let y: Int = (if true { x } else { x }) as Int; }"#
+    );
+
+    #[test]
+    fn placeholders_need_human() {
+        let src = r#"fn f() { g(1);; } fn g(x: Int, y: Int) {}"#;
+        let syntax = walrus_parser::parse(src);
+        let hir = crate::hir::lower(&syntax);
+        let scopes = crate::scopes::scopes(&hir);
+        let types = crate::ty::infer(hir.clone(), scopes);
+        let needs_human = (hir.diagnostics.iter())
+            .chain(&types.diagnostics)
+            .flat_map(Diagnostic::fix_its)
+            .map(|fix_it| fix_it.needs_human())
+            .collect::<Vec<_>>();
+        assert_eq!(needs_human, [false, true]);
+    }
+
+    #[test]
+    fn overlapping_edits() {
+        let edit = |start: u32, end: u32, text: &str| TextEdit {
+            span: TextRange::new(start.into(), end.into()),
+            text: text.into(),
+        };
+        let edits = [
+            edit(4, 5, "x"),
+            edit(0, 2, "ab"),
+            edit(1, 3, "!"),
+            edit(4, 4, "y"),
+        ];
+        assert_eq!(apply_edits("01234", &edits), "ab23x");
+    }
+}
@@ -0,0 +1,413 @@
+//! Simplifying the HIR once it has been type checked, so that codegen (or an
+//! interpreter) does not have to evaluate what is already known at compile
+//! time:
+//! * builtin operators whose operands are constants are replaced by their
+//!   result, as are `const` items of scalar type,
+//! * an `if` whose test is constant is replaced by the branch it takes,
+//! * the statements of a block after one that never returns (eg `return` or
+//!   `exit(1)`) are dropped.
+//!
+//! Operations that would overflow or divide by zero are left for the
+//! generated code to trap on or wrap. The HIR is rewritten in place, so the
+//! ids of the remaining expressions, and the types and scopes recorded for
+//! them, stay valid.
+
+use crate::{
+    consteval::{self, ConstValue, ConstValues},
+    hir::*,
+    scopes::{Denotation, Scopes},
+    ty::{Ctor, InferenceResult, Type},
+};
+use arena::ArenaMap;
+use std::convert::TryFrom;
+use text_size::TextRange;
+
+pub fn fold(
+    module: &mut ModuleData,
+    scopes: &Scopes,
+    types: &InferenceResult,
+    consts: &ConstValues,
+) {
+    let bodies = (module.fn_defs.iter())
+        .map(|(_, fn_def)| fn_def.expr)
+        .collect::<Vec<_>>();
+    let mut ctx = Ctx {
+        module,
+        scopes,
+        types,
+        consts,
+        values: ArenaMap::default(),
+    };
+    for expr in bodies {
+        ctx.fold_expr(expr);
+    }
+}
+
+struct Ctx<'a> {
+    module: &'a mut ModuleData,
+    scopes: &'a Scopes,
+    types: &'a InferenceResult,
+    consts: &'a ConstValues,
+    /// The value of every expression that is known at compile time.
+    values: ArenaMap<ExprId, ConstValue>,
+}
+
+impl Ctx<'_> {
+    fn fold_expr(&mut self, id: ExprId) {
+        let mut children = Vec::new();
+        self.module[id].walk_child_exprs(|child| children.push(child));
+        for child in children {
+            self.fold_expr(child);
+        }
+
+        let ctor = self.types[id].ctor().copied();
+        match self.module[id].clone() {
+            Expr::Lit(lit) => {
+                if let Some(Ok(value)) = ctor.map(|ctor| consteval::eval_lit(ctor, lit)) {
+                    self.values.insert(id, value);
+                }
+            }
+            Expr::Var(var) => {
+                if let Some(Denotation::Const(const_id)) =
+                    self.scopes.lookup_expr(id, &self.module[var])
+                {
+                    if let Some(value) = self.consts.values.get(const_id) {
+                        self.replace_with_value(id, value.clone(), None);
+                    }
+                }
+            }
+            Expr::Field { expr, field } => {
+                let idx = consteval::field_index(self.module, &self.types[expr], field);
+                if let (
+                    Some(ConstValue::Tuple(fields) | ConstValue::Struct(_, fields)),
+                    Some(idx),
+                ) = (self.values.get(expr), idx)
+                {
+                    let value = fields[idx].clone();
+                    self.replace_with_value(id, value, None);
+                }
+            }
+            Expr::Unop { op, op_span, expr } => self.fold_unop(id, ctor, op, op_span, expr),
+            Expr::Binop {
+                lhs,
+                op,
+                op_span,
+                rhs,
+            } => self.fold_binop(id, ctor, lhs, op, op_span, rhs),
+            Expr::If {
+                test,
+                then_branch,
+                else_branch,
+            } => self.fold_if(id, test, then_branch, else_branch),
+            Expr::Block { stmts, expr } => self.fold_block(id, stmts, expr),
+            _ => {}
+        }
+    }
+
+    fn fold_unop(
+        &mut self,
+        id: ExprId,
+        ctor: Option<Ctor>,
+        op: Unop,
+        op_span: TextRange,
+        expr: ExprId,
+    ) {
+        let lit = match self.module[expr] {
+            Expr::Lit(Lit::Int(lit, _)) => Some(lit),
+            _ => None,
+        };
+        match (ctor, op, lit, self.values.get(expr)) {
+            // already the simplest form of a negative literal
+            (Some(ctor), Unop::Sub, Some(lit), _) => {
+                if let Ok(value) = consteval::eval_neg_lit(ctor, lit) {
+                    self.values.insert(id, value);
+                }
+            }
+            (Some(ctor), _, _, Some(value)) => {
+                if let Ok(value) = consteval::eval_unop(ctor, op, value.clone()) {
+                    self.replace_with_value(id, value, Some((expr, op_span)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn fold_binop(
+        &mut self,
+        id: ExprId,
+        ctor: Option<Ctor>,
+        lhs: ExprId,
+        op: Binop,
+        op_span: TextRange,
+        rhs: ExprId,
+    ) {
+        match (ctor, self.values.get(lhs), op, self.values.get(rhs)) {
+            (_, Some(ConstValue::Bool(true)), Binop::Lazy(LazyBinop::Or), _)
+            | (_, Some(ConstValue::Bool(false)), Binop::Lazy(LazyBinop::And), _) => {
+                let value = self.values[lhs].clone();
+                self.replace_with_value(id, value, None);
+            }
+            (_, Some(ConstValue::Bool(_)), Binop::Lazy(_), _) => self.replace_with_expr(id, rhs),
+            (Some(ctor), Some(lhs_value), _, Some(rhs_value)) => {
+                let value = consteval::eval_binop(ctor, lhs_value.clone(), op, rhs_value.clone());
+                if let Ok(value) = value {
+                    self.replace_with_value(id, value, Some((lhs, op_span)));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn fold_if(
+        &mut self,
+        id: ExprId,
+        test: ExprId,
+        then_branch: ExprId,
+        else_branch: Option<ExprId>,
+    ) {
+        match (self.values.get(test), else_branch) {
+            (Some(ConstValue::Bool(true)), Some(_)) => self.replace_with_expr(id, then_branch),
+            (Some(ConstValue::Bool(false)), Some(else_branch)) => {
+                self.replace_with_expr(id, else_branch);
+            }
+            // an `if` without an `else` is always `()`
+            (Some(ConstValue::Bool(true)), None) => {
+                self.module.exprs[id] = Expr::Block {
+                    stmts: vec![Stmt::Expr(then_branch)],
+                    expr: None,
+                }
+            }
+            (Some(ConstValue::Bool(false)), None) => {
+                self.module.exprs[id] = Expr::Block {
+                    stmts: vec![],
+                    expr: None,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn fold_block(&mut self, id: ExprId, mut stmts: Vec<Stmt>, expr: Option<ExprId>) {
+        let diverges = stmts.iter().position(|stmt| match stmt {
+            Stmt::Let { expr, .. } | Stmt::Expr(expr) => self.types[*expr] == Type::NEVER,
+        });
+        if let Some(idx) = diverges {
+            stmts.truncate(idx + 1);
+            self.module.exprs[id] = Expr::Block { stmts, expr: None };
+        } else if let (true, Some(expr)) = (stmts.is_empty(), expr) {
+            if let Some(value) = self.values.get(expr).cloned() {
+                self.values.insert(id, value);
+            }
+        }
+    }
+
+    /// Replaces `id` with a literal for `value`, if there is one. A negative
+    /// integer is written as `-lit`, which needs another expression to hold
+    /// `lit`: `spare` is an operand of `id` of the same type as `id`, that is
+    /// no longer used once `id` is replaced, along with the span of `id`'s
+    /// operator.
+    fn replace_with_value(
+        &mut self,
+        id: ExprId,
+        value: ConstValue,
+        spare: Option<(ExprId, TextRange)>,
+    ) {
+        let expr = match value {
+            ConstValue::Bool(b) => Some(Expr::Lit(Lit::Bool(b))),
+            ConstValue::Int(i) => match (u64::try_from(i), u64::try_from(-i), spare) {
+                (Ok(i), ..) => Some(Expr::Lit(Lit::Int(i, None))),
+                (_, Ok(lit), Some((spare, op_span))) => {
+                    self.module.exprs[spare] = Expr::Lit(Lit::Int(lit, None));
+                    self.values.insert(spare, ConstValue::Int(-i));
+                    Some(Expr::Unop {
+                        op: Unop::Sub,
+                        op_span,
+                        expr: spare,
+                    })
+                }
+                _ => None,
+            },
+            ConstValue::Float(f) => Some(Expr::Lit(Lit::Float(f, None))),
+            ConstValue::Char(c) => Some(Expr::Lit(Lit::Char(c))),
+            ConstValue::Tuple(_) | ConstValue::Struct(..) => None,
+        };
+        if let Some(expr) = expr {
+            self.module.exprs[id] = expr;
+        }
+        self.values.insert(id, value);
+    }
+
+    /// Replaces `id` with `{ expr }`, or with the value of `expr` if it has
+    /// one.
+    fn replace_with_expr(&mut self, id: ExprId, expr: ExprId) {
+        self.module.exprs[id] = Expr::Block {
+            stmts: vec![],
+            expr: Some(expr),
+        };
+        if let Some(value) = self.values.get(expr).cloned() {
+            self.replace_with_value(id, value, None);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! test_fold {
+        ($name:ident, $src:expr, $expected:expr) => {
+            #[test]
+            fn $name() { test_fold($src, $expected) }
+        };
+    }
+
+    /// Folds `src`, and compares the body of its first fn with `expected`.
+    fn test_fold(src: &str, expected: &str) {
+        let syntax = walrus_parser::parse(src);
+        let mut hir = lower(&syntax);
+        let scopes = crate::scopes::scopes(&hir);
+        let types = crate::ty::infer(hir.clone(), scopes.clone());
+        assert_eq!(types.diagnostics, vec![]);
+        let consts = consteval::eval_consts(&hir, &scopes, &types);
+
+        fold(&mut hir.data, &scopes, &types, &consts);
+        let (_, first_fn) = hir.data.fn_defs.iter().next().unwrap();
+        assert_eq!(print_expr(&hir.data, first_fn.expr), expected);
+    }
+
+    fn print_expr(module: &ModuleData, id: ExprId) -> String {
+        let print = |id| print_expr(module, id);
+        match &module[id] {
+            Expr::Lit(Lit::Bool(b)) => b.to_string(),
+            Expr::Lit(Lit::Int(i, _)) => i.to_string(),
+            Expr::Lit(Lit::Float(f, _)) => format!("{:?}", f.0),
+            Expr::Lit(Lit::Char(c)) => format!("{:?}", c),
+            Expr::Var(var) => module[*var].to_string(),
+            Expr::Tuple(exprs) => {
+                let exprs = exprs.iter().map(|expr| print(*expr)).collect::<Vec<_>>();
+                format!("({})", exprs.join(", "))
+            }
+            Expr::Unop { op, expr, .. } => {
+                let op = match op {
+                    Unop::Not => "!",
+                    Unop::Add => "+",
+                    Unop::Sub => "-",
+                    Unop::BitNot => "~",
+                };
+                format!("{}{}", op, print(*expr))
+            }
+            Expr::Binop { lhs, op, rhs, .. } => format!("({} {} {})", print(*lhs), op, print(*rhs)),
+            Expr::If {
+                test,
+                then_branch,
+                else_branch: Some(else_branch),
+            } => format!(
+                "if {} {} else {}",
+                print(*test),
+                print(*then_branch),
+                print(*else_branch)
+            ),
+            Expr::If {
+                test, then_branch, ..
+            } => format!("if {} {}", print(*test), print(*then_branch)),
+            Expr::Call { func, args } => {
+                let args = args.iter().map(|arg| print(*arg)).collect::<Vec<_>>();
+                format!("{}({})", print(*func), args.join(", "))
+            }
+            Expr::Return(Some(expr)) => format!("return {}", print(*expr)),
+            Expr::Block { stmts, expr } => {
+                let stmts = stmts.iter().map(|stmt| match stmt {
+                    Stmt::Let { pat, expr, .. } => match &module[*pat] {
+                        Pat::Var { var, .. } => format!("let {} = {};", module[*var], print(*expr)),
+                        pat => format!("let {:?} = {};", pat, print(*expr)),
+                    },
+                    Stmt::Expr(expr) => format!("{};", print(*expr)),
+                });
+                let items = stmts.chain(expr.map(print)).collect::<Vec<_>>();
+                match items.as_slice() {
+                    [] => String::from("{}"),
+                    items => format!("{{ {} }}", items.join(" ")),
+                }
+            }
+            expr => format!("{:?}", expr),
+        }
+    }
+
+    test_fold!(int_arithmetic, r#"fn f() -> _ { 1 + 2 * 3 }"#, "{ 7 }");
+    test_fold!(negative_result, r#"fn f() -> _ { 1 - 2 * 3 }"#, "{ -5 }");
+    test_fold!(negative_lit, r#"fn f() -> I8 { -128 }"#, "{ -128 }");
+    test_fold!(
+        overflow_is_not_folded,
+        r#"fn f() -> U64 { 18446744073709551615 * 18446744073709551615 }"#,
+        "{ (18446744073709551615 * 18446744073709551615) }"
+    );
+    test_fold!(
+        float_arithmetic,
+        r#"fn f() -> _ { let x: F64 = 1.5 * 2.0; x }"#,
+        "{ let x = 3.0; x }"
+    );
+    test_fold!(
+        cmp_and_not,
+        r#"fn f() -> _ { !(1 < 2) || 'a' == 'a' }"#,
+        "{ true }"
+    );
+    test_fold!(
+        partial,
+        r#"fn f(x: Int) -> _ { x * (2 + 3) }"#,
+        "{ (x * 5) }"
+    );
+    test_fold!(
+        lazy_short_circuit,
+        r#"fn f(x: Bool) -> _ { (false && x, true || x) }"#,
+        "{ (false, true) }"
+    );
+    test_fold!(
+        lazy_rhs,
+        r#"fn f(x: Bool) -> _ { 1 < 2 && x }"#,
+        "{ { x } }"
+    );
+    test_fold!(
+        overflow_not_folded,
+        r#"fn f() -> _ { let x: U8 = 200 + 100; x }"#,
+        "{ let x = (200 + 100); x }"
+    );
+    test_fold!(
+        div_by_zero_not_folded,
+        r#"fn f() -> _ { 1 / (2 - 2) }"#,
+        "{ (1 / 0) }"
+    );
+    test_fold!(
+        if_true,
+        r#"fn f(x: Int, y: Int) -> _ { if 2 > 1 { x } else { y } }"#,
+        "{ { { x } } }"
+    );
+    test_fold!(
+        if_false,
+        r#"fn f(x: Int, y: Int) -> _ { if 2 < 1 { x } else { y } }"#,
+        "{ { { y } } }"
+    );
+    test_fold!(
+        if_false_no_else,
+        r#"fn f() -> _ { if false { exit(1) }; 5 }"#,
+        "{ {}; 5 }"
+    );
+    test_fold!(
+        const_item,
+        r#"
+fn f() -> _ { LIMIT * 2 + PAIR.1 }
+const LIMIT: Int = 10;
+const PAIR: (Bool, Int) = (true, -1);
+"#,
+        "{ 19 }"
+    );
+    test_fold!(
+        after_return,
+        r#"fn f(x: Int) -> Int { return x; x + 1 }"#,
+        "{ return x; }"
+    );
+    test_fold!(
+        after_exit,
+        r#"fn f() -> Int { let x = 1 + 1; exit(x); let y = x; y }"#,
+        "{ let x = 2; exit(x); }"
+    );
+}
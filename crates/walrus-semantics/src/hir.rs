@@ -1,9 +1,14 @@
-use crate::{diagnostic::Diagnostic, syntax};
+use crate::{
+    builtins::Builtin,
+    diagnostic::{Diagnostic, Lint},
+    syntax,
+};
 use arena::{Arena, ArenaMap, Idx};
-use derive_more::Display;
+use derive_more::{Display, From};
 use ordered_float::OrderedFloat;
 use smol_str::SmolStr;
 use std::{fmt, ops::Index};
+use text_size::TextRange;
 
 mod lower;
 mod walk;
@@ -14,6 +19,11 @@ pub type VarId = Idx<Var>;
 pub type FnDefId = Idx<FnDef>;
 pub type StructDefId = Idx<StructDef>;
 pub type EnumDefId = Idx<EnumDef>;
+pub type ImplDefId = Idx<ImplDef>;
+pub type TraitDefId = Idx<TraitDef>;
+pub type TypeAliasDefId = Idx<TypeAliasDef>;
+pub type ConstDefId = Idx<ConstDef>;
+pub type ExternFnDefId = Idx<ExternFnDef>;
 pub type ExprId = Idx<Expr>;
 pub type TypeId = Idx<Type>;
 pub type PatId = Idx<Pat>;
@@ -37,6 +47,25 @@ impl From<syntax::Var> for Var {
     fn from(var: syntax::Var) -> Self { Self(var.0.text) }
 }
 
+/// A loop label, including its leading `'`.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct Label(SmolStr);
+
+impl Label {
+    pub fn as_str(&self) -> &str { &self.0 }
+}
+
+impl fmt::Debug for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{:?}", self.0) }
+}
+impl fmt::Display for Label {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { write!(f, "{}", self.0) }
+}
+
+impl From<syntax::Label> for Label {
+    fn from(label: syntax::Label) -> Self { Self(label.text) }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Module {
     pub decls: Vec<Decl>,
@@ -51,9 +80,17 @@ pub struct ModuleData {
     pub fn_defs: Arena<FnDef>,
     pub struct_defs: Arena<StructDef>,
     pub enum_defs: Arena<EnumDef>,
+    pub impl_defs: Arena<ImplDef>,
+    pub trait_defs: Arena<TraitDef>,
+    pub type_alias_defs: Arena<TypeAliasDef>,
+    pub const_defs: Arena<ConstDef>,
+    pub extern_fn_defs: Arena<ExternFnDef>,
     pub exprs: Arena<Expr>,
     pub types: Arena<Type>,
     pub pats: Arena<Pat>,
+    /// The start and end of the range of each desugared `for` loop. See
+    /// `lower_for_expr`.
+    pub for_ranges: Vec<(ExprId, ExprId)>,
 }
 impl Index<VarId> for ModuleData {
     type Output = Var;
@@ -71,6 +108,26 @@ impl Index<EnumDefId> for ModuleData {
     type Output = EnumDef;
     fn index(&self, id: EnumDefId) -> &Self::Output { &self.enum_defs[id] }
 }
+impl Index<ImplDefId> for ModuleData {
+    type Output = ImplDef;
+    fn index(&self, id: ImplDefId) -> &Self::Output { &self.impl_defs[id] }
+}
+impl Index<TraitDefId> for ModuleData {
+    type Output = TraitDef;
+    fn index(&self, id: TraitDefId) -> &Self::Output { &self.trait_defs[id] }
+}
+impl Index<TypeAliasDefId> for ModuleData {
+    type Output = TypeAliasDef;
+    fn index(&self, id: TypeAliasDefId) -> &Self::Output { &self.type_alias_defs[id] }
+}
+impl Index<ConstDefId> for ModuleData {
+    type Output = ConstDef;
+    fn index(&self, id: ConstDefId) -> &Self::Output { &self.const_defs[id] }
+}
+impl Index<ExternFnDefId> for ModuleData {
+    type Output = ExternFnDef;
+    fn index(&self, id: ExternFnDefId) -> &Self::Output { &self.extern_fn_defs[id] }
+}
 impl Index<ExprId> for ModuleData {
     type Output = Expr;
     fn index(&self, id: ExprId) -> &Self::Output { &self.exprs[id] }
@@ -84,12 +141,33 @@ impl Index<PatId> for ModuleData {
     fn index(&self, id: PatId) -> &Self::Output { &self.pats[id] }
 }
 
+impl ModuleData {
+    pub fn is_self_pat(&self, id: PatId) -> bool {
+        matches!(self[id], Pat::Var { var, .. } if self[var].as_str() == "self")
+    }
+
+    /// Whether the fn takes `self` as its first parameter, and so can be
+    /// called with method syntax.
+    pub fn is_method(&self, id: FnDefId) -> bool {
+        let fn_def = &self[id];
+        fn_def
+            .params
+            .first()
+            .map_or(false, |param| self.is_self_pat(param.pat))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct ModuleSource {
     pub vars: ArenaMap<VarId, syntax::Var>,
     pub fn_defs: ArenaMap<FnDefId, syntax::FnDef>,
     pub struct_defs: ArenaMap<StructDefId, syntax::StructDef>,
     pub enum_defs: ArenaMap<EnumDefId, syntax::EnumDef>,
+    pub impl_defs: ArenaMap<ImplDefId, syntax::ImplDef>,
+    pub trait_defs: ArenaMap<TraitDefId, syntax::TraitDef>,
+    pub type_alias_defs: ArenaMap<TypeAliasDefId, syntax::TypeAliasDef>,
+    pub const_defs: ArenaMap<ConstDefId, syntax::ConstDef>,
+    pub extern_fn_defs: ArenaMap<ExternFnDefId, syntax::ExternFnDef>,
     pub exprs: ArenaMap<ExprId, syntax::Expr>,
     pub types: ArenaMap<TypeId, syntax::Type>,
     pub pats: ArenaMap<PatId, syntax::Pat>,
@@ -106,6 +184,26 @@ impl Index<StructDefId> for ModuleSource {
     type Output = syntax::StructDef;
     fn index(&self, id: StructDefId) -> &Self::Output { &self.struct_defs[id] }
 }
+impl Index<ImplDefId> for ModuleSource {
+    type Output = syntax::ImplDef;
+    fn index(&self, id: ImplDefId) -> &Self::Output { &self.impl_defs[id] }
+}
+impl Index<TraitDefId> for ModuleSource {
+    type Output = syntax::TraitDef;
+    fn index(&self, id: TraitDefId) -> &Self::Output { &self.trait_defs[id] }
+}
+impl Index<TypeAliasDefId> for ModuleSource {
+    type Output = syntax::TypeAliasDef;
+    fn index(&self, id: TypeAliasDefId) -> &Self::Output { &self.type_alias_defs[id] }
+}
+impl Index<ConstDefId> for ModuleSource {
+    type Output = syntax::ConstDef;
+    fn index(&self, id: ConstDefId) -> &Self::Output { &self.const_defs[id] }
+}
+impl Index<ExternFnDefId> for ModuleSource {
+    type Output = syntax::ExternFnDef;
+    fn index(&self, id: ExternFnDefId) -> &Self::Output { &self.extern_fn_defs[id] }
+}
 impl Index<ExprId> for ModuleSource {
     type Output = syntax::Expr;
     fn index(&self, id: ExprId) -> &Self::Output { &self.exprs[id] }
@@ -124,14 +222,33 @@ pub enum Decl {
     Fn(FnDefId),
     Struct(StructDefId),
     Enum(EnumDefId),
+    Impl(ImplDefId),
+    Trait(TraitDefId),
+    TypeAlias(TypeAliasDefId),
+    Const(ConstDefId),
+    ExternFn(ExternFnDefId),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FnDef {
     pub name: VarId,
+    pub generics: Vec<GenericParam>,
     pub params: Vec<Param>,
     pub ret_type: Option<TypeId>,
     pub expr: ExprId,
+    /// Lints suppressed by `#[allow(...)]` on this fn.
+    pub allow: Vec<Lint>,
+}
+
+impl FnDef {
+    pub fn is_generic(&self) -> bool { !self.generics.is_empty() }
+}
+
+/// A type parameter of a generic fn, with the traits it is bounded by.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GenericParam {
+    pub name: VarId,
+    pub bounds: Vec<TypeId>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -152,6 +269,55 @@ pub struct EnumDef {
     pub variants: Vec<EnumVariant>,
 }
 
+/// `impl ty { fns }` or `impl trait_ for ty { fns }`. The fns are not visible
+/// at the top level, and are called as `ty::name(args)`, or as
+/// `receiver.name(args)` if their first parameter is `self`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImplDef {
+    pub trait_: Option<TypeId>,
+    pub ty: TypeId,
+    pub fns: Vec<FnDefId>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TraitDef {
+    pub name: VarId,
+    pub fns: Vec<FnSig>,
+}
+
+/// `type name = ty;`. Uses of `name` as a type stand for `ty`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TypeAliasDef {
+    pub name: VarId,
+    pub ty: TypeId,
+}
+
+/// `const name: ty = expr;`. `expr` is evaluated at compile time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ConstDef {
+    pub name: VarId,
+    pub ty: TypeId,
+    pub expr: ExprId,
+}
+
+/// `extern "C" fn name(params) -> ret_type;`. Defined outside the program and
+/// called with the C calling convention, so every parameter must have a type.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExternFnDef {
+    pub name: VarId,
+    pub params: Vec<Param>,
+    pub ret_type: Option<TypeId>,
+}
+
+/// In a `FnSig`, `Self` and an unannotated `self` parameter stand for the
+/// type implementing the trait.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FnSig {
+    pub name: VarId,
+    pub params: Vec<Param>,
+    pub ret_type: Option<TypeId>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct EnumVariant {
     pub name: VarId,
@@ -184,36 +350,69 @@ pub enum Expr {
     },
     Unop {
         op: Unop,
+        op_span: TextRange,
         expr: ExprId,
     },
     Binop {
         lhs: ExprId,
         op: Binop,
+        op_span: TextRange,
         rhs: ExprId,
     },
+    Cast {
+        expr: ExprId,
+        ty: TypeId,
+    },
     Call {
         func: ExprId,
         args: Vec<ExprId>,
     },
+    MethodCall {
+        receiver: ExprId,
+        method: VarId,
+        args: Vec<ExprId>,
+    },
+    Path {
+        ty: VarId,
+        name: VarId,
+    },
     Block {
         stmts: Vec<Stmt>,
         expr: Option<ExprId>,
     },
-    Loop(ExprId),
+    Loop {
+        label: Option<Label>,
+        body: ExprId,
+    },
     If {
         test: ExprId,
         then_branch: ExprId,
         else_branch: Option<ExprId>,
     },
-    Break(Option<ExprId>),
+    Match {
+        test: ExprId,
+        arms: Vec<MatchArm>,
+    },
+    Break {
+        label: Option<Label>,
+        expr: Option<ExprId>,
+    },
     Return(Option<ExprId>),
-    Continue,
+    Continue {
+        label: Option<Label>,
+    },
     Lambda {
         params: Vec<Param>,
         expr: ExprId,
     },
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MatchArm {
+    pub pat: PatId,
+    pub expr: ExprId,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub struct FieldInit {
     pub name: VarId,
@@ -231,14 +430,25 @@ pub enum Unop {
     Not,
     Add,
     Sub,
+    BitNot,
 }
 
 #[derive(Debug, Display, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Binop {
     Lazy(LazyBinop),
     Arithmetic(ArithmeticBinop),
+    Bit(BitBinop),
     Cmp(CmpBinop),
     Assign,
+    #[display(fmt = "{}=", _0)]
+    CompoundAssign(CompoundBinop),
+}
+
+/// The operator of a compound assignment such as `x += 1`.
+#[derive(Debug, Display, Copy, Clone, PartialEq, Eq, Hash, From)]
+pub enum CompoundBinop {
+    Arithmetic(ArithmeticBinop),
+    Bit(BitBinop),
 }
 
 #[derive(Debug, Display, Copy, Clone, PartialEq, Eq, Hash)]
@@ -259,6 +469,22 @@ pub enum ArithmeticBinop {
     Mul,
     #[display(fmt = "/")]
     Div,
+    #[display(fmt = "%")]
+    Rem,
+}
+
+#[derive(Debug, Display, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum BitBinop {
+    #[display(fmt = "&")]
+    And,
+    #[display(fmt = "|")]
+    Or,
+    #[display(fmt = "^")]
+    Xor,
+    #[display(fmt = "<<")]
+    Shl,
+    #[display(fmt = ">>")]
+    Shr,
 }
 
 #[derive(Debug, Display, Copy, Clone, PartialEq, Eq, Hash)]
@@ -277,12 +503,59 @@ pub enum CmpBinop {
     GreaterEq,
 }
 
+impl Unop {
+    /// The builtin trait through which the operator is overloaded for
+    /// structs and enums, eg `Neg` for `-`.
+    pub const fn operator_trait(self) -> Option<Builtin> {
+        match self {
+            Self::Sub => Some(Builtin::Neg),
+            Self::Not | Self::Add | Self::BitNot => None,
+        }
+    }
+}
+
+impl Binop {
+    /// `!=` is the negation of `Eq::eq`, and every ordering is derived from
+    /// `Ord::lt` by swapping and/or negating.
+    pub const fn operator_trait(self) -> Option<Builtin> {
+        match self {
+            Self::Arithmetic(op) | Self::CompoundAssign(CompoundBinop::Arithmetic(op)) => {
+                Some(op.operator_trait())
+            }
+            Self::Cmp(CmpBinop::Eq | CmpBinop::NotEq) => Some(Builtin::Eq),
+            Self::Cmp(_) => Some(Builtin::Ord),
+            Self::Lazy(_) | Self::Bit(_) | Self::Assign | Self::CompoundAssign(_) => None,
+        }
+    }
+}
+
+impl ArithmeticBinop {
+    pub const fn operator_trait(self) -> Builtin {
+        match self {
+            Self::Add => Builtin::Add,
+            Self::Sub => Builtin::Sub,
+            Self::Mul => Builtin::Mul,
+            Self::Div => Builtin::Div,
+            Self::Rem => Builtin::Rem,
+        }
+    }
+}
+
 impl From<syntax::Unop> for Unop {
     fn from(op: syntax::Unop) -> Self {
         match op {
             syntax::Unop::Not(_) => Self::Not,
             syntax::Unop::Add(_) => Self::Add,
             syntax::Unop::Sub(_) => Self::Sub,
+            syntax::Unop::BitNot(_) => Self::BitNot,
+        }
+    }
+}
+impl From<CompoundBinop> for Binop {
+    fn from(op: CompoundBinop) -> Self {
+        match op {
+            CompoundBinop::Arithmetic(op) => Self::Arithmetic(op),
+            CompoundBinop::Bit(op) => Self::Bit(op),
         }
     }
 }
@@ -295,7 +568,23 @@ impl From<syntax::Binop> for Binop {
             syntax::Binop::Sub(_) => Self::Arithmetic(ArithmeticBinop::Sub),
             syntax::Binop::Mul(_) => Self::Arithmetic(ArithmeticBinop::Mul),
             syntax::Binop::Div(_) => Self::Arithmetic(ArithmeticBinop::Div),
+            syntax::Binop::Rem(_) => Self::Arithmetic(ArithmeticBinop::Rem),
+            syntax::Binop::BitAnd(_) => Self::Bit(BitBinop::And),
+            syntax::Binop::BitOr(_) => Self::Bit(BitBinop::Or),
+            syntax::Binop::BitXor(_) => Self::Bit(BitBinop::Xor),
+            syntax::Binop::Shl(_) => Self::Bit(BitBinop::Shl),
+            syntax::Binop::Shr(_) => Self::Bit(BitBinop::Shr),
             syntax::Binop::Assign(_) => Self::Assign,
+            syntax::Binop::AddAssign(_) => Self::CompoundAssign(ArithmeticBinop::Add.into()),
+            syntax::Binop::SubAssign(_) => Self::CompoundAssign(ArithmeticBinop::Sub.into()),
+            syntax::Binop::MulAssign(_) => Self::CompoundAssign(ArithmeticBinop::Mul.into()),
+            syntax::Binop::DivAssign(_) => Self::CompoundAssign(ArithmeticBinop::Div.into()),
+            syntax::Binop::RemAssign(_) => Self::CompoundAssign(ArithmeticBinop::Rem.into()),
+            syntax::Binop::BitAndAssign(_) => Self::CompoundAssign(BitBinop::And.into()),
+            syntax::Binop::BitOrAssign(_) => Self::CompoundAssign(BitBinop::Or.into()),
+            syntax::Binop::BitXorAssign(_) => Self::CompoundAssign(BitBinop::Xor.into()),
+            syntax::Binop::ShlAssign(_) => Self::CompoundAssign(BitBinop::Shl.into()),
+            syntax::Binop::ShrAssign(_) => Self::CompoundAssign(BitBinop::Shr.into()),
             syntax::Binop::Eq(_) => Self::Cmp(CmpBinop::Eq),
             syntax::Binop::NotEq(_) => Self::Cmp(CmpBinop::NotEq),
             syntax::Binop::Less(_) => Self::Cmp(CmpBinop::Less),
@@ -319,16 +608,99 @@ pub enum Stmt {
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Lit {
     Bool(bool),
-    Int(u32),
-    Float(OrderedFloat<f32>),
+    Int(u64, Option<NumSuffix>),
+    Float(OrderedFloat<f64>, Option<NumSuffix>),
     Char(char),
 }
 
+/// The type a numeric literal is given by its suffix, eg `u8` in `10u8`.
+#[derive(Debug, Display, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum NumSuffix {
+    #[display(fmt = "i8")]
+    I8,
+    #[display(fmt = "i16")]
+    I16,
+    #[display(fmt = "i32")]
+    I32,
+    #[display(fmt = "i64")]
+    I64,
+    #[display(fmt = "u8")]
+    U8,
+    #[display(fmt = "u16")]
+    U16,
+    #[display(fmt = "u32")]
+    U32,
+    #[display(fmt = "u64")]
+    U64,
+    #[display(fmt = "f32")]
+    F32,
+    #[display(fmt = "f64")]
+    F64,
+}
+
+impl NumSuffix {
+    const ALL: [Self; 10] = [
+        Self::I8,
+        Self::I16,
+        Self::I32,
+        Self::I64,
+        Self::U8,
+        Self::U16,
+        Self::U32,
+        Self::U64,
+        Self::F32,
+        Self::F64,
+    ];
+
+    /// Splits a numeric literal into its digits and its suffix, if any.
+    pub fn split(text: &str) -> (&str, Option<Self>) {
+        Self::ALL
+            .iter()
+            .find_map(|suffix| {
+                let digits = text.strip_suffix(suffix.to_string().as_str())?;
+                Some((digits, Some(*suffix)))
+            })
+            .unwrap_or((text, None))
+    }
+
+    /// The largest integer literal with this suffix. A `negated` signed
+    /// literal can be one more than the type's maximum, so that eg `-128i8`
+    /// can be written.
+    pub fn max_int(self, negated: bool) -> Option<u64> {
+        let signed_max = |bits: u32| (1 << (bits - 1)) - u64::from(!negated);
+        match self {
+            Self::I8 => Some(signed_max(8)),
+            Self::I16 => Some(signed_max(16)),
+            Self::I32 => Some(signed_max(32)),
+            Self::I64 => Some(signed_max(64)),
+            Self::U8 => Some(u64::from(u8::MAX)),
+            Self::U16 => Some(u64::from(u16::MAX)),
+            Self::U32 => Some(u64::from(u32::MAX)),
+            Self::U64 => Some(u64::MAX),
+            Self::F32 | Self::F64 => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Pat {
-    Var(VarId),
+    Var {
+        var: VarId,
+        is_mut: bool,
+    },
     Ignore,
     Tuple(Vec<PatId>),
+    Enum {
+        name: VarId,
+        variant: VarId,
+        fields: Vec<FieldPat>,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FieldPat {
+    pub name: VarId,
+    pub pat: PatId,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
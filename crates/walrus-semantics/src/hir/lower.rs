@@ -1,9 +1,12 @@
 use super::*;
 use crate::diagnostic::LitError;
 use ordered_float::OrderedFloat;
+use text_size::{TextRange, TextSize};
 
 pub fn lower(syntax: &syntax::SourceFile) -> Module {
     let mut ctx = Ctx::default();
+    ctx.diagnostics
+        .extend(syntax.lex_errors.iter().copied().map(Diagnostic::LexError));
     let decls = syntax
         .decls
         .iter()
@@ -53,6 +56,44 @@ impl Ctx {
         id
     }
 
+    fn alloc_impl_def(&mut self, syntax: syntax::ImplDef, hir: ImplDef) -> ImplDefId {
+        let id = self.data.impl_defs.alloc(hir);
+        self.source.impl_defs.insert(id, syntax);
+        id
+    }
+
+    fn alloc_trait_def(&mut self, syntax: syntax::TraitDef, hir: TraitDef) -> TraitDefId {
+        let id = self.data.trait_defs.alloc(hir);
+        self.source.trait_defs.insert(id, syntax);
+        id
+    }
+
+    fn alloc_type_alias_def(
+        &mut self,
+        syntax: syntax::TypeAliasDef,
+        hir: TypeAliasDef,
+    ) -> TypeAliasDefId {
+        let id = self.data.type_alias_defs.alloc(hir);
+        self.source.type_alias_defs.insert(id, syntax);
+        id
+    }
+
+    fn alloc_const_def(&mut self, syntax: syntax::ConstDef, hir: ConstDef) -> ConstDefId {
+        let id = self.data.const_defs.alloc(hir);
+        self.source.const_defs.insert(id, syntax);
+        id
+    }
+
+    fn alloc_extern_fn_def(
+        &mut self,
+        syntax: syntax::ExternFnDef,
+        hir: ExternFnDef,
+    ) -> ExternFnDefId {
+        let id = self.data.extern_fn_defs.alloc(hir);
+        self.source.extern_fn_defs.insert(id, syntax);
+        id
+    }
+
     fn alloc_expr(&mut self, syntax: syntax::Expr, hir: Expr) -> ExprId {
         let id = self.data.exprs.alloc(hir);
         self.source.exprs.insert(id, syntax);
@@ -80,9 +121,14 @@ impl Ctx {
 
     fn lower_decl(&mut self, syntax: &syntax::Decl) -> Decl {
         match syntax {
-            syntax::Decl::Fn(syntax) => Decl::Fn(self.lower_fn_def(syntax)),
+            syntax::Decl::Fn(syntax) => Decl::Fn(self.lower_fn_def(syntax, None)),
             syntax::Decl::Struct(syntax) => Decl::Struct(self.lower_struct_def(syntax)),
             syntax::Decl::Enum(syntax) => Decl::Enum(self.lower_enum_def(syntax)),
+            syntax::Decl::Impl(syntax) => Decl::Impl(self.lower_impl_def(syntax)),
+            syntax::Decl::Trait(syntax) => Decl::Trait(self.lower_trait_def(syntax)),
+            syntax::Decl::TypeAlias(syntax) => Decl::TypeAlias(self.lower_type_alias_def(syntax)),
+            syntax::Decl::Const(syntax) => Decl::Const(self.lower_const_def(syntax)),
+            syntax::Decl::ExternFn(syntax) => Decl::ExternFn(self.lower_extern_fn_def(syntax)),
         }
     }
 
@@ -103,16 +149,146 @@ impl Ctx {
             .collect()
     }
 
-    fn lower_fn_def(&mut self, syntax: &syntax::FnDef) -> FnDefId {
+    /// `self_ty` is the type of the enclosing `impl` block, if any. It is
+    /// given to a `self` parameter without a type annotation.
+    fn lower_fn_def(&mut self, syntax: &syntax::FnDef, self_ty: Option<&syntax::Type>) -> FnDefId {
+        let name = self.lower_var(syntax.name.clone());
+        let generics = syntax
+            .generics
+            .iter()
+            .flat_map(|generics| generics.0.inner.iter())
+            .map(|param| self.lower_generic_param(param))
+            .collect();
+        let mut params = self.lower_param_list(&syntax.params);
+        if let (Some(self_ty), Some(param)) = (self_ty, params.first_mut()) {
+            if param.ty.is_none() && self.data.is_self_pat(param.pat) {
+                param.ty = Some(self.lower_type(self_ty));
+            }
+        }
         let hir = FnDef {
-            name: self.lower_var(syntax.name.clone()),
-            params: self.lower_param_list(&syntax.params),
+            name,
+            generics,
+            params,
             ret_type: syntax.ret.as_ref().map(|ret| self.lower_type(&ret.ty)),
             expr: self.lower_expr(&syntax.expr),
+            allow: self.lower_attrs(&syntax.attrs),
         };
         self.alloc_fn_def(syntax.clone(), hir)
     }
 
+    fn lower_generic_param(&mut self, syntax: &syntax::GenericParam) -> GenericParam {
+        GenericParam {
+            name: self.lower_var(syntax.name.clone()),
+            bounds: syntax
+                .bounds
+                .iter()
+                .flat_map(|bounds| bounds.traits.iter())
+                .map(|name| self.lower_type(&syntax::Type::Var(name.clone())))
+                .collect(),
+        }
+    }
+
+    /// The lints named by `#[allow(...)]` attributes.
+    fn lower_attrs(&mut self, syntax: &[syntax::Attr]) -> Vec<Lint> {
+        let mut lints = Vec::new();
+        for attr in syntax {
+            let body = &attr.body.inner;
+            let name = self.lower_var(body.name.clone());
+            if self.data[name].as_str() != "allow" {
+                self.diagnostics.push(Diagnostic::UnknownAttr(name));
+                continue;
+            }
+            for arg in body.args.inner.iter() {
+                let arg = self.lower_var(arg.clone());
+                match Lint::from_name(self.data[arg].as_str()) {
+                    Some(lint) => lints.push(lint),
+                    None => self.diagnostics.push(Diagnostic::UnknownLint(arg)),
+                }
+            }
+        }
+        lints
+    }
+
+    fn lower_impl_def(&mut self, syntax: &syntax::ImplDef) -> ImplDefId {
+        let hir = ImplDef {
+            trait_: syntax
+                .trait_ref
+                .as_ref()
+                .map(|trait_ref| self.lower_type(&syntax::Type::Var(trait_ref.name.clone()))),
+            ty: self.lower_type(&syntax.ty),
+            fns: syntax
+                .fns
+                .inner
+                .iter()
+                .map(|fn_def| self.lower_fn_def(fn_def, Some(&syntax.ty)))
+                .collect(),
+        };
+        self.alloc_impl_def(syntax.clone(), hir)
+    }
+
+    fn lower_trait_def(&mut self, syntax: &syntax::TraitDef) -> TraitDefId {
+        let hir = TraitDef {
+            name: self.lower_var(syntax.name.clone()),
+            fns: syntax
+                .fns
+                .inner
+                .iter()
+                .map(|fn_sig| self.lower_fn_sig(fn_sig))
+                .collect(),
+        };
+        self.alloc_trait_def(syntax.clone(), hir)
+    }
+
+    fn lower_type_alias_def(&mut self, syntax: &syntax::TypeAliasDef) -> TypeAliasDefId {
+        let hir = TypeAliasDef {
+            name: self.lower_var(syntax.name.clone()),
+            ty: self.lower_type(&syntax.ty),
+        };
+        self.alloc_type_alias_def(syntax.clone(), hir)
+    }
+
+    fn lower_const_def(&mut self, syntax: &syntax::ConstDef) -> ConstDefId {
+        let hir = ConstDef {
+            name: self.lower_var(syntax.name.clone()),
+            ty: self.lower_type(&syntax.ascription.ty),
+            expr: self.lower_expr(&syntax.expr),
+        };
+        self.alloc_const_def(syntax.clone(), hir)
+    }
+
+    fn lower_extern_fn_def(&mut self, syntax: &syntax::ExternFnDef) -> ExternFnDefId {
+        if let Some(abi) = &syntax.abi {
+            if abi.text != "\"C\"" {
+                self.diagnostics.push(Diagnostic::UnknownAbi(abi.clone()));
+            }
+        }
+        let FnSig {
+            name,
+            params,
+            ret_type,
+        } = self.lower_fn_sig(&syntax.sig);
+        for param in &params {
+            if param.ty.is_none() {
+                self.diagnostics
+                    .push(Diagnostic::ExternParamNeedsType(param.pat));
+            }
+        }
+        let hir = ExternFnDef {
+            name,
+            params,
+            ret_type,
+        };
+        self.alloc_extern_fn_def(syntax.clone(), hir)
+    }
+
+    fn lower_fn_sig(&mut self, syntax: &syntax::FnSig) -> FnSig {
+        FnSig {
+            name: self.lower_var(syntax.name.clone()),
+            params: self.lower_param_list(&syntax.params),
+            ret_type: syntax.ret.as_ref().map(|ret| self.lower_type(&ret.ty)),
+        }
+    }
+
     fn lower_struct_def(&mut self, syntax: &syntax::StructDef) -> StructDefId {
         let hir = StructDef {
             name: self.lower_var(syntax.name.clone()),
@@ -176,12 +352,28 @@ impl Ctx {
 
     fn lower_pat(&mut self, syntax: &syntax::Pat) -> PatId {
         let hir = match syntax {
-            syntax::Pat::Var(var) => Pat::Var(self.lower_var(var.clone())),
+            syntax::Pat::Var { kw_mut, var } => Pat::Var {
+                var: self.lower_var(var.clone()),
+                is_mut: kw_mut.is_some(),
+            },
             syntax::Pat::Ignore(_) => Pat::Ignore,
             syntax::Pat::Paren(pat) => return self.lower_pat(&pat.inner),
             syntax::Pat::Tuple(pats) => {
                 Pat::Tuple(pats.inner.iter().map(|pat| self.lower_pat(pat)).collect())
             }
+            syntax::Pat::Enum(pat) => Pat::Enum {
+                name: self.lower_var(pat.name.clone()),
+                variant: self.lower_var(pat.variant.clone()),
+                fields: pat
+                    .fields
+                    .inner
+                    .iter()
+                    .map(|field| FieldPat {
+                        name: self.lower_var(field.name.clone()),
+                        pat: self.lower_pat(&field.pat),
+                    })
+                    .collect(),
+            },
         };
         self.alloc_pat(syntax.clone(), hir)
     }
@@ -224,66 +416,75 @@ impl Ctx {
                 params: self.lower_param_list(&expr.params),
                 expr: self.lower_expr(&expr.expr),
             },
-            syntax::Expr::Unary(expr) => Expr::Unop {
-                op: expr.op.into(),
-                expr: self.lower_expr(&expr.expr),
-            },
+            syntax::Expr::Unary(expr) => self.lower_unary_expr(expr),
             syntax::Expr::Binary(expr) => Expr::Binop {
                 lhs: self.lower_expr(&expr.lhs),
                 op: expr.op.into(),
+                op_span: expr.op.span(),
                 rhs: self.lower_expr(&expr.rhs),
             },
+            syntax::Expr::Cast(expr) => Expr::Cast {
+                expr: self.lower_expr(&expr.expr),
+                ty: self.lower_type(&expr.ty),
+            },
             syntax::Expr::Call(expr) => Expr::Call {
                 func: self.lower_expr(&expr.func),
-                args: expr
-                    .args
-                    .0
-                    .inner
-                    .iter()
-                    .map(|expr| self.lower_expr(expr))
-                    .collect(),
+                args: self.lower_args(&expr.args),
+            },
+            syntax::Expr::MethodCall(expr) => Expr::MethodCall {
+                receiver: self.lower_expr(&expr.receiver),
+                method: self.lower_var(expr.method.clone()),
+                args: self.lower_args(&expr.args),
+            },
+            syntax::Expr::Path(expr) => Expr::Path {
+                ty: self.lower_var(expr.ty.clone()),
+                name: self.lower_var(expr.name.clone()),
             },
             syntax::Expr::Field(expr) => Expr::Field {
                 expr: self.lower_expr(&expr.base),
                 field: match &expr.field {
-                    syntax::Field::Tuple(int) => Field::Tuple(self.lower_int(&int.text, 10)),
+                    syntax::Field::Tuple(int) => Field::Tuple(self.lower_tuple_field(int)),
                     syntax::Field::Named(var) => Field::Named(self.lower_var(var.clone())),
                 },
             },
             syntax::Expr::Struct(expr) => Expr::Struct {
                 name: self.lower_var(expr.name.clone()),
-                fields: expr
-                    .fields
-                    .inner
-                    .iter()
-                    .map(|field| FieldInit {
-                        name: self.lower_var(field.name.clone()),
-                        val: self.lower_expr(&field.val),
-                    })
-                    .collect(),
+                fields: self.lower_field_inits(&expr.fields),
             },
             syntax::Expr::Enum(expr) => Expr::Enum {
                 name: self.lower_var(expr.name.clone()),
                 variant: self.lower_var(expr.variant.clone()),
-                fields: expr
-                    .fields
+                fields: self.lower_field_inits(&expr.fields),
+            },
+            syntax::Expr::If(expr) => self.lower_if_expr(expr),
+            syntax::Expr::Match(expr) => Expr::Match {
+                test: self.lower_expr(&expr.test_expr),
+                arms: expr
+                    .arms
                     .inner
                     .iter()
-                    .map(|field| FieldInit {
-                        name: self.lower_var(field.name.clone()),
-                        val: self.lower_expr(&field.val),
+                    .map(|arm| MatchArm {
+                        pat: self.lower_pat(&arm.pat),
+                        expr: self.lower_expr(&arm.expr),
                     })
                     .collect(),
             },
-            syntax::Expr::If(expr) => self.lower_if_expr(expr),
             syntax::Expr::Return(expr) => {
                 Expr::Return(expr.expr.as_ref().map(|expr| self.lower_expr(expr)))
             }
-            syntax::Expr::Break(expr) => {
-                Expr::Break(expr.expr.as_ref().map(|expr| self.lower_expr(expr)))
-            }
-            syntax::Expr::Continue(_) => Expr::Continue,
-            syntax::Expr::Loop(expr) => Expr::Loop(self.lower_expr(&expr.expr)),
+            syntax::Expr::Break(expr) => Expr::Break {
+                label: expr.label.clone().map(Label::from),
+                expr: expr.expr.as_ref().map(|expr| self.lower_expr(expr)),
+            },
+            syntax::Expr::Continue(expr) => Expr::Continue {
+                label: expr.label.clone().map(Label::from),
+            },
+            syntax::Expr::Loop(expr) => Expr::Loop {
+                label: Self::lower_label_decl(&expr.label),
+                body: self.lower_expr(&expr.expr),
+            },
+            syntax::Expr::While(expr) => self.lower_while_expr(syntax, expr),
+            syntax::Expr::For(expr) => self.lower_for_expr(syntax, expr),
             syntax::Expr::Block(block) => {
                 let stmts = block
                     .stmts
@@ -301,6 +502,46 @@ impl Ctx {
         self.alloc_expr(syntax.clone(), hir)
     }
 
+    fn lower_args(&mut self, syntax: &syntax::ArgList) -> Vec<ExprId> {
+        syntax
+            .0
+            .inner
+            .iter()
+            .map(|expr| self.lower_expr(expr))
+            .collect()
+    }
+
+    fn lower_field_inits(
+        &mut self,
+        syntax: &syntax::Curly<syntax::Punctuated0<syntax::FieldInit, syntax::Comma>>,
+    ) -> Vec<FieldInit> {
+        syntax
+            .inner
+            .iter()
+            .map(|field| FieldInit {
+                name: self.lower_var(field.name.clone()),
+                val: self.lower_expr(&field.val),
+            })
+            .collect()
+    }
+
+    /// The operand of `-lit` is lowered as a negated literal, so that eg
+    /// `-128i8` is not out of range.
+    fn lower_unary_expr(&mut self, syntax: &syntax::UnaryExpr) -> Expr {
+        let expr = match (&syntax.op, syntax.expr.as_ref()) {
+            (syntax::Unop::Sub(_), syntax::Expr::Lit(syntax::Lit::Int(int))) => {
+                let lit = Expr::Lit(self.lower_int_lit(int, true));
+                self.alloc_expr(syntax.expr.as_ref().clone(), lit)
+            }
+            _ => self.lower_expr(&syntax.expr),
+        };
+        Expr::Unop {
+            op: syntax.op.into(),
+            op_span: syntax.op.span(),
+            expr,
+        }
+    }
+
     fn lower_if_expr(&mut self, syntax: &syntax::IfExpr) -> Expr {
         Expr::If {
             test: self.lower_expr(&syntax.test_expr),
@@ -313,51 +554,263 @@ impl Ctx {
         }
     }
 
+    fn lower_label_decl(label: &Option<syntax::LabelDecl>) -> Option<Label> {
+        label.as_ref().map(|decl| Label::from(decl.label.clone()))
+    }
+
+    /// Desugars `while test { body }` to `loop { if !test { break }; body; }`.
+    fn lower_while_expr(&mut self, syntax: &syntax::Expr, while_expr: &syntax::WhileExpr) -> Expr {
+        let test = self.lower_expr(&while_expr.test_expr);
+        let body = self.lower_expr(&while_expr.expr);
+        Expr::Loop {
+            label: Self::lower_label_decl(&while_expr.label),
+            body: self.lower_loop_body(syntax, test, vec![Stmt::Expr(body)]),
+        }
+    }
+
+    /// Desugars `for pat in start..end { body }` to
+    /// ```text
+    /// {
+    ///     let mut for.iter = start;
+    ///     let for.end = end;
+    ///     loop {
+    ///         if !(for.iter < for.end) { break };
+    ///         let pat = for.iter;
+    ///         for.iter += 1;
+    ///         body;
+    ///     }
+    /// }
+    /// ```
+    /// `for.iter` and `for.end` are not valid identifiers, so user code cannot
+    /// refer to them. The counter is incremented before `body` runs, so that
+    /// `continue` moves on to the next iteration.
+    /// `start` and `end` are recorded in `for_ranges`, so that inference
+    /// expects them to be integers.
+    fn lower_for_expr(&mut self, syntax: &syntax::Expr, for_expr: &syntax::ForExpr) -> Expr {
+        let span = for_expr.kw_for.span;
+        let iter_var = syntax::Var(syntax::Ident {
+            span,
+            text: SmolStr::from("for.iter"),
+        });
+        let end_var = syntax::Var(syntax::Ident {
+            span,
+            text: SmolStr::from("for.end"),
+        });
+
+        let start = self.lower_expr(&for_expr.start);
+        let iter_pat = self.lower_desugared_pat(&iter_var, true);
+        let end = self.lower_expr(&for_expr.end);
+        let end_pat = self.lower_desugared_pat(&end_var, false);
+        self.data.for_ranges.push((start, end));
+
+        let test = Expr::Binop {
+            lhs: self.lower_desugared_var_expr(syntax, &iter_var),
+            op: Binop::Cmp(CmpBinop::Less),
+            op_span: for_expr.dot_dot.span,
+            rhs: self.lower_desugared_var_expr(syntax, &end_var),
+        };
+        let test = self.alloc_expr(syntax.clone(), test);
+
+        let pat = self.lower_pat(&for_expr.pat);
+        let iter = self.lower_desugared_var_expr(syntax, &iter_var);
+        let increment = Expr::Binop {
+            lhs: self.lower_desugared_var_expr(syntax, &iter_var),
+            op: Binop::CompoundAssign(ArithmeticBinop::Add.into()),
+            op_span: for_expr.dot_dot.span,
+            rhs: self.alloc_expr(syntax.clone(), Expr::Lit(Lit::Int(1, None))),
+        };
+        let increment = self.alloc_expr(syntax.clone(), increment);
+        let body = self.lower_expr(&for_expr.expr);
+
+        let loop_body = self.lower_loop_body(
+            syntax,
+            test,
+            vec![
+                Stmt::Let {
+                    pat,
+                    ty: None,
+                    expr: iter,
+                },
+                Stmt::Expr(increment),
+                Stmt::Expr(body),
+            ],
+        );
+        let loop_expr = Expr::Loop {
+            label: Self::lower_label_decl(&for_expr.label),
+            body: loop_body,
+        };
+        let loop_expr = self.alloc_expr(syntax.clone(), loop_expr);
+        Expr::Block {
+            stmts: vec![
+                Stmt::Let {
+                    pat: iter_pat,
+                    ty: None,
+                    expr: start,
+                },
+                Stmt::Let {
+                    pat: end_pat,
+                    ty: None,
+                    expr: end,
+                },
+            ],
+            expr: Some(loop_expr),
+        }
+    }
+
+    /// `{ if !test { break }; stmts }`, the body of a desugared loop. The
+    /// `break` comes first, so that it fixes the loop's type to `()` before any
+    /// `break` in `stmts` is inferred.
+    fn lower_loop_body(&mut self, syntax: &syntax::Expr, test: ExprId, stmts: Vec<Stmt>) -> ExprId {
+        // `!` cannot fail, so the loop's keyword is as good a location as any
+        let op_span = match syntax {
+            syntax::Expr::While(expr) => expr.kw_while.span,
+            syntax::Expr::For(expr) => expr.kw_for.span,
+            _ => unreachable!("not a loop: {syntax:?}"),
+        };
+        let not_test = Expr::Unop {
+            op: Unop::Not,
+            op_span,
+            expr: test,
+        };
+        let not_test = self.alloc_expr(syntax.clone(), not_test);
+        let exit = Expr::Break {
+            label: None,
+            expr: None,
+        };
+        let exit = self.alloc_expr(syntax.clone(), exit);
+        let exit = Expr::If {
+            test: not_test,
+            then_branch: exit,
+            else_branch: None,
+        };
+        let exit = self.alloc_expr(syntax.clone(), exit);
+        let stmts = std::iter::once(Stmt::Expr(exit)).chain(stmts).collect();
+        self.alloc_expr(syntax.clone(), Expr::Block { stmts, expr: None })
+    }
+
+    fn lower_desugared_pat(&mut self, var: &syntax::Var, is_mut: bool) -> PatId {
+        let kw_mut = if is_mut {
+            Some(syntax::KwMut { span: var.0.span })
+        } else {
+            None
+        };
+        let hir = Pat::Var {
+            var: self.lower_var(var.clone()),
+            is_mut,
+        };
+        let syntax = syntax::Pat::Var {
+            kw_mut,
+            var: var.clone(),
+        };
+        self.alloc_pat(syntax, hir)
+    }
+
+    fn lower_desugared_var_expr(&mut self, syntax: &syntax::Expr, var: &syntax::Var) -> ExprId {
+        let hir = Expr::Var(self.lower_var(var.clone()));
+        self.alloc_expr(syntax.clone(), hir)
+    }
+
     fn lower_lit(&mut self, syntax: &syntax::Lit) -> Lit {
-        use syntax::{BoolLit, FloatLit, IntLit, Lit::*};
+        use syntax::{BoolLit, FloatLit, Lit::*};
         match syntax {
             Bool(BoolLit::True(_)) => Lit::Bool(true),
             Bool(BoolLit::False(_)) => Lit::Bool(false),
-            Int(IntLit::Dec(int)) => Lit::Int(self.lower_int(&int.text, 10)),
-            Int(IntLit::Bin(int)) => Lit::Int(self.lower_int(&int.text["0b".len()..], 2)),
-            Int(IntLit::Hex(int)) => Lit::Int(self.lower_int(&int.text["0x".len()..], 16)),
-            Float(FloatLit(float)) => Lit::Float(self.lower_float(&float.text)),
-            Char(c) => Lit::Char(self.lower_char(c)),
+            Int(int) => self.lower_int_lit(int, false),
+            Float(FloatLit(float)) => self.lower_float(float.span, &float.text),
+            Char(c) => Lit::Char(self.lower_char(syntax.span(), c)),
+        }
+    }
+
+    fn lower_int_lit(&mut self, int: &syntax::IntLit, negated: bool) -> Lit {
+        use syntax::IntLit::*;
+        match int {
+            Dec(int) => self.lower_int(int.span, &int.text, 10, negated),
+            Bin(int) => self.lower_int(int.span, &int.text["0b".len()..], 2, negated),
+            Hex(int) => self.lower_int(int.span, &int.text["0x".len()..], 16, negated),
         }
     }
 
-    fn lower_int(&mut self, text: &str, radix: u32) -> u32 {
-        let text = text.replace("_", "");
-        match u32::from_str_radix(&text, radix) {
-            Ok(x) => x,
+    fn lower_int(&mut self, span: TextRange, text: &str, radix: u32, negated: bool) -> Lit {
+        // `f32` is also a valid suffix for a float, or the end of a hex int
+        let (digits, suffix) = match NumSuffix::split(text) {
+            (digits, Some(suffix)) if suffix.max_int(negated).is_some() => (digits, Some(suffix)),
+            _ => (text, None),
+        };
+        let digits = digits.replace("_", "");
+        let val = match u64::from_str_radix(&digits, radix) {
+            Ok(val) => val,
             Err(err) => {
-                self.diagnostics
-                    .push(Diagnostic::BadLit(LitError::Int(err)));
+                self.bad_lit(span, LitError::Int(err));
+                return Lit::Int(0, suffix);
+            }
+        };
+        match suffix {
+            Some(suffix) if Some(val) > suffix.max_int(negated) => {
+                self.bad_lit(span, LitError::OutOfRange(suffix));
+                Lit::Int(0, Some(suffix))
+            }
+            _ => Lit::Int(val, suffix),
+        }
+    }
+
+    /// Tuple fields are written as plain decimal ints, such as the `0` in
+    /// `x.0`.
+    fn lower_tuple_field(&mut self, int: &syntax::DecInt) -> u32 {
+        let digits = int.text.replace("_", "");
+        match digits.parse() {
+            Ok(idx) => idx,
+            Err(err) => {
+                self.bad_lit(int.span, LitError::Int(err));
                 0
             }
         }
     }
 
-    fn lower_float(&mut self, text: &str) -> OrderedFloat<f32> {
-        let text = text.replace("_", "");
-        match text.parse() {
-            Ok(x) => OrderedFloat(x),
+    /// Float literals without a suffix are parsed as `f64`, so that no
+    /// precision is lost before type inference decides the literal's width.
+    fn lower_float(&mut self, span: TextRange, text: &str) -> Lit {
+        let (digits, suffix) = NumSuffix::split(text);
+        let digits = digits.replace("_", "");
+        let val: f64 = match digits.parse() {
+            Ok(val) => val,
             Err(err) => {
-                self.diagnostics
-                    .push(Diagnostic::BadLit(LitError::Float(err)));
-                OrderedFloat(0.0)
+                self.bad_lit(span, LitError::Float(err));
+                return Lit::Float(OrderedFloat(0.0), suffix);
             }
+        };
+        let overflows = match suffix {
+            Some(NumSuffix::F32) => val.abs() > f64::from(f32::MAX),
+            _ => val.is_infinite(),
+        };
+        if overflows {
+            self.bad_lit(span, LitError::OutOfRange(suffix.unwrap_or(NumSuffix::F64)));
+            return Lit::Float(OrderedFloat(0.0), suffix);
         }
+        Lit::Float(OrderedFloat(val), suffix)
+    }
+
+    fn bad_lit(&mut self, span: TextRange, error: LitError) {
+        self.diagnostics.push(Diagnostic::BadLit { span, error });
     }
 
-    fn lower_char(&mut self, c: &syntax::CharLit) -> char {
+    fn lower_char(&mut self, span: TextRange, lit: &syntax::CharLit) -> char {
         use syntax::CharLit::*;
 
-        match c {
+        match lit {
             Simple(c) => c.text.chars().nth(1).unwrap(),
             Escaped(c) => {
-                let c = c.text.chars().nth(2).unwrap();
-                match c {
+                let escape = &c.text["'\\".len()..c.text.len() - "'".len()];
+                if let Some(digits) = escape.strip_prefix('x').filter(|d| !d.is_empty()) {
+                    // the lexer only allows two hex digits
+                    let val = u8::from_str_radix(digits, 16).unwrap();
+                    if !val.is_ascii() {
+                        self.bad_lit(span, LitError::ByteEscape(val));
+                        return '\0';
+                    }
+                    return char::from(val);
+                }
+                let escaped = escape.chars().next().unwrap();
+                match escaped {
                     'n' => '\n',
                     'r' => '\r',
                     't' => '\t',
@@ -366,22 +819,23 @@ impl Ctx {
                     '\'' => '\'',
                     '"' => '"',
                     _ => {
-                        self.diagnostics
-                            .push(Diagnostic::BadLit(LitError::EscapeChar(c)));
+                        self.bad_lit(span, LitError::EscapeChar(escaped));
                         '\0'
                     }
                 }
             }
             Unicode(c) => {
                 let text = &c.text;
-                let len = text.len();
-                let digits = &text["'\\u".len()..len - "'".len()];
-                let val = self.lower_int(digits, 16);
+                let digits = &text["'\\u{".len()..text.len() - "}'".len()];
+                // the lexer only allows one to six hex digits
+                let val = u32::from_str_radix(&digits.replace("_", ""), 16).unwrap();
                 match std::char::from_u32(val) {
                     Some(c) => c,
                     None => {
-                        self.diagnostics
-                            .push(Diagnostic::BadLit(LitError::UnicodeChar(val)));
+                        // surrogates and anything above `\u{10FFFF}`
+                        let quote = TextSize::of('\'');
+                        let escape_span = TextRange::new(span.start() + quote, span.end() - quote);
+                        self.bad_lit(escape_span, LitError::UnicodeChar(val));
                         '\0'
                     }
                 }
@@ -389,3 +843,188 @@ impl Ctx {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! test_lit {
+        ($name:ident, $src:expr, $lit:expr) => {
+            #[test]
+            fn $name() { test_lit($src, $lit, None) }
+        };
+        ($name:ident, $src:expr, $lit:expr, $error:expr) => {
+            #[test]
+            fn $name() { test_lit($src, $lit, Some($error)) }
+        };
+    }
+
+    /// Lowers the literal `src`, and compares it and the error it was
+    /// reported with, if any, with `expected`.
+    fn test_lit(src: &str, lit: Lit, error: Option<LitError>) {
+        let fn_src = format!("fn f() {{ {src} }}");
+        let syntax = walrus_parser::parse(&fn_src);
+        let hir = lower(&syntax);
+        let got = hir
+            .data
+            .exprs
+            .iter()
+            .find_map(|(_, expr)| match expr {
+                Expr::Lit(lit) => Some(*lit),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(got, lit);
+
+        // an error in a `\u{...}` escape is reported at the escape
+        let error_src = match error {
+            Some(LitError::UnicodeChar(_)) => src.trim_matches('\''),
+            _ => src,
+        };
+        let errors = hir
+            .diagnostics
+            .into_iter()
+            .map(|diagnostic| match diagnostic {
+                Diagnostic::BadLit { span, error } => {
+                    assert_eq!(&fn_src[span], error_src);
+                    error
+                }
+                diagnostic => panic!("unexpected diagnostic {:?}", diagnostic),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(errors, error.into_iter().collect::<Vec<_>>());
+    }
+
+    fn parse_int_error(src: &str) -> LitError { LitError::Int(src.parse::<u64>().unwrap_err()) }
+
+    test_lit!(int, "1_000", Lit::Int(1000, None));
+    test_lit!(int_suffix, "255u8", Lit::Int(255, Some(NumSuffix::U8)));
+    test_lit!(hex_suffix, "0xFFi64", Lit::Int(255, Some(NumSuffix::I64)));
+    test_lit!(hex_ending_in_f32, "0x1f32", Lit::Int(0x1f32, None));
+    test_lit!(signed_max, "127i8", Lit::Int(127, Some(NumSuffix::I8)));
+    test_lit!(
+        signed_min_without_minus,
+        "128i8",
+        Lit::Int(0, Some(NumSuffix::I8)),
+        LitError::OutOfRange(NumSuffix::I8)
+    );
+    test_lit!(signed_min, "-128i8", Lit::Int(128, Some(NumSuffix::I8)));
+    test_lit!(
+        signed_min_hex,
+        "-0x8000_0000_0000_0000i64",
+        Lit::Int(1 << 63, Some(NumSuffix::I64))
+    );
+    test_lit!(
+        u64_max,
+        "18446744073709551615u64",
+        Lit::Int(u64::MAX, Some(NumSuffix::U64))
+    );
+    test_lit!(
+        int_overflow,
+        "18446744073709551616",
+        Lit::Int(0, None),
+        parse_int_error("18446744073709551616")
+    );
+    test_lit!(
+        suffix_overflow,
+        "256u8",
+        Lit::Int(0, Some(NumSuffix::U8)),
+        LitError::OutOfRange(NumSuffix::U8)
+    );
+    test_lit!(
+        signed_overflow,
+        "129i8",
+        Lit::Int(0, Some(NumSuffix::I8)),
+        LitError::OutOfRange(NumSuffix::I8)
+    );
+    test_lit!(float, "2.5e-3", Lit::Float(OrderedFloat(2.5e-3), None));
+    test_lit!(
+        float_trailing_dot,
+        "1.",
+        Lit::Float(OrderedFloat(1.0), None)
+    );
+    test_lit!(
+        float_suffix,
+        "3.0f64",
+        Lit::Float(OrderedFloat(3.0), Some(NumSuffix::F64))
+    );
+    test_lit!(
+        float_overflow,
+        "1e999",
+        Lit::Float(OrderedFloat(0.0), None),
+        LitError::OutOfRange(NumSuffix::F64)
+    );
+    test_lit!(unicode_char, r"'\u{1F6_00}'", Lit::Char('😀'));
+    test_lit!(byte_escape_char, r"'\x7F'", Lit::Char('\x7F'));
+    test_lit!(null_char, r"'\0'", Lit::Char('\0'));
+    test_lit!(quote_char, r"'\''", Lit::Char('\''));
+    test_lit!(double_quote_char, r#"'\"'"#, Lit::Char('"'));
+    test_lit!(
+        non_ascii_byte_escape,
+        r"'\x80'",
+        Lit::Char('\0'),
+        LitError::ByteEscape(0x80)
+    );
+    test_lit!(
+        unknown_escape,
+        r"'\x'",
+        Lit::Char('\0'),
+        LitError::EscapeChar('x')
+    );
+    test_lit!(
+        surrogate_char,
+        r"'\u{D800}'",
+        Lit::Char('\0'),
+        LitError::UnicodeChar(0xD800)
+    );
+    test_lit!(
+        char_out_of_range,
+        r"'\u{110000}'",
+        Lit::Char('\0'),
+        LitError::UnicodeChar(0x11_0000)
+    );
+    test_lit!(
+        f32_overflow,
+        "1e39f32",
+        Lit::Float(OrderedFloat(0.0), Some(NumSuffix::F32)),
+        LitError::OutOfRange(NumSuffix::F32)
+    );
+
+    #[test]
+    fn extern_fn_errors() {
+        let syntax = walrus_parser::parse(r#"extern "Rust" fn f(x, y: Int);"#);
+        let hir = lower(&syntax);
+        let got = (hir.diagnostics.iter())
+            .map(|diagnostic| match diagnostic {
+                Diagnostic::UnknownAbi(abi) => format!("abi {}", abi.text),
+                Diagnostic::ExternParamNeedsType(pat) => match hir.data[*pat] {
+                    Pat::Var { var, .. } => format!("param {}", hir.data[var]),
+                    _ => unreachable!(),
+                },
+                _ => unreachable!("{:?}", diagnostic),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(got, [r#"abi "Rust""#, "param x"]);
+    }
+
+    #[test]
+    fn lex_errors() {
+        let src = r"fn main() -> _ { 0x } fn f() -> _ { '\u0a' } fn g() -> _ { '\u{}' }";
+        let syntax = walrus_parser::parse(src);
+        let hir = lower(&syntax);
+        let got = (hir.diagnostics.iter())
+            .map(|diagnostic| match diagnostic {
+                Diagnostic::LexError(error) => (&src[error.range], error.kind),
+                _ => unreachable!("{:?}", diagnostic),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            got,
+            [
+                ("0x", syntax::LexErrorKind::MissingDigits),
+                (r"'\u0a'", syntax::LexErrorKind::UnbracedUnicodeEscape),
+                (r"\u{}", syntax::LexErrorKind::BadUnicodeEscape),
+            ]
+        );
+    }
+}
@@ -3,15 +3,16 @@ use super::*;
 impl Expr {
     pub fn walk_child_exprs(&self, mut f: impl FnMut(ExprId)) {
         match self {
-            Self::Lit(_) | Self::Var(_) | Self::Continue => {}
+            Self::Lit(_) | Self::Var(_) | Self::Path { .. } | Self::Continue { .. } => {}
             Self::Tuple(exprs) => exprs.iter().for_each(|expr| f(*expr)),
             Self::Struct { fields, .. } | Self::Enum { fields, .. } => {
                 fields.iter().for_each(|field| f(field.val))
             }
             Self::Field { expr, .. }
             | Self::Unop { expr, .. }
+            | Self::Cast { expr, .. }
             | Self::Lambda { expr, .. }
-            | Self::Loop(expr) => f(*expr),
+            | Self::Loop { body: expr, .. } => f(*expr),
             Self::Binop { lhs, rhs, .. } => {
                 f(*lhs);
                 f(*rhs);
@@ -20,6 +21,10 @@ impl Expr {
                 f(*func);
                 args.iter().for_each(|expr| f(*expr))
             }
+            Self::MethodCall { receiver, args, .. } => {
+                f(*receiver);
+                args.iter().for_each(|expr| f(*expr))
+            }
             Self::Block { stmts, expr } => {
                 for stmt in stmts {
                     match stmt {
@@ -41,7 +46,11 @@ impl Expr {
                     f(*else_branch)
                 }
             }
-            Self::Break(expr) | Self::Return(expr) => {
+            Self::Match { test, arms } => {
+                f(*test);
+                arms.iter().for_each(|arm| f(arm.expr))
+            }
+            Self::Break { expr, .. } | Self::Return(expr) => {
                 if let Some(expr) = expr {
                     f(*expr)
                 }
@@ -66,8 +75,9 @@ impl Type {
 impl Pat {
     pub fn walk_child_pats(&self, f: impl FnMut(PatId)) {
         match self {
-            Self::Var(_) | Self::Ignore => {}
+            Self::Var { .. } | Self::Ignore => {}
             Self::Tuple(pats) => pats.iter().copied().for_each(f),
+            Self::Enum { fields, .. } => fields.iter().map(|field| field.pat).for_each(f),
         }
     }
 }
@@ -21,8 +21,12 @@
 #![feature(format_args_capture, or_patterns)]
 
 pub mod builtins;
-mod diagnostic;
+pub mod consteval;
+pub mod diagnostic;
+pub mod fix;
+pub mod fold;
 pub mod hir;
+pub mod lint;
 pub mod scopes;
 pub mod ty;
 
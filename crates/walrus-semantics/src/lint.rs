@@ -0,0 +1,371 @@
+//! Warnings about code that is well typed but probably a mistake:
+//! * `unused_var`: a variable binding that is never referenced,
+//! * `unused_fn`: a fn that cannot be reached from `main`,
+//! * `unreachable_code`: the statements of a block after one that never returns
+//!   (eg `return` or `exit(1)`),
+//! * `unused_result`: `let _ = expr` where `expr` is not of type `()`.
+//!
+//! A lint is suppressed for the whole body of a fn, including its lambdas, by
+//! `#[allow(name)]` on the fn. Bindings and fns whose names start with `_`
+//! are never reported.
+
+use crate::{
+    diagnostic::Diagnostic,
+    hir::*,
+    scopes::{Denotation, Scopes},
+    ty::{InferenceResult, MethodCall, Type},
+};
+use arena::ArenaMap;
+use std::collections::HashSet;
+
+pub fn lint(module: &Module, scopes: &Scopes, types: &InferenceResult) -> Vec<Diagnostic> {
+    let mut ctx = Ctx {
+        module: &module.data,
+        scopes,
+        types,
+        calls: ArenaMap::default(),
+        diagnostics: Vec::new(),
+    };
+    for (id, fn_def) in module.data.fn_defs.iter() {
+        ctx.lint_fn(id, fn_def);
+    }
+    ctx.lint_unused_fns(module);
+    ctx.diagnostics
+}
+
+struct Ctx<'a> {
+    module: &'a ModuleData,
+    scopes: &'a Scopes,
+    types: &'a InferenceResult,
+    /// The fns referenced from the body of each fn.
+    calls: ArenaMap<FnDefId, Vec<FnDefId>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// What one fn body binds and refers to.
+#[derive(Default)]
+struct FnUses {
+    bindings: Vec<PatId>,
+    used_pats: HashSet<PatId>,
+    used_fns: Vec<FnDefId>,
+}
+
+impl Ctx<'_> {
+    fn lint_fn(&mut self, id: FnDefId, fn_def: &FnDef) {
+        let mut uses = FnUses::default();
+        for param in &fn_def.params {
+            self.collect_bindings(&mut uses, param.pat);
+        }
+        self.lint_expr(&mut uses, fn_def, fn_def.expr);
+
+        for pat in uses.bindings {
+            if !uses.used_pats.contains(&pat) {
+                self.report(fn_def, Diagnostic::UnusedVar(pat));
+            }
+        }
+        self.calls.insert(id, uses.used_fns);
+    }
+
+    fn lint_expr(&mut self, uses: &mut FnUses, fn_def: &FnDef, id: ExprId) {
+        let module = self.module;
+        match &module[id] {
+            Expr::Var(var) => match self.scopes.lookup_expr(id, &module[*var]) {
+                Some(Denotation::Local(pat)) => {
+                    uses.used_pats.insert(pat);
+                }
+                Some(Denotation::Fn(fn_id)) => uses.used_fns.push(fn_id),
+                _ => {}
+            },
+            Expr::Path { ty, name } => {
+                let method = self
+                    .scopes
+                    .lookup_expr(id, &module[*ty])
+                    .and_then(|denotation| self.scopes.lookup_method(denotation, &module[*name]));
+                uses.used_fns.extend(method);
+            }
+            Expr::MethodCall { .. } => {
+                if let Some(MethodCall::Fn(fn_id)) = self.types.method_calls.get(id) {
+                    uses.used_fns.push(*fn_id);
+                }
+            }
+            Expr::Block { stmts, expr } => {
+                self.lint_block(uses, fn_def, stmts, *expr);
+                return;
+            }
+            Expr::Match { arms, .. } => {
+                for arm in arms {
+                    self.collect_bindings(uses, arm.pat);
+                }
+            }
+            Expr::Lambda { params, .. } => {
+                for param in params {
+                    self.collect_bindings(uses, param.pat);
+                }
+            }
+            _ => {}
+        }
+        module[id].walk_child_exprs(|child| self.lint_expr(uses, fn_def, child));
+    }
+
+    fn lint_block(
+        &mut self,
+        uses: &mut FnUses,
+        fn_def: &FnDef,
+        stmts: &[Stmt],
+        tail: Option<ExprId>,
+    ) {
+        let mut diverged = false;
+        let exprs = stmts.iter().map(Some).chain(std::iter::once(None));
+        for stmt in exprs {
+            let expr = match stmt {
+                Some(Stmt::Let { pat, expr, .. }) => {
+                    self.collect_bindings(uses, *pat);
+                    let ignored = matches!(self.module[*pat], Pat::Ignore);
+                    if ignored && !is_unit_or_never(&self.types[*expr]) {
+                        self.report(fn_def, Diagnostic::UnusedResult(*expr));
+                    }
+                    *expr
+                }
+                Some(Stmt::Expr(expr)) => *expr,
+                None => match tail {
+                    Some(expr) => expr,
+                    None => break,
+                },
+            };
+            if diverged {
+                self.report(fn_def, Diagnostic::UnreachableCode(expr));
+                diverged = false;
+            } else if self.types[expr] == Type::NEVER && stmt.is_some() {
+                // only the first unreachable statement is reported
+                diverged = true;
+            }
+            self.lint_expr(uses, fn_def, expr);
+        }
+    }
+
+    fn collect_bindings(&self, uses: &mut FnUses, id: PatId) {
+        if let Pat::Var { var, .. } = &self.module[id] {
+            let name = self.module[*var].as_str();
+            if !name.starts_with('_') && name != "self" {
+                uses.bindings.push(id);
+            }
+        }
+        self.module[id].walk_child_pats(|child| self.collect_bindings(uses, child));
+    }
+
+    /// Reports every fn that `main` does not reach through the fns it refers
+    /// to. Methods of trait impls are exempt, since the trait requires them.
+    /// Without a `main`, there is nothing to be reachable from.
+    fn lint_unused_fns(&mut self, module: &Module) {
+        let main = module.decls.iter().find_map(|decl| match decl {
+            Decl::Fn(id) if self.module[self.module[*id].name].as_str() == "main" => Some(*id),
+            _ => None,
+        });
+        let main = match main {
+            Some(main) => main,
+            None => return,
+        };
+
+        let mut reachable = HashSet::new();
+        let mut worklist = vec![main];
+        while let Some(id) = worklist.pop() {
+            if reachable.insert(id) {
+                worklist.extend(self.calls.get(id).into_iter().flatten().copied());
+            }
+        }
+
+        let trait_fns = (self.module.impl_defs.iter())
+            .flat_map(|(_, impl_def)| match impl_def.trait_ {
+                Some(_) => impl_def.fns.as_slice(),
+                None => &[],
+            })
+            .copied()
+            .collect::<HashSet<_>>();
+        for (id, fn_def) in self.module.fn_defs.iter() {
+            let name = self.module[fn_def.name].as_str();
+            if !reachable.contains(&id) && !trait_fns.contains(&id) && !name.starts_with('_') {
+                self.report(fn_def, Diagnostic::UnusedFn(id));
+            }
+        }
+    }
+
+    /// Records `diagnostic` unless `fn_def` allows its lint.
+    fn report(&mut self, fn_def: &FnDef, diagnostic: Diagnostic) {
+        let allowed = matches!(diagnostic.lint(), Some(lint) if fn_def.allow.contains(&lint));
+        if !allowed {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+}
+
+fn is_unit_or_never(ty: &Type) -> bool { *ty == Type::UNIT || *ty == Type::NEVER }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! test_lint {
+        ($name:ident, $src:expr, [$($expected:expr),* $(,)?]) => {
+            #[test]
+            fn $name() { test_lint($src, &[$($expected),*]) }
+        };
+    }
+
+    /// Lints `src`, and compares each warning, as its lint name followed by
+    /// the name or expression it is about, with `expected`.
+    fn test_lint(src: &str, expected: &[&str]) {
+        let syntax = walrus_parser::parse(src);
+        let hir = lower(&syntax);
+        let scopes = crate::scopes::scopes(&hir);
+        let types = crate::ty::infer(hir.clone(), scopes.clone());
+        assert_eq!(hir.diagnostics, vec![]);
+        assert_eq!(types.diagnostics, vec![]);
+
+        let module = &hir.data;
+        let got = lint(&hir, &scopes, &types)
+            .into_iter()
+            .map(|diagnostic| {
+                let subject = match diagnostic {
+                    Diagnostic::UnusedVar(pat) => match module[pat] {
+                        Pat::Var { var, .. } => module[var].to_string(),
+                        _ => unreachable!(),
+                    },
+                    Diagnostic::UnusedFn(id) => module[module[id].name].to_string(),
+                    Diagnostic::UnreachableCode(expr) | Diagnostic::UnusedResult(expr) => {
+                        match &module[expr] {
+                            Expr::Lit(Lit::Int(i, _)) => i.to_string(),
+                            Expr::Call { func, .. } => match module[*func] {
+                                Expr::Var(var) => format!("{}()", module[var]),
+                                _ => unreachable!(),
+                            },
+                            expr => unreachable!("{:?}", expr),
+                        }
+                    }
+                    _ => unreachable!("{:?}", diagnostic),
+                };
+                format!("{} {}", diagnostic.lint().unwrap().name(), subject)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(got, expected);
+    }
+
+    test_lint!(no_warnings, r#"fn main() { let x = 1; x; }"#, []);
+    test_lint!(
+        unused_var,
+        r#"fn main() { let x = 1; let (y, _z) = (2, 3); }"#,
+        ["unused_var x", "unused_var y"]
+    );
+    test_lint!(
+        unused_param,
+        r#"fn main() { f(1); } fn f(x: Int) {}"#,
+        ["unused_var x"]
+    );
+    test_lint!(
+        unused_match_binding,
+        r#"
+enum E { A { x: Int } }
+fn main() { f(E::A { x: 1 }) }
+fn f(e: E) { match e { E::A { x: y } => {} } }
+"#,
+        ["unused_var y"]
+    );
+    test_lint!(
+        unused_lambda_param,
+        r#"fn main() { let f = (x: Int) => 1; f(2); }"#,
+        ["unused_var x"]
+    );
+    test_lint!(
+        used_in_lambda,
+        r#"fn main() { let x = 1; let f = () => x; f(); }"#,
+        []
+    );
+    test_lint!(
+        unused_for_var,
+        r#"fn main() { for i in 0..10 {} }"#,
+        ["unused_var i"]
+    );
+    test_lint!(
+        unused_self,
+        r#"struct S {} impl S { fn f(self) {} } fn main() { let s = S {}; s.f(); }"#,
+        []
+    );
+    test_lint!(
+        unused_fn,
+        r#"fn main() {} fn f() { g() } fn g() {} fn _h() {}"#,
+        ["unused_fn f", "unused_fn g"]
+    );
+    test_lint!(
+        reachable_fns,
+        r#"
+struct S {}
+impl S {
+    fn new() -> S { S {} }
+    fn get(self) -> Int { helper() }
+    fn unused(self) {}
+}
+fn helper() -> Int { 1 }
+fn main() { let s = S::new(); s.get(); }
+"#,
+        ["unused_fn unused"]
+    );
+    test_lint!(
+        trait_methods_are_used,
+        r#"
+struct S {}
+impl Add for S { fn add(self, other: S) -> S { other } }
+fn main() {}
+"#,
+        []
+    );
+    test_lint!(no_main, r#"fn f() {}"#, []);
+    test_lint!(
+        unreachable_code,
+        r#"fn main() -> Int { return 1; 2; 3 }"#,
+        ["unreachable_code 2"]
+    );
+    test_lint!(
+        unreachable_tail,
+        r#"fn main() -> Int { exit(1); 2 }"#,
+        ["unreachable_code 2"]
+    );
+    test_lint!(reachable_tail, r#"fn main() -> Int { return 1 }"#, []);
+    test_lint!(
+        unused_result,
+        r#"fn main() { let _ = f(); let _ = g(); } fn f() -> Int { 1 } fn g() {}"#,
+        ["unused_result f()"]
+    );
+    test_lint!(
+        allow,
+        r#"
+#[allow(unused_var, unreachable_code)]
+fn main() { let x = 1; return; 2; }
+#[allow(unused_fn)]
+fn f() {}
+"#,
+        []
+    );
+    test_lint!(
+        allow_is_per_fn,
+        r#"
+#[allow(unused_var)]
+fn main() { let x = 1; f() }
+fn f() { let y = 2; }
+"#,
+        ["unused_var y"]
+    );
+
+    #[test]
+    fn unknown_attrs() {
+        let syntax =
+            walrus_parser::parse(r#"#[deny(unused_var)] #[allow(unused_vars)] fn main() {}"#);
+        let hir = lower(&syntax);
+        let got = (hir.diagnostics.iter())
+            .map(|diagnostic| match diagnostic {
+                Diagnostic::UnknownAttr(var) => format!("attr {}", hir.data[*var]),
+                Diagnostic::UnknownLint(var) => format!("lint {}", hir.data[*var]),
+                _ => unreachable!("{:?}", diagnostic),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(got, ["attr deny", "lint unused_vars"]);
+    }
+}
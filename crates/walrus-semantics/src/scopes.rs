@@ -1,4 +1,8 @@
-use crate::{builtins::Builtin, diagnostic::Diagnostic, hir::*};
+use crate::{
+    builtins::{Builtin, BuiltinKind},
+    diagnostic::Diagnostic,
+    hir::*,
+};
 use arena::{Arena, ArenaMap, Idx};
 use std::collections::HashMap;
 
@@ -10,18 +14,46 @@ pub fn scopes(module: &Module) -> Scopes {
 
 pub type ScopeId = Idx<Scope>;
 pub type Denotations = HashMap<Var, Denotation>;
+/// The fns defined in the `impl` blocks of a type.
+pub type Methods = HashMap<Var, FnDefId>;
 type Vars = HashMap<Var, VarId>;
 type LambdaDepth = u32;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Denotation {
     Local(PatId),
     Fn(FnDefId),
     Struct(StructDefId),
     Enum(EnumDefId),
+    Trait(TraitDefId),
+    TypeAlias(TypeAliasDefId),
+    Const(ConstDefId),
+    ExternFn(ExternFnDefId),
+    /// The `n`th type parameter of a generic fn.
+    GenericParam(FnDefId, u32),
     Builtin(Builtin),
 }
 
+/// A trait declared with `trait`, or one of the builtin traits that
+/// overload the operators, such as `Eq` and `Add`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum TraitId {
+    Def(TraitDefId),
+    Builtin(Builtin),
+}
+
+impl Denotation {
+    pub fn as_trait(self) -> Option<TraitId> {
+        match self {
+            Self::Trait(id) => Some(TraitId::Def(id)),
+            Self::Builtin(builtin) if builtin.kind() == BuiltinKind::Trait => {
+                Some(TraitId::Builtin(builtin))
+            }
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct Scope {
     pub parent: Option<ScopeId>,
@@ -36,6 +68,15 @@ pub struct Scopes {
     scope_of_expr: ArenaMap<ExprId, ScopeId>,
     scope_of_type: ArenaMap<TypeId, ScopeId>,
     scope_of_pat: ArenaMap<PatId, ScopeId>,
+    /// The methods of each `Denotation::Struct` and `Denotation::Enum`
+    methods: HashMap<Denotation, Methods>,
+    /// The methods of each `impl Trait for Type` block, by type and trait
+    trait_methods: HashMap<(Denotation, TraitId), Methods>,
+    /// The label of every labeled loop enclosing the current expression,
+    /// innermost last
+    labels: Vec<(Label, ExprId)>,
+    /// The loop each labeled `break` or `continue` targets
+    label_targets: ArenaMap<ExprId, ExprId>,
     pub diagnostics: Vec<Diagnostic>,
 }
 
@@ -62,6 +103,68 @@ impl Scopes {
         self.lookup_in_scope(scope, var)
     }
 
+    pub fn lookup_pat(&self, pat: PatId, var: &Var) -> Option<Denotation> {
+        let scope = self.scope_of_pat[pat];
+        self.lookup_in_scope(scope, var)
+    }
+
+    pub fn lookup_struct_method(&self, id: StructDefId, var: &Var) -> Option<FnDefId> {
+        self.lookup_method(Denotation::Struct(id), var)
+    }
+
+    pub fn lookup_enum_method(&self, id: EnumDefId, var: &Var) -> Option<FnDefId> {
+        self.lookup_method(Denotation::Enum(id), var)
+    }
+
+    pub fn lookup_builtin_method(&self, builtin: Builtin, var: &Var) -> Option<FnDefId> {
+        self.lookup_method(Denotation::Builtin(builtin), var)
+    }
+
+    pub fn lookup_method(&self, denotation: Denotation, var: &Var) -> Option<FnDefId> {
+        self.methods.get(&denotation)?.get(var).copied()
+    }
+
+    /// The fn overloading an operator for `denotation`, if the operator's
+    /// builtin trait is implemented for `denotation`.
+    pub fn lookup_operator(
+        &self,
+        denotation: Denotation,
+        operator_trait: Builtin,
+    ) -> Option<FnDefId> {
+        let method = Var::new(operator_trait.trait_method()?);
+        self.lookup_trait_method(denotation, TraitId::Builtin(operator_trait), &method)
+    }
+
+    /// The fn named `var` in the `impl` block of `trait_id` for `denotation`.
+    pub fn lookup_trait_method(
+        &self,
+        denotation: Denotation,
+        trait_id: TraitId,
+        var: &Var,
+    ) -> Option<FnDefId> {
+        self.trait_methods
+            .get(&(denotation, trait_id))?
+            .get(var)
+            .copied()
+    }
+
+    /// The loop targeted by the labeled `break` or `continue` `expr`, unless
+    /// no enclosing loop has its label.
+    pub fn label_target(&self, expr: ExprId) -> Option<ExprId> {
+        self.label_targets.get(expr).copied()
+    }
+
+    /// Every name visible from `expr`, innermost first, followed by the
+    /// builtins. Shadowed names are included.
+    pub fn visible_from_expr(&self, expr: ExprId) -> impl Iterator<Item = (Var, Denotation)> + '_ {
+        self.visible_in_scope(self.scope_of_expr[expr])
+    }
+
+    /// Every name visible from the type `id`, as for `visible_from_expr`.
+    pub fn visible_from_type(&self, id: TypeId) -> impl Iterator<Item = (Var, Denotation)> + '_ {
+        self.visible_in_scope(self.scope_of_type[id])
+    }
+
     pub fn scope_of_expr(&self, id: ExprId) -> &Scope { &self.scopes[self.scope_of_expr[id]] }
     pub fn scope_of_type(&self, id: TypeId) -> &Scope { &self.scopes[self.scope_of_type[id]] }
     pub fn scope_of_pat(&self, id: PatId) -> &Scope { &self.scopes[self.scope_of_pat[id]] }
@@ -69,6 +172,15 @@ impl Scopes {
 
 /// Private helpers
 impl Scopes {
+    fn visible_in_scope(&self, scope: ScopeId) -> impl Iterator<Item = (Var, Denotation)> + '_ {
+        let builtins = (Builtin::all().iter())
+            .map(|builtin| (Var::new(builtin.name()), Denotation::Builtin(*builtin)));
+        self.scope_chain(scope)
+            .flat_map(move |scope| self.scopes[scope].denotations.iter())
+            .map(|(var, denotation)| (var.clone(), *denotation))
+            .chain(builtins)
+    }
+
     fn new() -> Self {
         let mut scopes = Arena::default();
         let scope = scopes.alloc(Scope::default());
@@ -78,6 +190,10 @@ impl Scopes {
             scope_of_expr: ArenaMap::default(),
             scope_of_type: ArenaMap::default(),
             scope_of_pat: ArenaMap::default(),
+            methods: HashMap::new(),
+            trait_methods: HashMap::new(),
+            labels: Vec::new(),
+            label_targets: ArenaMap::default(),
             diagnostics: Vec::new(),
         }
     }
@@ -157,6 +273,20 @@ impl Scopes {
                 Decl::Struct(id) => self.struct_def_scope(module, &mut toplevel_defs, *id),
                 Decl::Enum(id) => self.enum_def_scope(module, &mut toplevel_defs, *id),
                 Decl::Fn(id) => self.fn_def_scope(module, &mut toplevel_defs, *id),
+                Decl::Trait(id) => self.trait_def_scope(module, &mut toplevel_defs, *id),
+                Decl::TypeAlias(id) => self.type_alias_def_scope(module, &mut toplevel_defs, *id),
+                Decl::Const(id) => self.const_def_scope(module, &mut toplevel_defs, *id),
+                Decl::ExternFn(id) => self.extern_fn_def_scope(module, &mut toplevel_defs, *id),
+                Decl::Impl(_) => {}
+            }
+        }
+
+        // the type of an `impl` block can only be looked up once every
+        // toplevel def has been seen
+        let mut names = HashMap::new();
+        for decl in &module.decls {
+            if let Decl::Impl(id) = decl {
+                self.impl_def_scope(module, &mut names, *id)
             }
         }
     }
@@ -192,11 +322,142 @@ impl Scopes {
         }
     }
 
+    /// `names` holds the names of the fns already defined for each type, so
+    /// that a fn defined in two `impl` blocks for the same type is reported.
+    fn impl_def_scope(
+        &mut self,
+        module: &Module,
+        names: &mut HashMap<Denotation, Vars>,
+        id: ImplDefId,
+    ) {
+        let impl_def = &module.data[id];
+        let trait_id = impl_def.trait_.and_then(|trait_| {
+            self.type_scope(module, trait_);
+            match &module.data[trait_] {
+                Type::Var(var) => self.lookup_type(trait_, &module.data[*var])?.as_trait(),
+                _ => None,
+            }
+        });
+        self.type_scope(module, impl_def.ty);
+        // a bad type is reported by inference, when it resolves `impl_def.ty`
+        let denotation = match &module.data[impl_def.ty] {
+            Type::Var(var) => self.lookup_type(impl_def.ty, &module.data[*var]),
+            _ => None,
+        };
+
+        // builtin types can implement traits, but cannot have inherent methods
+        let denotation = denotation.filter(|denotation| match denotation {
+            Denotation::Struct(_) | Denotation::Enum(_) => true,
+            Denotation::Builtin(builtin) => {
+                builtin.kind() == BuiltinKind::Type && impl_def.trait_.is_some()
+            }
+            _ => false,
+        });
+
+        for fn_id in &impl_def.fns {
+            if let Some(denotation) = denotation {
+                let fn_def = &module.data[*fn_id];
+                if self.insert_var(module, names.entry(denotation).or_default(), fn_def.name) {
+                    let var = module.data[fn_def.name].clone();
+                    if let Some(trait_id) = trait_id {
+                        self.trait_methods
+                            .entry((denotation, trait_id))
+                            .or_default()
+                            .insert(var.clone(), *fn_id);
+                    }
+                    self.methods
+                        .entry(denotation)
+                        .or_default()
+                        .insert(var, *fn_id);
+                }
+            }
+            self.fn_body_scope(module, *fn_id)
+        }
+    }
+
+    fn trait_def_scope(&mut self, module: &Module, toplevel_defs: &mut Vars, id: TraitDefId) {
+        let trait_def = &module.data[id];
+        self.insert_denotation(module, toplevel_defs, trait_def.name, Denotation::Trait(id));
+
+        let mut fns = Vars::new();
+        for fn_sig in &trait_def.fns {
+            self.insert_var(module, &mut fns, fn_sig.name);
+            self.in_child_scope(|this| {
+                let mut params = Vars::new();
+                for param in &fn_sig.params {
+                    this.param_scope(module, &mut params, param)
+                }
+                if let Some(ty) = fn_sig.ret_type {
+                    this.type_scope(module, ty)
+                }
+            })
+        }
+    }
+
+    fn type_alias_def_scope(
+        &mut self,
+        module: &Module,
+        toplevel_defs: &mut Vars,
+        id: TypeAliasDefId,
+    ) {
+        let alias_def = &module.data[id];
+        self.insert_denotation(
+            module,
+            toplevel_defs,
+            alias_def.name,
+            Denotation::TypeAlias(id),
+        );
+        self.type_scope(module, alias_def.ty);
+    }
+
+    fn const_def_scope(&mut self, module: &Module, toplevel_defs: &mut Vars, id: ConstDefId) {
+        let const_def = &module.data[id];
+        self.insert_denotation(module, toplevel_defs, const_def.name, Denotation::Const(id));
+        self.type_scope(module, const_def.ty);
+        self.expr_scope(module, const_def.expr);
+    }
+
+    fn extern_fn_def_scope(
+        &mut self,
+        module: &Module,
+        toplevel_defs: &mut Vars,
+        id: ExternFnDefId,
+    ) {
+        let extern_fn_def = &module.data[id];
+        self.insert_denotation(
+            module,
+            toplevel_defs,
+            extern_fn_def.name,
+            Denotation::ExternFn(id),
+        );
+        self.in_child_scope(|this| {
+            let mut params = Vars::new();
+            for param in &extern_fn_def.params {
+                this.param_scope(module, &mut params, param)
+            }
+            if let Some(ty) = extern_fn_def.ret_type {
+                this.type_scope(module, ty)
+            }
+        })
+    }
+
     fn fn_def_scope(&mut self, module: &Module, toplevel_defs: &mut Vars, id: FnDefId) {
         let fn_def = &module.data[id];
         self.insert_denotation(module, toplevel_defs, fn_def.name, Denotation::Fn(id));
+        self.fn_body_scope(module, id)
+    }
 
+    fn fn_body_scope(&mut self, module: &Module, id: FnDefId) {
+        let fn_def = &module.data[id];
         self.in_child_scope(|this| {
+            let mut generics = Vars::new();
+            for (idx, generic) in (0..).zip(&fn_def.generics) {
+                let denotation = Denotation::GenericParam(id, idx);
+                this.insert_denotation(module, &mut generics, generic.name, denotation);
+                for bound in &generic.bounds {
+                    this.type_scope(module, *bound)
+                }
+            }
             let mut params = Vars::new();
             for param in &fn_def.params {
                 this.param_scope(module, &mut params, param)
@@ -220,7 +481,9 @@ impl Scopes {
         self.set_scope_of_pat(id, self.scope);
         let pat = &module.data[id];
         match pat {
-            Pat::Var(var) => self.insert_denotation(module, vars, *var, Denotation::Local(id)),
+            Pat::Var { var, .. } => {
+                self.insert_denotation(module, vars, *var, Denotation::Local(id))
+            }
             pat => pat.walk_child_pats(|id| self.pat_scope(module, vars, id)),
         }
     }
@@ -239,16 +502,59 @@ impl Scopes {
                 self.in_child_scope(|this| this.block_scope(module, stmts, *expr))
             }
             Expr::Lambda { params, expr } => self.in_child_lambda_scope(|this| {
+                // `break` and `continue` cannot jump out of a lambda
+                let old_labels = std::mem::take(&mut this.labels);
                 let mut vars = Vars::new();
                 for param in params {
                     this.param_scope(module, &mut vars, param)
                 }
-                this.expr_scope(module, *expr)
+                this.expr_scope(module, *expr);
+                this.labels = old_labels;
             }),
+            Expr::Loop {
+                label: Some(label),
+                body,
+            } => {
+                self.labels.push((label.clone(), id));
+                self.expr_scope(module, *body);
+                self.labels.pop();
+            }
+            Expr::Break {
+                label: Some(label), ..
+            }
+            | Expr::Continue { label: Some(label) } => {
+                self.label_scope(id, label);
+                expr.walk_child_exprs(|id| self.expr_scope(module, id))
+            }
+            Expr::Cast { expr, ty } => {
+                self.expr_scope(module, *expr);
+                self.type_scope(module, *ty);
+            }
+            Expr::Match { test, arms } => {
+                self.expr_scope(module, *test);
+                for arm in arms {
+                    self.in_child_scope(|this| {
+                        this.pat_scope(module, &mut Vars::new(), arm.pat);
+                        this.expr_scope(module, arm.expr)
+                    })
+                }
+            }
             expr => expr.walk_child_exprs(|id| self.expr_scope(module, id)),
         }
     }
 
+    /// Resolves the label of a `break` or `continue` to the innermost
+    /// enclosing loop with that label.
+    fn label_scope(&mut self, expr: ExprId, label: &Label) {
+        match (self.labels.iter()).rfind(|(loop_label, _)| loop_label == label) {
+            Some((_, target)) => self.label_targets.insert(expr, *target),
+            None => self.diagnostics.push(Diagnostic::UnknownLabel {
+                expr,
+                label: label.clone(),
+            }),
+        }
+    }
+
     fn block_scope(&mut self, module: &Module, stmts: &[Stmt], last: Option<ExprId>) {
         match stmts {
             [Stmt::Expr(expr), rest @ ..] => {
@@ -272,3 +578,42 @@ impl Scopes {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels() {
+        let src = r#"
+fn f() -> _ {
+    'outer: loop {
+        'inner: loop { break 'outer };
+        () => continue 'outer;
+        break 'inner
+    }
+}
+"#;
+        let syntax = walrus_parser::parse(src);
+        let hir = crate::hir::lower(&syntax);
+        let scopes = scopes(&hir);
+        let got = (scopes.diagnostics.iter())
+            .map(|diagnostic| match diagnostic {
+                Diagnostic::UnknownLabel { label, .. } => label.to_string(),
+                _ => unreachable!("{:?}", diagnostic),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(got, ["'outer", "'inner"]);
+
+        let targets = (hir.data.exprs.iter())
+            .filter_map(|(id, _)| scopes.label_target(id))
+            .map(|target| match &hir.data[target] {
+                Expr::Loop {
+                    label: Some(label), ..
+                } => label.to_string(),
+                expr => unreachable!("{:?}", expr),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(targets, ["'outer"]);
+    }
+}
@@ -1,10 +1,14 @@
 use self::unify::TypeVarId;
-use crate::hir::{EnumDefId, StructDefId};
+use crate::hir::{EnumDefId, FnDefId, NumSuffix, StructDefId};
 
 mod infer;
+mod recursive;
 mod unify;
 
-pub use self::infer::{infer, InferenceId, InferenceResult};
+pub use self::{
+    infer::{infer, InferenceId, InferenceResult, MethodCall},
+    recursive::boxed_fields,
+};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Type {
@@ -19,6 +23,18 @@ pub struct FnType {
     pub ret: Type,
 }
 
+impl FnType {
+    /// Replaces the type parameters of `fn_def` by `args`.
+    pub fn subst(&self, fn_def: FnDefId, args: &[Type]) -> Self {
+        Self {
+            params: (self.params.iter())
+                .map(|param| param.subst(fn_def, args))
+                .collect(),
+            ret: self.ret.subst(fn_def, args),
+        }
+    }
+}
+
 impl From<FnType> for Type {
     fn from(func: FnType) -> Self { Self::function(func.params, func.ret) }
 }
@@ -27,7 +43,15 @@ impl Type {
     pub const UNIT: Self = Self::new0(Ctor::Tuple);
     pub const BOOL: Self = Self::new0(Ctor::Bool);
     pub const INT: Self = Self::new0(Ctor::Int);
+    pub const I8: Self = Self::new0(Ctor::I8);
+    pub const I16: Self = Self::new0(Ctor::I16);
+    pub const I64: Self = Self::new0(Ctor::I64);
+    pub const U8: Self = Self::new0(Ctor::U8);
+    pub const U16: Self = Self::new0(Ctor::U16);
+    pub const U32: Self = Self::new0(Ctor::U32);
+    pub const U64: Self = Self::new0(Ctor::U64);
     pub const FLOAT: Self = Self::new0(Ctor::Float);
+    pub const F64: Self = Self::new0(Ctor::F64);
     pub const CHAR: Self = Self::new0(Ctor::Char);
     pub const NEVER: Self = Self::new0(Ctor::Never);
 
@@ -62,6 +86,12 @@ impl Type {
             params: vec![],
         }
     }
+    pub const fn param(fn_def: FnDefId, idx: u32) -> Self {
+        Self::App {
+            ctor: Ctor::Param(fn_def, idx),
+            params: vec![],
+        }
+    }
 
     pub fn as_tuple(&self) -> Option<&[Self]> {
         match self {
@@ -96,6 +126,15 @@ impl Type {
             _ => None,
         }
     }
+    pub const fn as_enum(&self) -> Option<EnumDefId> {
+        match self {
+            Self::App {
+                ctor: Ctor::Enum(id),
+                ..
+            } => Some(*id),
+            _ => None,
+        }
+    }
     pub const fn ctor(&self) -> Option<&Ctor> {
         match self {
             Self::App { ctor, .. } => Some(ctor),
@@ -130,40 +169,148 @@ impl Type {
         });
         self
     }
+
+    fn any(&self, f: &impl Fn(&Self) -> bool) -> bool {
+        f(self)
+            || self
+                .params()
+                .map_or(false, |params| params.iter().any(|ty| ty.any(f)))
+    }
+
+    /// Replaces the type parameters of `fn_def` by `args`.
+    pub fn subst(&self, fn_def: FnDefId, args: &[Self]) -> Self {
+        self.clone().fold(&mut |ty| match ty {
+            Self::App {
+                ctor: Ctor::Param(id, idx),
+                ..
+            } if id == fn_def => args[idx as usize].clone(),
+            ty => ty,
+        })
+    }
+
+    /// The inverse of `subst`: matches `self`, which mentions the type
+    /// parameters of `fn_def`, against `ty`, and fills in `args` with the
+    /// types the parameters stand for. `Never` only fills in a parameter that
+    /// nothing else does, since it can be coerced to any type.
+    pub fn match_params(&self, fn_def: FnDefId, ty: &Self, args: &mut [Option<Self>]) {
+        match (self, ty) {
+            (
+                Self::App {
+                    ctor: Ctor::Param(id, idx),
+                    ..
+                },
+                ty,
+            ) if *id == fn_def => {
+                let arg = &mut args[*idx as usize];
+                if arg.is_none() || arg.as_ref() == Some(&Self::NEVER) {
+                    *arg = Some(ty.clone());
+                }
+            }
+            (Self::App { params, .. }, Self::App { params: tys, .. }) => {
+                for (param, ty) in params.iter().zip(tys) {
+                    param.match_params(fn_def, ty, args)
+                }
+            }
+            _ => {}
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Ctor {
     Bool,
     Int,
+    I8,
+    I16,
+    I64,
+    U8,
+    U16,
+    U32,
+    U64,
     Float,
+    F64,
     Char,
     Never,
     Tuple,
     Fn,
     Struct(StructDefId),
     Enum(EnumDefId),
+    /// The `n`th type parameter of a generic fn. Inside the fn it stands
+    /// for any type satisfying its bounds, and so only unifies with itself.
+    Param(FnDefId, u32),
 }
 
+impl Ctor {
+    pub const fn is_int(self) -> bool { self.is_signed_int() || self.is_unsigned_int() }
+
+    pub const fn is_signed_int(self) -> bool {
+        matches!(self, Self::Int | Self::I8 | Self::I16 | Self::I64)
+    }
+
+    pub const fn is_unsigned_int(self) -> bool {
+        matches!(self, Self::U8 | Self::U16 | Self::U32 | Self::U64)
+    }
+
+    pub const fn is_float(self) -> bool { matches!(self, Self::Float | Self::F64) }
+
+    /// The suffix that gives a numeric literal this type.
+    pub const fn num_suffix(self) -> Option<NumSuffix> {
+        match self {
+            Self::I8 => Some(NumSuffix::I8),
+            Self::I16 => Some(NumSuffix::I16),
+            Self::Int => Some(NumSuffix::I32),
+            Self::I64 => Some(NumSuffix::I64),
+            Self::U8 => Some(NumSuffix::U8),
+            Self::U16 => Some(NumSuffix::U16),
+            Self::U32 => Some(NumSuffix::U32),
+            Self::U64 => Some(NumSuffix::U64),
+            Self::Float => Some(NumSuffix::F32),
+            Self::F64 => Some(NumSuffix::F64),
+            _ => None,
+        }
+    }
+}
+
+impl From<NumSuffix> for Ctor {
+    fn from(suffix: NumSuffix) -> Self {
+        match suffix {
+            NumSuffix::I8 => Self::I8,
+            NumSuffix::I16 => Self::I16,
+            NumSuffix::I32 => Self::Int,
+            NumSuffix::I64 => Self::I64,
+            NumSuffix::U8 => Self::U8,
+            NumSuffix::U16 => Self::U16,
+            NumSuffix::U32 => Self::U32,
+            NumSuffix::U64 => Self::U64,
+            NumSuffix::F32 => Self::Float,
+            NumSuffix::F64 => Self::F64,
+        }
+    }
+}
+
+/// A type variable that has not been solved yet.
+/// `Int` and `Float` variables are introduced by numeric literals: they can
+/// only be unified with an integer (respectively floating point) type, and
+/// default to `Int` (respectively `Float`) if nothing constrains them.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum InferType {
     Var(TypeVarId),
-    /* TODO
-    Int,
-    Float,
-    */
+    Int(TypeVarId),
+    Float(TypeVarId),
 }
 
 impl InferType {
     const fn fallback_value(self) -> Type {
         match self {
             Self::Var(_) => Type::Unknown,
+            Self::Int(_) => Type::INT,
+            Self::Float(_) => Type::FLOAT,
         }
     }
 
     const fn to_inner(self) -> TypeVarId {
         match self {
-            Self::Var(ty) => ty,
+            Self::Var(ty) | Self::Int(ty) | Self::Float(ty) => ty,
         }
     }
 }
@@ -203,6 +350,156 @@ mod tests {
     test_infer!(int_lit, r#"fn f() -> _ {1}"#, Type::INT);
     test_infer!(float_lit, r#"fn f() -> _ {1.0}"#, Type::FLOAT);
     test_infer!(char_lit, r#"fn f() -> _ {'a'}"#, Type::CHAR);
+    test_infer!(int_lit_u8, r#"fn f() -> _ {let x: U8 = 5; x}"#, Type::U8);
+    test_infer!(
+        float_lit_f64,
+        r#"fn f() -> _ {let y: F64 = 1.0; y}"#,
+        Type::F64
+    );
+    test_infer!(int_lit_ret_type, r#"fn f() -> I64 {5}"#, Type::I64);
+    test_infer!(int_lit_suffix, r#"fn f() -> _ {1 + 10u8}"#, Type::U8);
+    test_infer!(float_lit_suffix, r#"fn f() -> _ {3.0f64}"#, Type::F64);
+    test_infer!(lit_suffix_mismatch, r#"fn f() -> Int {10u8}"#, Type::INT);
+    test_infer!(
+        int_lit_arithmetic,
+        r#"fn f() -> _ {let x: U16 = 1; 2 + x}"#,
+        Type::U16
+    );
+    test_infer!(int_lit_mismatch, r#"fn f() -> Float {5}"#, Type::FLOAT);
+    test_infer!(
+        int_lit_out_of_range,
+        r#"fn f() -> _ {let x: U8 = 300; x}"#,
+        Type::U8
+    );
+    test_infer!(
+        int_lit_out_of_range_ret,
+        r#"fn f() -> Int {5000000000}"#,
+        Type::INT
+    );
+    test_infer!(
+        neg_int_lit_unsigned,
+        r#"fn f() -> _ {let x: U8 = -5; x}"#,
+        Type::U8
+    );
+    test_infer!(
+        neg_int_lit_signed_min,
+        r#"fn f() -> _ {let x: I8 = -128; x}"#,
+        Type::I8
+    );
+    test_infer!(
+        float_lit_out_of_range,
+        r#"fn f() -> Float {1e39}"#,
+        Type::FLOAT
+    );
+
+    test_infer!(cast_int_to_float, r#"fn f() -> _ {1 as F64}"#, Type::F64);
+    test_infer!(
+        cast_float_to_int,
+        r#"fn f() -> _ {let x: F64 = 2.5; x as I64}"#,
+        Type::I64
+    );
+    test_infer!(cast_char_to_int, r#"fn f() -> _ {'a' as U32}"#, Type::U32);
+    test_infer!(
+        cast_int_lit_to_char,
+        r#"fn f() -> _ {65 as Char}"#,
+        Type::CHAR
+    );
+    test_infer!(cast_invalid, r#"fn f() -> _ {(1, 2) as Int}"#, Type::INT);
+    test_infer!(rem, r#"fn f() -> _ {7 % 2}"#, Type::INT);
+    test_infer!(rem_float, r#"fn f() -> _ {7.5 % 2.0}"#, Type::FLOAT);
+    test_infer!(add_bool, r#"fn f() -> _ {true + false}"#, Type::BOOL);
+    test_infer!(mul_char, r#"fn f() -> _ {'a' * 'b'}"#, Type::CHAR);
+    test_infer!(
+        sub_tuple,
+        r#"fn f() -> _ {(1, 2) - (3, 4)}"#,
+        Type::tuple(vec![Type::INT, Type::INT])
+    );
+    test_infer!(bit_and, r#"fn f() -> _ {let x: U8 = 12; x & 10}"#, Type::U8);
+    test_infer!(bit_or_bool, r#"fn f() -> _ {true | false}"#, Type::BOOL);
+    test_infer!(bit_not, r#"fn f() -> _ {~5}"#, Type::INT);
+    test_infer!(bit_not_bool, r#"fn f() -> _ {~true}"#, Type::BOOL);
+    test_infer!(bit_not_float, r#"fn f() -> _ {~1.0}"#, Type::FLOAT);
+    test_infer!(bit_and_unknown_lhs, r#"fn f(x) -> _ {x & 1}"#, Type::INT);
+    test_infer!(
+        shift,
+        r#"fn f() -> _ {let x: I64 = 1; x << 3 >> 1}"#,
+        Type::I64
+    );
+    test_infer!(bit_xor_float, r#"fn f() -> _ {1.0 ^ 2.0}"#, Type::FLOAT);
+    test_infer!(shift_bool, r#"fn f() -> _ {true << false}"#, Type::BOOL);
+    test_infer!(
+        compound_assign,
+        r#"fn f() -> _ {let mut x: U8 = 1; x += 2}"#,
+        Type::UNIT
+    );
+    test_infer!(
+        compound_assign_mismatch,
+        r#"fn f() -> _ {let mut x = 1; x *= 2.0}"#,
+        Type::UNIT
+    );
+    test_infer!(
+        compound_assign_bit_float,
+        r#"fn f() -> _ {let mut x = 1.0; x |= 2.0}"#,
+        Type::UNIT
+    );
+    test_infer!(
+        compound_assign_not_lvalue,
+        r#"fn f() -> _ {1 -= 2}"#,
+        Type::UNIT
+    );
+
+    test_infer!(
+        assign_mut,
+        r#"fn f() -> _ {let mut x = 1; x = 2}"#,
+        Type::UNIT
+    );
+    test_infer!(
+        assign_immutable,
+        r#"fn f() -> _ {let x = 1; x = 2}"#,
+        Type::UNIT
+    );
+    test_infer!(
+        assign_mut_param,
+        r#"fn f(mut x: Int) -> _ {x += 1}"#,
+        Type::UNIT
+    );
+    test_infer!(
+        assign_immutable_param,
+        r#"fn f(x: Int) -> _ {x += 1}"#,
+        Type::UNIT
+    );
+    test_infer!(
+        assign_immutable_field,
+        r#"fn f() -> _ {let (mut x, y) = ((1, 2), (3, 4)); x.0 = 5; y.1 = 6}"#,
+        Type::UNIT
+    );
+    test_infer!(
+        assign_nested_field,
+        r#"
+struct Foo {
+    x: (Int, Bool),
+}
+fn f() -> _ {
+    let mut foo = Foo { x: (1, true) };
+    foo.x.1 = false;
+    foo.x.1
+}
+"#,
+        Type::BOOL
+    );
+    test_infer!(
+        assign_field_not_lvalue,
+        r#"fn f() -> _ {let g = () => (0, 0); g().0 = 1}"#,
+        Type::UNIT
+    );
+    test_infer!(
+        assign_to_fn,
+        r#"
+fn f() -> _ { g = g; 0 }
+fn g() -> Int { 1 }
+"#,
+        Type::INT
+    );
 
     test_infer!(unit_stmt, r#"fn f() -> _ {1;}"#, Type::UNIT);
     test_infer!(let_var, r#"fn f() -> _ {let x = 5; x}"#, Type::INT);
@@ -260,6 +557,184 @@ fn f() -> _ { Foo::X { x: 0 } }
 "#,
         Type::enum_(EnumDefId::new(0))
     );
+    test_infer!(
+        enum_no_such_variant,
+        r#"
+enum Foo { X {} }
+fn f() -> _ { Foo::Y {} }
+"#,
+        Type::enum_(EnumDefId::new(0))
+    );
+    test_infer!(
+        match_enum,
+        r#"
+enum List {
+    Nil {},
+    Cons { head: Int, tail: List },
+}
+fn f(xs: List) -> _ {
+    match xs {
+        List::Nil {} => 0,
+        List::Cons { head: x } => x,
+    }
+}
+"#,
+        Type::INT
+    );
+    test_infer!(
+        match_nested,
+        r#"
+enum Bit { Zero {}, One {} }
+fn f(a: Bit, b: Bit) -> _ {
+    match (a, b) {
+        (Bit::Zero {}, _) => 0,
+        (Bit::One {}, Bit::Zero {}) => 1,
+        (_, Bit::One {}) => 2,
+    }
+}
+"#,
+        Type::INT
+    );
+    test_infer!(
+        match_non_exhaustive,
+        r#"
+enum Bit { Zero {}, One {} }
+enum Pair { Pair { x: Bit, y: Bit } }
+fn f(pair: Pair) -> _ {
+    match pair {
+        Pair::Pair { x: Bit::Zero {} } => 0,
+        Pair::Pair { y: Bit::One {} } => 1,
+    }
+}
+"#,
+        Type::INT
+    );
+    test_infer!(
+        match_missing_variants,
+        r#"
+enum Foo { X {}, Y {}, Z {} }
+fn f(foo: Foo) -> _ {
+    match foo { Foo::Y {} => 1 }
+}
+"#,
+        Type::INT
+    );
+    test_infer!(
+        match_arms_mismatch,
+        r#"
+enum Bit { Zero {}, One {} }
+fn f(bit: Bit) -> _ {
+    match bit {
+        Bit::Zero {} => 0,
+        Bit::One {} => false,
+    }
+}
+"#,
+        Type::INT
+    );
+    test_infer!(
+        match_bad_pats,
+        r#"
+enum Bit { Zero {}, One {} }
+struct S {}
+fn f(bit: Bit) -> _ {
+    match bit {
+        Bit::Two {} => 0,
+        S::Zero {} => 1,
+        Bit::One { x: _ } => 2,
+        _ => 3,
+    }
+}
+"#,
+        Type::INT
+    );
+    test_infer!(
+        refutable_let,
+        r#"
+enum Bit { Zero {}, One {} }
+fn f() -> _ {
+    let Bit::Zero {} = Bit::One {};
+}
+"#,
+        Type::UNIT
+    );
+
+    test_infer!(
+        type_alias,
+        r#"
+fn f(p: Point) -> _ { p.1 }
+type Point = (Int, Coord);
+type Coord = Bool;
+"#,
+        Type::BOOL
+    );
+    test_infer!(
+        type_alias_in_struct,
+        r#"
+type Id = U8;
+struct Foo { id: Id }
+fn f(foo: Foo) -> _ { foo.id }
+"#,
+        Type::U8
+    );
+    test_infer!(
+        cyclic_type_alias,
+        r#"
+type A = (Int, B);
+type B = A;
+fn f(a: A) -> _ { a }
+"#,
+        Type::tuple(vec![Type::INT, Type::Unknown])
+    );
+    test_infer!(
+        const_in_fn,
+        r#"
+const MAX: U8 = 2 * LIMIT;
+const LIMIT: U8 = 100;
+fn f() -> _ { MAX }
+"#,
+        Type::U8
+    );
+    test_infer!(
+        const_mismatch,
+        r#"
+const FLAG: Bool = 1;
+fn f() -> _ { FLAG }
+"#,
+        Type::BOOL
+    );
+    test_infer!(
+        assign_to_const,
+        r#"
+const MAX: Int = 10;
+fn f() -> _ { MAX = 5 }
+"#,
+        Type::UNIT
+    );
+    test_infer!(
+        extern_fn_call,
+        r#"
+extern "C" fn abs(x: Int) -> Int;
+fn f() -> _ { abs(-5) }
+"#,
+        Type::INT
+    );
+    test_infer!(
+        extern_fn_as_value,
+        r#"
+extern fn exit(status: Int) -> Never;
+fn f() -> _ { let g = exit; g }
+"#,
+        Type::function(vec![Type::INT], Type::NEVER)
+    );
+    test_infer!(
+        extern_fn_not_ffi_safe,
+        r#"
+extern "C" fn swap(pair: (Int, Int)) -> (Int, Int);
+fn g() -> _ { 0 }
+"#,
+        Type::INT
+    );
 
     test_infer!(
         struct_destructure,
@@ -304,13 +779,561 @@ fn f() -> _ {
         Type::INT
     );
 
+    test_infer!(
+        call_unknown_fn,
+        r#"fn f(g: _) -> _ { g(1) + 1 }"#,
+        Type::INT
+    );
+
+    test_infer!(
+        self_application,
+        r#"fn f(x: _) -> _ { x(x) }"#,
+        Type::Unknown
+    );
+
+    test_infer!(
+        recursive_lambda,
+        r#"fn f() -> _ { (h) => h(h) }"#,
+        Type::function(
+            vec![Type::function(vec![Type::Unknown], Type::Unknown)],
+            Type::Unknown
+        )
+    );
+
     test_infer!(unary_sub, r#"fn f() -> _ {-0}"#, Type::INT);
     test_infer!(unary_add, r#"fn f() -> _ {+0}"#, Type::INT);
 
     test_infer!(cmp, r#"fn f() -> _ {0 == 1}"#, Type::BOOL);
+    test_infer!(
+        tuple_eq,
+        r#"fn f() -> _ {(1, true) == (1, false)}"#,
+        Type::BOOL
+    );
+    test_infer!(
+        tuple_less,
+        r#"fn f() -> _ {(1, 'a') < (1, 'b')}"#,
+        Type::BOOL
+    );
+    test_infer!(
+        struct_eq,
+        r#"
+struct Foo {
+    x: Int,
+    y: (Bool, Char),
+}
+fn f() -> _ {
+    let a = Foo { x: 0, y: (false, 'a') };
+    let b = Foo { x: 0, y: (true, 'b') };
+    a == b
+}
+"#,
+        Type::BOOL
+    );
+    test_infer!(
+        struct_less,
+        r#"
+struct Foo { x: Int }
+fn f() -> _ {
+    let a = Foo { x: 0 };
+    let b = Foo { x: 1 };
+    a < b
+}
+"#,
+        Type::BOOL
+    );
+    test_infer!(
+        enum_eq,
+        r#"
+enum Foo {
+    X { x: Int },
+    Y { y: Bool },
+}
+fn f() -> _ {
+    let a = Foo::X { x: 0 };
+    let b = Foo::Y { y: true };
+    a != b
+}
+"#,
+        Type::BOOL
+    );
+    test_infer!(
+        recursive_enum_eq,
+        r#"
+enum List {
+    Nil {},
+    Cons { head: Int, tail: List },
+}
+fn f() -> _ {
+    let xs = List::Nil {};
+    xs == xs
+}
+"#,
+        Type::BOOL
+    );
+    test_infer!(
+        closure_eq,
+        r#"fn f() -> _ { let g = () => 1; g == g }"#,
+        Type::BOOL
+    );
+    test_infer!(
+        closure_eq_inferred_later,
+        r#"
+fn eq(a: _, b: _) -> _ { a == b }
+fn f() -> _ { eq(() => 1, () => 2) }
+"#,
+        Type::BOOL
+    );
+    test_infer!(
+        struct_with_closure_eq,
+        r#"
+struct Foo { f: () -> Int }
+fn f() -> _ {
+    let foo = Foo { f: () => 1 };
+    foo == foo
+}
+"#,
+        Type::BOOL
+    );
     test_infer!(loop_never, r#"fn f() -> Never { loop {} }"#, Type::NEVER);
     test_infer!(loop_unit, r#"fn f() -> _ { loop { break } }"#, Type::UNIT);
     test_infer!(loop_int, r#"fn f() -> _ { loop { break 1 } }"#, Type::INT);
+    test_infer!(while_unit, r#"fn f() -> _ { while true {} }"#, Type::UNIT);
+    test_infer!(while_not_bool, r#"fn f() -> _ { while 1 {} }"#, Type::UNIT);
+    test_infer!(
+        for_range,
+        r#"fn f() -> _ { let mut sum: U8 = 0; for i in 0..10 { sum += i }; sum }"#,
+        Type::U8
+    );
+    test_infer!(
+        for_break_value,
+        r#"fn f() -> _ { for i in 0..10 { break i } }"#,
+        Type::UNIT
+    );
+    test_infer!(
+        for_float_range,
+        r#"fn f() -> _ { for x in 0.0..1.0 {} }"#,
+        Type::UNIT
+    );
+    test_infer!(
+        for_char_range,
+        r#"fn f() -> _ { for c in 'a'..'z' {} }"#,
+        Type::UNIT
+    );
+    test_infer!(
+        labeled_break_outer,
+        r#"fn f() -> _ { 'outer: loop { loop { break 'outer 1 } } }"#,
+        Type::INT
+    );
+    test_infer!(
+        labeled_break_mismatch,
+        r#"fn f() -> _ { 'outer: loop { loop { break 'outer 1 }; break 'outer true } }"#,
+        Type::INT
+    );
+    test_infer!(
+        labeled_continue_outer,
+        r#"fn f() -> _ { 'outer: while true { loop { continue 'outer } } }"#,
+        Type::UNIT
+    );
+    test_infer!(
+        unknown_label,
+        r#"fn f() -> Never { 'outer: loop { break 'inner } }"#,
+        Type::NEVER
+    );
+    test_infer!(
+        break_out_of_lambda,
+        r#"fn f() -> _ { 'outer: loop { () => break 'outer; break } }"#,
+        Type::UNIT
+    );
+
+    test_infer!(
+        method_call,
+        r#"
+fn f() -> _ { let p = Point::new(1, 2); p.sum() }
+struct Point { x: Int, y: Int }
+impl Point {
+    fn new(x: Int, y: Int) -> Point { Point { x: x, y: y } }
+    fn sum(self) -> _ { self.x + self.y }
+}
+"#,
+        Type::INT
+    );
+    test_infer!(
+        method_call_with_args,
+        r#"
+fn f() -> _ { let p = Point { x: 1, y: 2 }; p.is_left_of(p) }
+struct Point { x: Int, y: Int }
+impl Point {
+    fn is_left_of(self, other: Point) -> _ { self.x < other.x }
+}
+"#,
+        Type::BOOL
+    );
+    test_infer!(
+        enum_method_call,
+        r#"
+fn f() -> _ { let b = Bit::One {}; b.to_int() }
+enum Bit { Zero {}, One {} }
+impl Bit {
+    fn to_int(self) -> Int { 1 }
+}
+"#,
+        Type::INT
+    );
+    test_infer!(
+        path_fn_value,
+        r#"
+fn f() -> _ { Point::sum }
+struct Point { x: Int, y: Int }
+impl Point {
+    fn sum(self) -> Int { self.x + self.y }
+}
+"#,
+        Type::function(vec![Type::struct_(StructDefId::new(0))], Type::INT)
+    );
+    test_infer!(
+        no_such_method,
+        r#"
+fn f() -> _ { let p = Point {}; p.sum() }
+struct Point {}
+"#,
+        Type::Unknown
+    );
+    test_infer!(
+        assoc_fn_not_method,
+        r#"
+fn f() -> _ { let p = Point {}; p.new() }
+struct Point {}
+impl Point {
+    fn new() -> Point { Point {} }
+}
+"#,
+        Type::Unknown
+    );
+    test_infer!(
+        method_arg_count_mismatch,
+        r#"
+fn f() -> _ { let p = Point {}; p.get(1) }
+struct Point {}
+impl Point {
+    fn get(self) -> Int { 0 }
+}
+"#,
+        Type::INT
+    );
+    test_infer!(
+        path_unbound_type,
+        r#"fn f() -> _ { Point::new() }"#,
+        Type::Unknown
+    );
+    test_infer!(
+        method_not_in_toplevel_scope,
+        r#"
+fn f() -> _ { new() }
+struct Point {}
+impl Point {
+    fn new() -> Point { Point {} }
+}
+"#,
+        Type::Unknown
+    );
+    test_infer!(
+        impl_builtin_type,
+        r#"
+fn f() {}
+impl Int {}
+"#,
+        Type::UNIT
+    );
+
+    test_infer!(
+        trait_method_call,
+        r#"
+fn f() -> _ { let p = Point { x: 1 }; p.eq(p) }
+struct Point { x: Int }
+impl Eq for Point {
+    fn eq(self, other: Point) -> Bool { self.x == other.x }
+}
+"#,
+        Type::BOOL
+    );
+    test_infer!(
+        trait_impl_for_builtin_type,
+        r#"
+fn f() -> _ { let x: Int = 1; x.double() }
+trait Double { fn double(self) -> Self; }
+impl Double for Int {
+    fn double(self) -> Int { self * 2 }
+}
+"#,
+        Type::INT
+    );
+    test_infer!(
+        builtin_impl_method_call,
+        r#"
+fn f() -> _ { 1.lt(2) && 'a'.eq('b') }
+"#,
+        Type::BOOL
+    );
+    test_infer!(
+        conflicts_with_builtin_impl,
+        r#"
+fn f() {}
+impl Eq for Int {
+    fn eq(self, other: Int) -> Bool { true }
+}
+"#,
+        Type::UNIT
+    );
+    test_infer!(
+        trait_sig_guides_inference,
+        r#"
+fn f() -> _ { let p = Point {}; p.size() }
+trait Size { fn size(self) -> U8; }
+struct Point {}
+impl Size for Point {
+    fn size(self) -> _ { 0 }
+}
+"#,
+        Type::U8
+    );
+    test_infer!(
+        missing_trait_method,
+        r#"
+fn f() {}
+struct Point {}
+impl Eq for Point {}
+"#,
+        Type::UNIT
+    );
+    test_infer!(
+        not_a_trait_method,
+        r#"
+fn f() {}
+trait Eq {}
+struct Point {}
+impl Eq for Point {
+    fn eq(self, other: Point) -> Bool { true }
+}
+"#,
+        Type::UNIT
+    );
+    test_infer!(
+        trait_method_mismatch,
+        r#"
+fn f() {}
+struct Point {}
+impl Eq for Point {
+    fn eq(self, other: Int) -> Bool { true }
+}
+"#,
+        Type::UNIT
+    );
+    test_infer!(
+        conflicting_impls,
+        r#"
+fn f() {}
+trait Marker {}
+struct Point {}
+impl Marker for Point {}
+impl Marker for Point {}
+"#,
+        Type::UNIT
+    );
+    test_infer!(
+        impl_unbound_trait,
+        r#"
+fn f() {}
+struct Point {}
+impl Marker for Point {}
+"#,
+        Type::UNIT
+    );
+    test_infer!(
+        impl_non_trait,
+        r#"
+fn f() {}
+struct Point {}
+impl Point for Point {}
+"#,
+        Type::UNIT
+    );
+
+    test_infer!(
+        generic_identity,
+        r#"
+fn f() -> _ { (id(1), id(true)) }
+fn id<T>(x: T) -> T { x }
+"#,
+        Type::tuple(vec![Type::INT, Type::BOOL])
+    );
+    test_infer!(
+        generic_bound,
+        r#"
+fn f() -> _ { max(1, 2) }
+fn max<T: Ord>(a: T, b: T) -> T { if a < b { b } else { a } }
+"#,
+        Type::INT
+    );
+    test_infer!(
+        generic_bound_shadowed_trait,
+        r#"
+fn f() -> _ { max(1, 2) }
+fn max<T: Ord>(a: T, b: T) -> T { if a < b { b } else { a } }
+trait Ord { fn lt(self, other: Self) -> Bool; }
+"#,
+        Type::INT
+    );
+    test_infer!(
+        generic_bound_user_impl,
+        r#"
+fn f() -> _ { max(V { x: 1 }, V { x: 2 }).x }
+fn max<T: Ord>(a: T, b: T) -> T { if a.lt(b) { b } else { a } }
+struct V { x: Int }
+impl Ord for V {
+    fn lt(self, other: V) -> Bool { self.x < other.x }
+}
+"#,
+        Type::INT
+    );
+    test_infer!(
+        generic_unsatisfied_bound,
+        r#"
+fn f() -> _ { max(V {}, V {}) }
+fn max<T: Ord>(a: T, b: T) -> T { if a < b { b } else { a } }
+struct V {}
+"#,
+        Type::struct_(StructDefId::new(0))
+    );
+    test_infer!(
+        generic_without_bound,
+        r#"
+fn f() {}
+fn max<T>(a: T, b: T) -> T { if a < b { b } else { a + b } }
+"#,
+        Type::UNIT
+    );
+    test_infer!(
+        generic_unannotated,
+        r#"
+fn f() {}
+fn zero<T>(x: T) -> _ { 0 }
+"#,
+        Type::UNIT
+    );
+    test_infer!(
+        generic_arg_not_inferred,
+        r#"
+fn f() { let g = id; }
+fn id<T>(x: T) -> T { x }
+"#,
+        Type::UNIT
+    );
+
+    test_infer!(
+        overloaded_add,
+        r#"
+fn f() -> _ { let v = V { x: 1 }; (v + v).x }
+struct V { x: Int }
+impl Add for V {
+    fn add(self, other: V) -> V { V { x: self.x + other.x } }
+}
+"#,
+        Type::INT
+    );
+    test_infer!(
+        overloaded_add_shadowed_name,
+        r#"
+fn f() -> _ { let Add = 1; let v = V { x: Add }; (v + v).x }
+struct V { x: Int }
+impl Add for V {
+    fn add(self, other: V) -> V { V { x: self.x + other.x } }
+}
+"#,
+        Type::INT
+    );
+    test_infer!(
+        overloaded_mul,
+        r#"
+fn f() -> _ { let v = V { x: 1.0 }; (v * v).x }
+struct V { x: Float }
+impl Mul for V {
+    fn mul(self, other: _) -> V { V { x: self.x * other.x } }
+}
+"#,
+        Type::FLOAT
+    );
+    test_infer!(
+        overloaded_add_assign,
+        r#"
+fn f() -> _ { let mut v = V { x: 1 }; v += v; v.x }
+struct V { x: Int }
+impl Add for V {
+    fn add(self, other: V) -> V { V { x: self.x + other.x } }
+}
+"#,
+        Type::INT
+    );
+    test_infer!(
+        overloaded_neg,
+        r#"
+fn f() -> _ { let v = V { x: 1 }; (-v).x }
+struct V { x: Int }
+impl Neg for V {
+    fn neg(self) -> V { V { x: -self.x } }
+}
+"#,
+        Type::INT
+    );
+    test_infer!(
+        neg_struct_without_impl,
+        r#"
+fn f() -> _ { let s = S {}; -s; }
+struct S {}
+"#,
+        Type::UNIT
+    );
+    test_infer!(
+        overloaded_cmp,
+        r#"
+fn f() -> _ { let v = V { x: 1 }; v < v || v >= v }
+struct V { x: Int }
+impl Ord for V {
+    fn lt(self, other: V) -> Bool { self.x < other.x }
+}
+"#,
+        Type::BOOL
+    );
+    test_infer!(
+        overloaded_cmp_field,
+        r#"
+fn f() -> _ { let v = V { x: 1 }; (v, 1) < (v, 1) }
+struct V { x: Int }
+impl Ord for V {
+    fn lt(self, other: V) -> Bool { self.x < other.x }
+}
+"#,
+        Type::BOOL
+    );
+    test_infer!(
+        overloaded_cmp_not_bool,
+        r#"
+fn f() -> _ { let v = V { x: 1 }; v < v }
+trait Ord { fn lt(self, other: Self) -> Int; }
+struct V { x: Int }
+impl Ord for V {
+    fn lt(self, other: V) -> Int { self.x - other.x }
+}
+"#,
+        Type::BOOL
+    );
+    test_infer!(
+        add_without_impl,
+        r#"
+fn f() { let v = V { x: 1 }; v + v; }
+struct V { x: Int }
+"#,
+        Type::UNIT
+    );
 
     test_infer!(return_unit, r#"fn f() -> _ { return }"#, Type::UNIT);
     test_infer!(return_int, r#"fn f() -> _ { return 1 }"#, Type::INT);
@@ -368,4 +1391,35 @@ fn is_even(x: _) -> _ { if x == 0 { true  } else { is_odd(x - 1)  } }
             Type::function(vec![Type::INT], Type::BOOL),
         ])
     );
+
+    test_infer!(
+        suggest_local,
+        r#"fn f() -> _ { let count = 1; let cont = 2; cuont }"#,
+        Type::Unknown
+    );
+    test_infer!(
+        suggest_builtin,
+        r#"fn f() -> _ { pitchar('a') }"#,
+        Type::Unknown
+    );
+    test_infer!(
+        suggest_no_types_for_values,
+        r#"struct Foo {} fn f() -> _ { foo }"#,
+        Type::Unknown
+    );
+    test_infer!(
+        suggest_type,
+        r#"struct Point {} fn f(p: Piont) -> _ {}"#,
+        Type::UNIT
+    );
+    test_infer!(
+        suggest_field,
+        r#"struct Point { xpos: Int, ypos: Int } fn f(p: Point) -> _ { p.xpso }"#,
+        Type::Unknown
+    );
+    test_infer!(
+        suggest_struct_lit_field,
+        r#"struct Point { xpos: Int } fn f() -> _ { Point { xpso: 1 } }"#,
+        Type::struct_(StructDefId::new(0))
+    );
 }
@@ -1,16 +1,26 @@
-use std::{collections::HashMap, ops::Index};
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Index,
+};
 
-use super::{unify::InferenceTable, Type, *};
+use super::{
+    unify::{InferenceTable, UnifyError},
+    Type, *,
+};
 use crate::{
-    builtins::BuiltinKind,
-    diagnostic::Diagnostic,
+    builtins::{Builtin, BuiltinKind},
+    consteval::{self, EvalError},
+    diagnostic::{Diagnostic, LitError},
+    fix::{self, FixIt},
     hir,
     hir::*,
-    scopes::{Denotation, Scopes},
+    scopes::{Denotation, Scopes, TraitId},
 };
 use arena::ArenaMap;
 use either::{Either, Either::*};
 
+mod exhaustive;
+
 pub fn infer(module: Module, scopes: Scopes) -> InferenceResult {
     let mut ctx = Ctx::new(module, scopes);
     ctx.infer_module();
@@ -23,6 +33,7 @@ pub struct InferenceResult {
     pub type_of_type: ArenaMap<TypeId, Type>,
     pub type_of_pat: ArenaMap<PatId, Type>,
     pub type_of_fn: ArenaMap<FnDefId, FnType>,
+    pub method_calls: ArenaMap<ExprId, MethodCall>,
     pub diagnostics: Vec<Diagnostic>,
 }
 
@@ -43,6 +54,19 @@ impl Index<FnDefId> for InferenceResult {
     fn index(&self, id: FnDefId) -> &Self::Output { &self.type_of_fn[id] }
 }
 
+/// The fn that a method call calls.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MethodCall {
+    /// A fn in an `impl` block for the receiver's type.
+    Fn(FnDefId),
+    /// A fn of a trait that the receiver's type parameter is bounded by. It
+    /// is the fn of the type argument's impl of the trait.
+    Trait(TraitId),
+    /// The method of a scalar type's built-in impl of `Eq` or `Ord`, which
+    /// compares the receiver with the argument.
+    BuiltinCmp(CmpBinop),
+}
+
 /// An id representing any "thing" whose type is infered.
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum InferenceId {
@@ -57,7 +81,27 @@ struct Ctx {
     result: InferenceResult,
     table: InferenceTable,
     fn_type: Option<FnType>,
-    loop_type: Option<Type>,
+    /// The id and type of every loop enclosing the current expression,
+    /// innermost last.
+    loops: Vec<(ExprId, Type)>,
+    /// The trait and type of every `impl Trait for Type` seen so far.
+    trait_impls: Vec<(TraitId, Type, ImplDefId)>,
+    /// The expansion of every type alias resolved so far.
+    type_aliases: ArenaMap<TypeAliasDefId, Type>,
+    /// The type aliases being expanded, innermost last.
+    type_aliases_in_progress: Vec<TypeAliasDefId>,
+    /// The signature of every `extern fn`. Their types are all written out,
+    /// so these never contain type variables.
+    extern_fn_types: ArenaMap<ExternFnDefId, FnType>,
+    /// The traits each type parameter of every generic fn is bounded by.
+    bounds: ArenaMap<FnDefId, Vec<Vec<TraitId>>>,
+    /// Every use of a generic fn, with the type arguments it was given.
+    /// Their bounds are checked once the arguments are known.
+    instantiations: Vec<(ExprId, FnDefId, Vec<Type>)>,
+    /// The comparisons whose operand type was not known yet when they were
+    /// inferred, such as `a == b` where `a` is a parameter without a type.
+    /// They are checked again once the type is known.
+    unresolved_cmps: Vec<(CmpBinop, Type)>,
 }
 
 impl Ctx {
@@ -68,11 +112,29 @@ impl Ctx {
             result: InferenceResult::default(),
             table: InferenceTable::default(),
             fn_type: None,
-            loop_type: None,
+            loops: Vec::new(),
+            trait_impls: Vec::new(),
+            type_aliases: ArenaMap::default(),
+            type_aliases_in_progress: Vec::new(),
+            extern_fn_types: ArenaMap::default(),
+            bounds: ArenaMap::default(),
+            instantiations: Vec::new(),
+            unresolved_cmps: Vec::new(),
         }
     }
 
     fn finish(mut self) -> InferenceResult {
+        for (op, lhs_type) in std::mem::take(&mut self.unresolved_cmps) {
+            let lhs_type = self.propagate_type_completely(&lhs_type);
+            if !self.is_comparable(&lhs_type, op) {
+                self.result.diagnostics.push(Diagnostic::CannotApplyBinop {
+                    lhs_type,
+                    rhs_type: Type::Unknown,
+                    op: Binop::Cmp(op),
+                });
+            }
+        }
+
         let mut result = std::mem::take(&mut self.result);
         for (id, ty) in result.type_of_expr.iter_mut() {
             let was_unknown = ty == &Type::Unknown;
@@ -108,23 +170,125 @@ impl Ctx {
             *ty = self.propagate_fn_type_completely(ty);
         }
 
+        for (expr, fn_id, args) in std::mem::take(&mut self.instantiations) {
+            let args = (args.iter())
+                .map(|arg| self.propagate_type_completely(arg))
+                .collect::<Vec<_>>();
+            self.check_bounds(&mut result, expr, fn_id, &args);
+        }
+
+        for diagnostic in &mut result.diagnostics {
+            for ty in diagnostic.types_mut() {
+                *ty = self.propagate_type_completely(ty);
+            }
+        }
+
+        self.check_lits(&mut result);
         result
     }
 
+    /// Range-checks the numeric literals against the types inference gave
+    /// them, such as the `300` in `let x: U8 = 300`. Literals with a suffix
+    /// have already been checked when lowering.
+    fn check_lits(&self, result: &mut InferenceResult) {
+        let exprs = &self.module.data.exprs;
+        // `-128` is a valid `I8`, even though `128` is not
+        let negated = (exprs.iter())
+            .filter_map(|(_, expr)| match expr {
+                Expr::Unop {
+                    op: Unop::Sub,
+                    expr,
+                    ..
+                } if matches!(exprs[*expr], Expr::Lit(Lit::Int(..))) => Some(*expr),
+                _ => None,
+            })
+            .collect::<HashSet<_>>();
+
+        for (id, expr) in exprs.iter() {
+            let ctor = match result.type_of_expr.get(id).and_then(Type::ctor) {
+                Some(ctor) => *ctor,
+                None => continue,
+            };
+            let value = match expr {
+                Expr::Lit(lit) if matches!(lit, Lit::Int(..) | Lit::Float(..)) => {
+                    if negated.contains(&id) {
+                        continue;
+                    }
+                    consteval::eval_lit(ctor, *lit)
+                }
+                Expr::Unop {
+                    op: Unop::Sub,
+                    expr,
+                    ..
+                } if negated.contains(expr) => match exprs[*expr] {
+                    Expr::Lit(Lit::Int(val, _)) => consteval::eval_neg_lit(ctor, val),
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            if let (Err(EvalError::Overflow), Some(suffix)) = (value, ctor.num_suffix()) {
+                result.diagnostics.push(Diagnostic::BadLit {
+                    span: self.module.source[id].span(),
+                    error: LitError::OutOfRange(suffix),
+                });
+            }
+        }
+    }
+
+    /// Checks that the type arguments a generic fn was given at `expr`
+    /// satisfy the bounds of its type parameters.
+    fn check_bounds(
+        &self,
+        result: &mut InferenceResult,
+        expr: ExprId,
+        fn_id: FnDefId,
+        args: &[Type],
+    ) {
+        if args.iter().any(|arg| arg.any(&|ty| ty == &Type::Unknown)) {
+            (result.diagnostics).push(Diagnostic::InferenceFail(InferenceId::Expr(expr)));
+            return;
+        }
+        for (arg, bounds) in args.iter().zip(&self.bounds[fn_id]) {
+            for trait_id in bounds {
+                if !self.implements(arg, *trait_id) {
+                    result.diagnostics.push(Diagnostic::UnsatisfiedBound {
+                        expr,
+                        ty: arg.clone(),
+                        trait_id: *trait_id,
+                    });
+                }
+            }
+        }
+    }
+
     fn with_fn_type<T>(&mut self, ty: FnType, f: impl Fn(&mut Self) -> T) -> T {
         let old_fn_type = self.fn_type.clone();
+        // `break` and `continue` cannot jump out of a function
+        let old_loops = std::mem::take(&mut self.loops);
         self.fn_type = Some(ty);
         let ret = f(self);
         self.fn_type = old_fn_type;
+        self.loops = old_loops;
         ret
     }
 
-    fn with_loop_type<T>(&mut self, ty: Type, f: impl Fn(&mut Self) -> T) -> T {
-        let old_loop_type = self.loop_type.clone();
-        self.loop_type = Some(ty);
-        let ret = f(self);
-        self.loop_type = old_loop_type;
-        ret
+    /// The index in `self.loops` of the loop targeted by the `break` or
+    /// `continue` `id`. Reports `expr` if there is no enclosing loop. An
+    /// unknown label has already been reported by `Scopes`.
+    fn target_loop(&mut self, id: ExprId, expr: ExprId, label: &Option<Label>) -> Option<usize> {
+        match label {
+            None if self.loops.is_empty() => {
+                self.result
+                    .diagnostics
+                    .push(Diagnostic::BreakNotInLoop(expr));
+                None
+            }
+            None => Some(self.loops.len() - 1),
+            Some(_) => {
+                let target = self.scopes.label_target(id)?;
+                (self.loops.iter()).rposition(|(loop_expr, _)| *loop_expr == target)
+            }
+        }
     }
 
     fn set_expr_type(&mut self, id: ExprId, ty: Type) { self.result.type_of_expr.insert(id, ty); }
@@ -135,6 +299,8 @@ impl Ctx {
     }
 
     fn new_type_var(&mut self) -> Type { Type::Infer(InferType::Var(self.table.new_type_var())) }
+    fn new_int_var(&mut self) -> Type { Type::Infer(InferType::Int(self.table.new_type_var())) }
+    fn new_float_var(&mut self) -> Type { Type::Infer(InferType::Float(self.table.new_type_var())) }
 
     fn resolve_type(&mut self, id: TypeId) -> Type {
         let ty = self.module.data[id].clone();
@@ -154,6 +320,28 @@ impl Ctx {
         ty
     }
 
+    /// Suggestions for `var` among the names in `visible` whose denotations
+    /// satisfy `is_wanted`; see `fix::rename_suggestions`.
+    fn suggest_names(
+        &self,
+        var: VarId,
+        visible: impl Iterator<Item = (Var, Denotation)>,
+        is_wanted: impl Fn(Denotation) -> bool,
+    ) -> (Vec<FixIt>, Vec<Var>) {
+        let candidates = visible
+            .filter(|(_, denotation)| is_wanted(*denotation))
+            .map(|(name, _)| name);
+        fix::rename_suggestions(var, &self.module.data[var], candidates)
+    }
+
+    /// Suggestions for the field `var` among `fields`.
+    fn suggest_fields(&self, var: VarId, fields: &[StructField]) -> (Vec<FixIt>, Vec<Var>) {
+        let candidates = fields
+            .iter()
+            .map(|field| self.module.data[field.name].clone());
+        fix::rename_suggestions(var, &self.module.data[var], candidates)
+    }
+
     fn resolve_var_type(&mut self, id: TypeId, var_id: VarId) -> Type {
         let var = &self.module.data[var_id];
         let denotation = self.scopes.lookup_type(id, var);
@@ -161,35 +349,163 @@ impl Ctx {
             Some(Denotation::Builtin(b)) if b.kind() == BuiltinKind::Type => b.ty(),
             Some(Denotation::Struct(id)) => Type::struct_(id),
             Some(Denotation::Enum(id)) => Type::enum_(id),
+            Some(Denotation::TypeAlias(alias)) => self.resolve_type_alias(alias),
+            Some(Denotation::GenericParam(fn_id, idx)) => Type::param(fn_id, idx),
             _ => {
+                let visible = self.scopes.visible_from_type(id);
+                let (fix_its, suggestions) = self.suggest_names(var_id, visible, |d| {
+                    matches!(
+                        d,
+                        Denotation::Struct(_)
+                            | Denotation::Enum(_)
+                            | Denotation::TypeAlias(_)
+                            | Denotation::GenericParam(..)
+                    ) || matches!(d, Denotation::Builtin(b) if b.kind() == BuiltinKind::Type)
+                });
                 self.result.diagnostics.push(Diagnostic::UnboundVar {
                     id: Right(id),
                     var: var_id,
                     denotation,
+                    fix_its,
+                    suggestions,
                 });
                 Type::Unknown
             }
         }
     }
 
+    /// Expands the type alias `id`. Every alias is expanded once, so that
+    /// an alias that refers back to itself is only reported once.
+    fn resolve_type_alias(&mut self, id: TypeAliasDefId) -> Type {
+        if let Some(ty) = self.type_aliases.get(id) {
+            return ty.clone();
+        }
+        if self.type_aliases_in_progress.contains(&id) {
+            self.result
+                .diagnostics
+                .push(Diagnostic::CyclicTypeAlias(id));
+            return Type::Unknown;
+        }
+        self.type_aliases_in_progress.push(id);
+        let ty = self.resolve_type(self.module.data[id].ty);
+        self.type_aliases_in_progress.pop();
+        self.type_aliases.insert(id, ty.clone());
+        ty
+    }
+
     fn resolve_var_expr(&mut self, id: ExprId, var_id: VarId) -> Type {
         let var = &self.module.data[var_id];
         let denotation = self.scopes.lookup_expr(id, var);
         match denotation {
             Some(Denotation::Local(id)) => self.result.type_of_pat[id].clone(),
-            Some(Denotation::Fn(id)) => self.result.type_of_fn[id].clone().into(),
+            Some(Denotation::Fn(fn_id)) => self.instantiate_fn(id, fn_id).into(),
+            Some(Denotation::ExternFn(id)) => self.extern_fn_types[id].clone().into(),
+            Some(Denotation::Const(id)) => {
+                self.result.type_of_type[self.module.data[id].ty].clone()
+            }
             Some(Denotation::Builtin(b)) if b.kind() == BuiltinKind::Value => b.ty(),
             _ => {
+                let visible = self.scopes.visible_from_expr(id);
+                let (fix_its, suggestions) = self.suggest_names(var_id, visible, |d| {
+                    matches!(
+                        d,
+                        Denotation::Local(_)
+                            | Denotation::Fn(_)
+                            | Denotation::ExternFn(_)
+                            | Denotation::Const(_)
+                    ) || matches!(d, Denotation::Builtin(b) if b.kind() == BuiltinKind::Value)
+                });
                 self.result.diagnostics.push(Diagnostic::UnboundVar {
                     id: Left(id),
                     var: var_id,
                     denotation,
+                    fix_its,
+                    suggestions,
                 });
                 Type::Unknown
             }
         }
     }
 
+    /// The type of the fn `fn_id` where it is used at `expr`. Each type
+    /// parameter of a generic fn is replaced by a fresh type variable, which
+    /// is checked against the parameter's bounds once inference is done.
+    fn instantiate_fn(&mut self, expr: ExprId, fn_id: FnDefId) -> FnType {
+        let fn_type = self.result.type_of_fn[fn_id].clone();
+        let generics = self.module.data[fn_id].generics.len();
+        if generics == 0 {
+            return fn_type;
+        }
+        let args = (0..generics)
+            .map(|_| self.new_type_var())
+            .collect::<Vec<_>>();
+        let fn_type = fn_type.subst(fn_id, &args);
+        self.instantiations.push((expr, fn_id, args));
+        fn_type
+    }
+
+    /// Whether `ty` implements the trait `trait_id`: with an `impl` block, a
+    /// built-in impl, or because it is a type parameter bounded by the trait.
+    fn implements(&self, ty: &Type, trait_id: TraitId) -> bool {
+        match ty.ctor() {
+            Some(Ctor::Param(fn_id, idx)) => self.bounds[*fn_id][*idx as usize].contains(&trait_id),
+            _ => {
+                self.has_builtin_impl(ty, trait_id)
+                    || (self.trait_impls.iter())
+                        .any(|(id, impl_ty, _)| *id == trait_id && impl_ty == ty)
+            }
+        }
+    }
+
+    /// The scalar types implement the builtin traits `Eq` and `Ord`. Their
+    /// arithmetic operators are built in, but not through trait impls.
+    fn has_builtin_impl(&self, ty: &Type, trait_id: TraitId) -> bool {
+        matches!(trait_id, TraitId::Builtin(Builtin::Eq | Builtin::Ord)) && ty.is_scalar()
+    }
+
+    /// The names of the fns of the trait `trait_id`.
+    fn trait_fn_names(&self, trait_id: TraitId) -> Vec<Var> {
+        match trait_id {
+            TraitId::Def(id) => (self.module.data[id].fns.iter())
+                .map(|fn_sig| self.module.data[fn_sig.name].clone())
+                .collect(),
+            TraitId::Builtin(builtin) => (builtin.trait_method().iter())
+                .map(|name| Var::new(*name))
+                .collect(),
+        }
+    }
+
+    /// The type of the fn `name` of the trait `trait_id` when it is
+    /// implemented for `self_ty`. If `is_method`, the fn must take `self`.
+    fn trait_fn_type(
+        &mut self,
+        trait_id: TraitId,
+        name: &Var,
+        self_ty: &Type,
+        is_method: bool,
+    ) -> Option<FnType> {
+        match trait_id {
+            TraitId::Def(id) => {
+                let data = &self.module.data;
+                let fn_sig = (data[id].fns.iter())
+                    .find(|fn_sig| {
+                        let takes_self = (fn_sig.params.first())
+                            .map_or(false, |param| data.is_self_pat(param.pat));
+                        &data[fn_sig.name] == name && (takes_self || !is_method)
+                    })?
+                    .clone();
+                Some(self.resolve_fn_sig(&fn_sig, self_ty))
+            }
+            TraitId::Builtin(builtin) => {
+                let method = builtin.trait_method()?;
+                if name.as_str() != method {
+                    return None;
+                }
+                builtin.trait_method_type(self_ty)
+            }
+        }
+    }
+
     /// Propagates the type as far as currently possible, replacing type
     /// variables by their known types. All types returned by the `infer_*`
     /// functions should be resolved as far as possible, i.e. contain no
@@ -218,7 +534,7 @@ impl Ctx {
 
     fn try_to_unify(&mut self, id: Either<ExprId, PatId>, expected: &Type, got: &Type) -> Type {
         match self.coerce(got, expected) {
-            false => {
+            Err(UnifyError::Mismatch) => {
                 self.result.diagnostics.push(Diagnostic::TypeMismatch {
                     id,
                     expected: expected.clone(),
@@ -226,8 +542,16 @@ impl Ctx {
                 });
                 got.clone()
             }
-            true if expected == &Type::Unknown => got.clone(),
-            true => expected.clone(),
+            Err(UnifyError::InfiniteType) => {
+                self.result.diagnostics.push(Diagnostic::InfiniteType {
+                    id,
+                    expected: expected.clone(),
+                    got: got.clone(),
+                });
+                Type::Unknown
+            }
+            Ok(()) if expected == &Type::Unknown => got.clone(),
+            Ok(()) => expected.clone(),
         }
     }
 
@@ -241,11 +565,11 @@ impl Ctx {
         self.propagate_type_as_far_as_possible(&ty)
     }
 
-    fn unify(&mut self, t1: &Type, t2: &Type) -> bool { self.table.unify(t1, t2) }
+    fn unify(&mut self, t1: &Type, t2: &Type) -> Result<(), UnifyError> { self.table.unify(t1, t2) }
 
-    fn coerce(&mut self, from: &Type, to: &Type) -> bool {
+    fn coerce(&mut self, from: &Type, to: &Type) -> Result<(), UnifyError> {
         if from == &Type::NEVER {
-            true
+            Ok(())
         } else {
             self.unify(from, to)
         }
@@ -273,6 +597,16 @@ impl Ctx {
             Decl::Struct(id) => self.infer_struct_decl(id),
             Decl::Enum(id) => self.infer_enum_decl(id),
             Decl::Fn(id) => self.infer_fn_decl(id),
+            Decl::Impl(id) => self.infer_impl_decl(id),
+            // the signatures of a trait are resolved separately for each impl
+            Decl::Trait(_) => {}
+            Decl::TypeAlias(id) => {
+                self.resolve_type_alias(id);
+            }
+            Decl::Const(id) => {
+                self.resolve_type(self.module.data[id].ty);
+            }
+            Decl::ExternFn(id) => self.infer_extern_fn_decl(id),
         }
     }
 
@@ -292,25 +626,264 @@ impl Ctx {
         }
     }
 
+    fn infer_impl_decl(&mut self, id: ImplDefId) {
+        let impl_def = self.module.data[id].clone();
+        let ty = self.resolve_type(impl_def.ty);
+        match ty {
+            Type::App {
+                ctor: Ctor::Struct(_) | Ctor::Enum(_),
+                ..
+            }
+            | Type::Unknown => {}
+            // builtin types can implement traits, but cannot have inherent methods
+            _ if impl_def.trait_.is_some() && Builtin::of_type(&ty).is_some() => {}
+            _ => self.result.diagnostics.push(Diagnostic::BadImplType {
+                id: impl_def.ty,
+                ty: ty.clone(),
+            }),
+        }
+        for fn_id in &impl_def.fns {
+            self.infer_fn_decl(*fn_id);
+        }
+        if let Some(trait_id) = impl_def
+            .trait_
+            .and_then(|trait_| self.resolve_trait(trait_))
+        {
+            self.check_trait_impl(id, trait_id, &ty);
+        }
+    }
+
+    fn resolve_trait(&mut self, id: TypeId) -> Option<TraitId> {
+        let var_id = match self.module.data[id] {
+            hir::Type::Var(var) => var,
+            _ => unreachable!(),
+        };
+        let var = &self.module.data[var_id];
+        let denotation = self.scopes.lookup_type(id, var);
+        match denotation.and_then(Denotation::as_trait) {
+            Some(trait_id) => Some(trait_id),
+            None => {
+                let visible = self.scopes.visible_from_type(id);
+                let (fix_its, suggestions) =
+                    self.suggest_names(var_id, visible, |d| d.as_trait().is_some());
+                self.result.diagnostics.push(Diagnostic::UnboundVar {
+                    id: Right(id),
+                    var: var_id,
+                    denotation,
+                    fix_its,
+                    suggestions,
+                });
+                None
+            }
+        }
+    }
+
+    /// Checks that the impl is the only one of the trait for `self_ty`, and
+    /// that it defines exactly the fns of the trait, with matching signatures.
+    fn check_trait_impl(&mut self, impl_id: ImplDefId, trait_id: TraitId, self_ty: &Type) {
+        let impl_def = self.module.data[impl_id].clone();
+        let trait_fn_names = self.trait_fn_names(trait_id);
+
+        if self.has_builtin_impl(self_ty, trait_id) {
+            self.result
+                .diagnostics
+                .push(Diagnostic::ConflictsWithBuiltinImpl {
+                    impl_def: impl_id,
+                    ty: self_ty.clone(),
+                });
+        } else if self_ty != &Type::Unknown {
+            let first = self
+                .trait_impls
+                .iter()
+                .find(|(id, ty, _)| *id == trait_id && ty == self_ty);
+            match first {
+                Some((_, _, first)) => self.result.diagnostics.push(Diagnostic::ConflictingImpls {
+                    first: *first,
+                    second: impl_id,
+                }),
+                None => self.trait_impls.push((trait_id, self_ty.clone(), impl_id)),
+            }
+        }
+
+        for name in &trait_fn_names {
+            let fn_id = impl_def
+                .fns
+                .iter()
+                .copied()
+                .find(|fn_id| &self.module.data[self.module.data[*fn_id].name] == name);
+            match fn_id {
+                None => self
+                    .result
+                    .diagnostics
+                    .push(Diagnostic::MissingTraitMethod {
+                        impl_def: impl_id,
+                        method: name.clone(),
+                    }),
+                Some(fn_id) => {
+                    let expected = self.trait_fn_type(trait_id, name, self_ty, false);
+                    if let Some(expected) = expected.map(Type::from) {
+                        let got: Type = self.result.type_of_fn[fn_id].clone().into();
+                        if self.unify(&expected, &got).is_err() {
+                            self.result
+                                .diagnostics
+                                .push(Diagnostic::TraitMethodMismatch {
+                                    fn_def: fn_id,
+                                    expected,
+                                    got,
+                                });
+                        }
+                    }
+                }
+            }
+        }
+
+        for fn_id in impl_def.fns {
+            let name = &self.module.data[self.module.data[fn_id].name];
+            if !trait_fn_names.contains(name) {
+                self.result.diagnostics.push(Diagnostic::NotATraitMethod {
+                    fn_def: fn_id,
+                    trait_id,
+                });
+            }
+        }
+    }
+
+    fn resolve_fn_sig(&mut self, fn_sig: &FnSig, self_ty: &Type) -> FnType {
+        let params = fn_sig
+            .params
+            .iter()
+            .map(|param| match param.ty {
+                Some(ty) => self.resolve_type_with_self(ty, self_ty),
+                None if self.module.data.is_self_pat(param.pat) => self_ty.clone(),
+                None => self.new_type_var(),
+            })
+            .collect();
+        let ret = fn_sig
+            .ret_type
+            .map_or(Type::UNIT, |ty| self.resolve_type_with_self(ty, self_ty));
+        FnType { params, ret }
+    }
+
+    /// Like `resolve_type`, but `Self` stands for `self_ty`.
+    fn resolve_type_with_self(&mut self, id: TypeId, self_ty: &Type) -> Type {
+        match self.module.data[id].clone() {
+            hir::Type::Var(var) if self.module.data[var].as_str() == "Self" => self_ty.clone(),
+            hir::Type::Tuple(tys) => Type::tuple(
+                tys.iter()
+                    .map(|ty| self.resolve_type_with_self(*ty, self_ty))
+                    .collect(),
+            ),
+            hir::Type::Fn { params, ret } => Type::function(
+                params
+                    .iter()
+                    .map(|ty| self.resolve_type_with_self(*ty, self_ty))
+                    .collect(),
+                self.resolve_type_with_self(ret, self_ty),
+            ),
+            _ => self.resolve_type(id),
+        }
+    }
+
     fn infer_fn_decl(&mut self, fn_id: FnDefId) {
         let fn_decl = self.module.data[fn_id].clone();
+        if fn_decl.is_generic() {
+            let bounds = (fn_decl.generics.iter())
+                .map(|generic| {
+                    (generic.bounds.iter())
+                        .filter_map(|bound| self.resolve_trait(*bound))
+                        .collect()
+                })
+                .collect();
+            self.bounds.insert(fn_id, bounds);
+        }
         let params = fn_decl
             .params
             .iter()
             .map(|param| self.infer_binding(param.pat, param.ty, None))
-            .collect();
+            .collect::<Vec<_>>();
         let ret = fn_decl
             .ret_type
             .map_or(Type::UNIT, |ty| self.resolve_type(ty));
+        // every use of a generic fn gets its own copy of its signature, which
+        // cannot depend on what its body is later inferred to be
+        let is_infer = |ty: &Type| matches!(ty, Type::Infer(_));
+        let mut sig = params.iter().chain(std::iter::once(&ret));
+        if fn_decl.is_generic() && sig.any(|ty| ty.any(&is_infer)) {
+            self.result
+                .diagnostics
+                .push(Diagnostic::UnannotatedGenericFn(fn_id));
+        }
         self.set_fn_type(fn_id, FnType { params, ret });
     }
 
+    /// Only scalars can be passed to and from C, and so an extern fn's types
+    /// must all be known from its signature.
+    fn infer_extern_fn_decl(&mut self, id: ExternFnDefId) {
+        let extern_fn_def = self.module.data[id].clone();
+        let params = extern_fn_def
+            .params
+            .iter()
+            .map(|param| match param.ty {
+                Some(ty) => {
+                    let param_ty = self.infer_binding(param.pat, Some(ty), None);
+                    self.check_ffi_safe(ty, &param_ty, false);
+                    param_ty
+                }
+                // reported when lowering
+                None => self.infer_pat(&Type::Unknown, param.pat),
+            })
+            .collect();
+        let ret = match extern_fn_def.ret_type {
+            Some(ty) => {
+                let ret = self.resolve_type(ty);
+                self.check_ffi_safe(ty, &ret, true);
+                ret
+            }
+            None => Type::UNIT,
+        };
+        self.extern_fn_types.insert(id, FnType { params, ret });
+    }
+
+    fn check_ffi_safe(&mut self, id: TypeId, ty: &Type, is_ret: bool) {
+        let is_safe = match ty {
+            Type::Unknown => true,
+            _ if is_ret && (ty == &Type::UNIT || ty == &Type::NEVER) => true,
+            _ => ty.ctor().map_or(false, |ctor| {
+                matches!(ctor, Ctor::Bool | Ctor::Char) || ctor.is_int() || ctor.is_float()
+            }),
+        };
+        if !is_safe {
+            self.result
+                .diagnostics
+                .push(Diagnostic::NotFfiSafe { id, ty: ty.clone() });
+        }
+    }
+
     fn infer_decl_body(&mut self, decl: Decl) {
         match decl {
-            Decl::Struct(_) | Decl::Enum(_) => {}
+            Decl::Struct(_)
+            | Decl::Enum(_)
+            | Decl::Trait(_)
+            | Decl::TypeAlias(_)
+            | Decl::ExternFn(_) => {}
+            Decl::Const(id) => {
+                let const_def = self.module.data[id];
+                let ty = self.result.type_of_type[const_def.ty].clone();
+                let expr_ty = self.infer_expr(&Type::Unknown, const_def.expr);
+                self.try_to_unify_and_propagate_as_far_as_possible(
+                    Left(const_def.expr),
+                    &ty,
+                    &expr_ty,
+                );
+            }
             Decl::Fn(fn_id) => {
                 self.infer_fn_body(fn_id);
             }
+            Decl::Impl(id) => {
+                for fn_id in self.module.data[id].fns.clone() {
+                    self.infer_fn_body(fn_id);
+                }
+            }
         };
     }
 
@@ -346,13 +919,19 @@ impl Ctx {
             None => ty,
         };
         let expr_ty = self.propagate_type_as_far_as_possible(&expr_ty);
-        self.infer_pat(&expr_ty, pat)
+        let ty = self.infer_pat(&expr_ty, pat);
+        if !self.is_irrefutable(pat, &ty) {
+            self.result
+                .diagnostics
+                .push(Diagnostic::RefutablePat { pat });
+        }
+        ty
     }
 
     fn infer_pat(&mut self, expected: &Type, id: PatId) -> Type {
         let pat = self.module.data[id].clone();
         let ty = match pat {
-            Pat::Var(_) | Pat::Ignore => expected.clone(),
+            Pat::Var { .. } | Pat::Ignore => expected.clone(),
             Pat::Tuple(pats) => {
                 let expectations = expected.as_tuple().unwrap_or(&[]);
                 let expectations = expectations.iter().chain(std::iter::repeat(&Type::Unknown));
@@ -363,16 +942,83 @@ impl Ctx {
                     .collect();
                 Type::tuple(tys)
             }
+            Pat::Enum {
+                name,
+                variant,
+                fields,
+            } => self.infer_enum_pat(id, name, variant, &fields),
         };
         let ty = self.propagate_type_as_far_as_possible(&ty);
         self.set_pat_type(id, ty.clone());
         self.try_to_unify_and_propagate_as_far_as_possible(Right(id), expected, &ty)
     }
 
+    fn infer_enum_pat(
+        &mut self,
+        pat: PatId,
+        name: VarId,
+        variant: VarId,
+        fields: &[FieldPat],
+    ) -> Type {
+        let var = &self.module.data[name];
+        let denotation = self.scopes.lookup_pat(pat, var);
+        let enum_id = match denotation {
+            Some(Denotation::Enum(id)) => id,
+            _ => {
+                self.result.diagnostics.push(Diagnostic::NotAnEnum {
+                    pat,
+                    var: name,
+                    denotation,
+                });
+                for field in fields {
+                    self.infer_pat(&Type::Unknown, field.pat);
+                }
+                return Type::Unknown;
+            }
+        };
+
+        let enum_def = self.module.data[enum_id].clone();
+        let variant_def = (enum_def.variants.iter())
+            .find(|v| self.module.data[v.name] == self.module.data[variant]);
+        if variant_def.is_none() {
+            self.result.diagnostics.push(Diagnostic::NoSuchVariant {
+                id: Right(pat),
+                enum_def: enum_id,
+                variant,
+            });
+        }
+        for field in fields {
+            let expected = match variant_def {
+                None => Type::Unknown,
+                Some(variant_def) => {
+                    let field_def = (variant_def.fields.iter())
+                        .find(|f| self.module.data[f.name] == self.module.data[field.name]);
+                    match field_def {
+                        Some(field_def) => self.result.type_of_type[field_def.ty].clone(),
+                        None => {
+                            let (fix_its, suggestions) =
+                                self.suggest_fields(field.name, &variant_def.fields);
+                            self.result.diagnostics.push(Diagnostic::NoSuchPatField {
+                                pat,
+                                field: field.name,
+                                possible_fields: variant_def.fields.clone(),
+                                fix_its,
+                                suggestions,
+                            });
+                            Type::Unknown
+                        }
+                    }
+                }
+            };
+            self.infer_pat(&expected, field.pat);
+        }
+        Type::enum_(enum_id)
+    }
+
     fn infer_expr(&mut self, expected: &Type, id: ExprId) -> Type {
         let expr = self.module.data[id].clone();
         let ty = match expr {
-            Expr::Lit(lit) => lit.ty(),
+            Expr::Lit(lit) => self.infer_lit(lit),
             Expr::Var(var) => self.resolve_var_expr(id, var),
             Expr::Tuple(exprs) => self.infer_tuple_expr(expected, &exprs),
             Expr::Struct { name, fields } => self.infer_struct_expr(id, name, &fields),
@@ -386,15 +1032,23 @@ impl Ctx {
                 then_branch,
                 else_branch,
             } => self.infer_if_expr(test, then_branch, else_branch),
+            Expr::Match { test, arms } => self.infer_match_expr(id, test, &arms),
             Expr::Lambda { params, expr } => self.infer_lambda_expr(expected, &params, expr),
-            Expr::Call { func, args } => self.infer_call_expr(func, &args),
+            Expr::Call { func, args } => self.infer_call_expr(id, func, &args),
+            Expr::MethodCall {
+                receiver,
+                method,
+                args,
+            } => self.infer_method_call_expr(id, receiver, method, &args),
+            Expr::Path { ty, name } => self.infer_path_expr(id, ty, name),
             Expr::Field { expr, field } => self.infer_field_expr(expr, field),
-            Expr::Unop { op, expr } => self.infer_unop_expr(op, expr),
-            Expr::Binop { lhs, op, rhs } => self.infer_binop_expr(op, lhs, rhs),
-            Expr::Loop(expr) => self.infer_loop_expr(expected, expr),
+            Expr::Unop { op, expr, .. } => self.infer_unop_expr(id, op, expr),
+            Expr::Binop { lhs, op, rhs, .. } => self.infer_binop_expr(id, op, lhs, rhs),
+            Expr::Cast { expr, ty } => self.infer_cast_expr(id, expr, ty),
+            Expr::Loop { body, .. } => self.infer_loop_expr(id, expected, body),
             Expr::Return(expr) => self.infer_return_expr(id, expr),
-            Expr::Break(expr) => self.infer_break_expr(id, expr),
-            Expr::Continue => self.infer_continue_expr(id),
+            Expr::Break { label, expr } => self.infer_break_expr(id, &label, expr),
+            Expr::Continue { label } => self.infer_continue_expr(id, &label),
             Expr::Block { stmts, expr } => self.infer_block_expr(expected, &stmts, expr),
         };
         let ty = self.propagate_type_as_far_as_possible(&ty);
@@ -402,6 +1056,19 @@ impl Ctx {
         self.try_to_unify_and_propagate_as_far_as_possible(Left(id), expected, &ty)
     }
 
+    /// Numeric literals get a fresh `Int`/`Float` type variable, so that eg `5`
+    /// can be used as a `U8` if the context expects one, unless a suffix fixes
+    /// their type.
+    fn infer_lit(&mut self, lit: Lit) -> Type {
+        match lit {
+            Lit::Bool(_) => Type::BOOL,
+            Lit::Int(_, Some(suffix)) | Lit::Float(_, Some(suffix)) => Type::new0(suffix.into()),
+            Lit::Int(_, None) => self.new_int_var(),
+            Lit::Float(_, None) => self.new_float_var(),
+            Lit::Char(_) => Type::CHAR,
+        }
+    }
+
     fn infer_tuple_expr(&mut self, expected: &Type, exprs: &[ExprId]) -> Type {
         let expectations = expected.as_tuple().unwrap_or(&[]);
         let expectations = expectations.iter().chain(std::iter::repeat(&Type::Unknown));
@@ -443,14 +1110,21 @@ impl Ctx {
             Some(Denotation::Enum(id)) => {
                 let enum_def = self.module.data[id].clone();
                 let enum_type = Type::enum_(id);
-                let variant = enum_def
+                let variant_def = enum_def
                     .variants
                     .iter()
                     .find(|v| self.module.data[v.name] == self.module.data[variant]);
-                match variant {
-                    None => todo!("No such variant"),
-                    Some(variant) => {
-                        self.infer_fields(expr, Some(&variant.fields), fields);
+                match variant_def {
+                    None => {
+                        self.result.diagnostics.push(Diagnostic::NoSuchVariant {
+                            id: Left(expr),
+                            enum_def: id,
+                            variant,
+                        });
+                        self.infer_fields(expr, None, fields);
+                    }
+                    Some(variant_def) => {
+                        self.infer_fields(expr, Some(&variant_def.fields), fields);
                     }
                 }
                 enum_type
@@ -481,10 +1155,13 @@ impl Ctx {
                         .find(|field| self.module.data[field.name] == self.module.data[init.name]);
                     match field {
                         None => {
+                            let (fix_its, suggestions) = self.suggest_fields(init.name, fields);
                             self.result.diagnostics.push(Diagnostic::NoSuchField {
                                 field: Field::Named(init.name),
                                 expr: init.val,
                                 possible_fields: Left(fields.to_vec()),
+                                fix_its,
+                                suggestions,
                             });
                             None
                         }
@@ -496,18 +1173,25 @@ impl Ctx {
         }
 
         if let Some(fields) = fields {
-            for field in fields {
-                let name = &self.module.data[field.name];
-                let init = inits.iter().find(|f| &self.module.data[f.name] == name);
-                match init {
-                    Some(_) => {}
-                    None => {
-                        self.result.diagnostics.push(Diagnostic::MissingField {
-                            expr,
-                            field: Field::Named(field.name),
-                        });
-                    }
-                }
+            let missing = (fields.iter())
+                .map(|field| field.name)
+                .filter(|name| !first_init.contains_key(&self.module.data[*name]))
+                .collect::<Vec<_>>();
+            // a single fix-it adds all the missing fields, so that their edits
+            // do not have to agree on where the commas go
+            let mut fix_its = match missing.as_slice() {
+                [] => Vec::new(),
+                _ => vec![FixIt::AddFields {
+                    expr,
+                    fields: missing.clone(),
+                }],
+            };
+            for field in missing {
+                self.result.diagnostics.push(Diagnostic::MissingField {
+                    expr,
+                    field: Field::Named(field),
+                    fix_its: std::mem::take(&mut fix_its),
+                });
             }
         }
     }
@@ -527,7 +1211,7 @@ impl Ctx {
             Some(else_branch) => {
                 let then_ty = self.infer_expr(&Type::Unknown, then_branch);
                 let else_ty = self.infer_expr(&Type::Unknown, else_branch);
-                if self.unify(&then_ty, &else_ty) {
+                if self.unify(&then_ty, &else_ty).is_ok() {
                     then_ty
                 } else {
                     self.result.diagnostics.push(Diagnostic::IfBranchMismatch {
@@ -542,6 +1226,29 @@ impl Ctx {
         }
     }
 
+    /// The arms are tried in order, so their bodies must all have the same
+    /// type, like the branches of an `if`.
+    fn infer_match_expr(&mut self, expr: ExprId, test: ExprId, arms: &[MatchArm]) -> Type {
+        let test_ty = self.infer_expr(&Type::Unknown, test);
+        let mut ty = Type::NEVER;
+        for arm in arms {
+            self.infer_pat(&test_ty, arm.pat);
+            let arm_ty = self.infer_expr(&Type::Unknown, arm.expr);
+            if ty == Type::NEVER {
+                ty = arm_ty;
+            } else if self.unify(&ty, &arm_ty).is_err() {
+                self.result.diagnostics.push(Diagnostic::TypeMismatch {
+                    id: Left(arm.expr),
+                    expected: ty.clone(),
+                    got: arm_ty,
+                });
+            }
+        }
+        let test_ty = self.propagate_type_as_far_as_possible(&test_ty);
+        self.check_match_is_exhaustive(expr, &test_ty, arms);
+        ty
+    }
+
     fn infer_lambda_expr(&mut self, expected: &Type, params: &[Param], body: ExprId) -> Type {
         let param_types = params
             .iter()
@@ -552,13 +1259,26 @@ impl Ctx {
             params: param_types,
             ret: ret_type.clone(),
         };
-        self.unify(&lambda_ty.clone().into(), expected);
+        // a mismatch is reported when `infer_expr` unifies the lambda's type with
+        // `expected`
+        let _ = self.unify(&lambda_ty.clone().into(), expected);
         self.with_fn_type(lambda_ty.clone(), |this| this.infer_expr(&ret_type, body));
         lambda_ty.into()
     }
 
-    fn infer_call_expr(&mut self, func: ExprId, args: &[ExprId]) -> Type {
-        let func_ty = self.infer_expr(&Type::Unknown, func);
+    fn infer_call_expr(&mut self, expr: ExprId, func: ExprId, args: &[ExprId]) -> Type {
+        let mut func_ty = self.infer_expr(&Type::Unknown, func);
+        if let Type::Infer(InferType::Var(_)) = func_ty {
+            // calling a value of unknown type constrains it to be a function
+            let fn_ty: Type = FnType {
+                params: args.iter().map(|_| self.new_type_var()).collect(),
+                ret: self.new_type_var(),
+            }
+            .into();
+            if self.unify(&func_ty, &fn_ty).is_ok() {
+                func_ty = fn_ty;
+            }
+        }
         match func_ty.as_fn() {
             None => {
                 self.result.diagnostics.push(Diagnostic::CalledNonFn {
@@ -574,6 +1294,7 @@ impl Ctx {
                         ty: func_ty,
                         expected: params.len(),
                         got: args.len(),
+                        fix_its: vec![fix::arg_count_fix(expr, params.len(), args.len())],
                     });
                 }
                 for (arg, param) in args.iter().zip(params.iter()) {
@@ -584,6 +1305,155 @@ impl Ctx {
         }
     }
 
+    /// The fn named `method` in an `impl` block for `ty`.
+    fn lookup_method(&self, ty: &Type, method: VarId) -> Option<FnDefId> {
+        let var = &self.module.data[method];
+        match ty.ctor()? {
+            Ctor::Struct(id) => self.scopes.lookup_struct_method(*id, var),
+            Ctor::Enum(id) => self.scopes.lookup_enum_method(*id, var),
+            _ => self
+                .scopes
+                .lookup_builtin_method(Builtin::of_type(ty)?, var),
+        }
+    }
+
+    /// The fn called by the method call `expr` and its type. It is either a
+    /// fn in an `impl` block for `ty`, or a trait fn if `ty` is a type
+    /// parameter bounded by the trait, or a scalar with a built-in impl of it.
+    fn lookup_method_type(
+        &mut self,
+        expr: ExprId,
+        ty: &Type,
+        method: VarId,
+    ) -> Option<(MethodCall, FnType)> {
+        let fn_id = self
+            .lookup_method(ty, method)
+            .filter(|fn_id| self.module.data.is_method(*fn_id));
+        if let Some(fn_id) = fn_id {
+            return Some((MethodCall::Fn(fn_id), self.instantiate_fn(expr, fn_id)));
+        }
+        let traits = match ty.ctor() {
+            Some(Ctor::Param(fn_id, idx)) => self.bounds[*fn_id][*idx as usize].clone(),
+            _ if ty.is_scalar() => vec![
+                TraitId::Builtin(Builtin::Eq),
+                TraitId::Builtin(Builtin::Ord),
+            ],
+            _ => return None,
+        };
+        let method = self.module.data[method].clone();
+        traits.into_iter().find_map(|trait_id| {
+            let fn_type = self.trait_fn_type(trait_id, &method, ty, true)?;
+            let call = match trait_id {
+                TraitId::Builtin(builtin) if ty.is_scalar() => {
+                    MethodCall::BuiltinCmp(builtin.builtin_cmp()?)
+                }
+                _ => MethodCall::Trait(trait_id),
+            };
+            Some((call, fn_type))
+        })
+    }
+
+    /// The type of the fn overloading an operator through the builtin trait
+    /// `operator_trait` for operands of type `ty`. A type parameter can only
+    /// use the operators of the traits it is bounded by.
+    fn lookup_operator(&mut self, ty: &Type, operator_trait: Option<Builtin>) -> Option<FnType> {
+        let trait_id = TraitId::Builtin(operator_trait?);
+        let denotation = match ty.ctor()? {
+            Ctor::Struct(id) => Denotation::Struct(*id),
+            Ctor::Enum(id) => Denotation::Enum(*id),
+            Ctor::Param(fn_id, idx) => {
+                if !self.bounds[*fn_id][*idx as usize].contains(&trait_id) {
+                    return None;
+                }
+                let method = Var::new(operator_trait?.trait_method()?);
+                return self.trait_fn_type(trait_id, &method, ty, false);
+            }
+            _ => return None,
+        };
+        let fn_id = (self.scopes).lookup_operator(denotation, operator_trait?)?;
+        Some(self.result.type_of_fn[fn_id].clone())
+    }
+
+    fn infer_method_call_expr(
+        &mut self,
+        expr: ExprId,
+        receiver: ExprId,
+        method: VarId,
+        args: &[ExprId],
+    ) -> Type {
+        let receiver_ty = self.infer_expr(&Type::Unknown, receiver);
+        let fn_type = match self.lookup_method_type(expr, &receiver_ty, method) {
+            Some((call, fn_type)) => {
+                self.result.method_calls.insert(expr, call);
+                fn_type
+            }
+            None => {
+                if receiver_ty != Type::Unknown {
+                    self.result.diagnostics.push(Diagnostic::NoSuchMethod {
+                        expr,
+                        ty: receiver_ty,
+                        method,
+                    });
+                }
+                for arg in args {
+                    self.infer_expr(&Type::Unknown, *arg);
+                }
+                return Type::Unknown;
+            }
+        };
+
+        let (self_param, params) = fn_type.params.split_first().unwrap();
+        self.try_to_unify(Left(receiver), self_param, &receiver_ty);
+        if args.len() != params.len() {
+            self.result.diagnostics.push(Diagnostic::ArgCountMismatch {
+                expr,
+                ty: fn_type.clone().into(),
+                expected: params.len(),
+                got: args.len(),
+                fix_its: vec![fix::arg_count_fix(expr, params.len(), args.len())],
+            });
+        }
+        for (arg, param) in args.iter().zip(params.iter()) {
+            self.infer_expr(param, *arg);
+        }
+        fn_type.ret
+    }
+
+    fn infer_path_expr(&mut self, expr: ExprId, ty: VarId, name: VarId) -> Type {
+        let var = &self.module.data[ty];
+        let denotation = self.scopes.lookup_expr(expr, var);
+        let (denotation, self_ty) = match denotation {
+            Some(Denotation::Struct(id)) => (Denotation::Struct(id), Type::struct_(id)),
+            Some(Denotation::Enum(id)) => (Denotation::Enum(id), Type::enum_(id)),
+            _ => {
+                let visible = self.scopes.visible_from_expr(expr);
+                let (fix_its, suggestions) = self.suggest_names(ty, visible, |d| {
+                    matches!(d, Denotation::Struct(_) | Denotation::Enum(_))
+                });
+                self.result.diagnostics.push(Diagnostic::UnboundVar {
+                    id: Left(expr),
+                    var: ty,
+                    denotation,
+                    fix_its,
+                    suggestions,
+                });
+                return Type::Unknown;
+            }
+        };
+        let method = &self.module.data[name];
+        match self.scopes.lookup_method(denotation, method) {
+            Some(fn_id) => self.instantiate_fn(expr, fn_id).into(),
+            None => {
+                self.result.diagnostics.push(Diagnostic::NoSuchMethod {
+                    expr,
+                    ty: self_ty,
+                    method: name,
+                });
+                Type::Unknown
+            }
+        }
+    }
+
     fn infer_field_expr(&mut self, base: ExprId, field: Field) -> Type {
         let base_type = self.infer_expr(&Type::Unknown, base);
         match base_type {
@@ -597,6 +1467,8 @@ impl Ctx {
                         expr: base,
                         possible_fields: Right(params.len() as u32),
                         field,
+                        fix_its: Vec::new(),
+                        suggestions: Vec::new(),
                     });
                     Type::Unknown
                 }
@@ -616,10 +1488,14 @@ impl Ctx {
                         match target {
                             Some(field) => self.result.type_of_type[field.ty].clone(),
                             None => {
+                                let (fix_its, suggestions) =
+                                    self.suggest_fields(name, &struct_def.fields);
                                 self.result.diagnostics.push(Diagnostic::NoSuchField {
                                     expr: base,
                                     possible_fields: Left(struct_def.fields.clone()),
                                     field,
+                                    fix_its,
+                                    suggestions,
                                 });
                                 Type::Unknown
                             }
@@ -630,6 +1506,8 @@ impl Ctx {
                             expr: base,
                             possible_fields: Left(struct_def.fields.clone()),
                             field,
+                            fix_its: Vec::new(),
+                            suggestions: Vec::new(),
                         });
                         Type::Unknown
                     }
@@ -645,27 +1523,136 @@ impl Ctx {
         }
     }
 
-    fn infer_unop_expr(&mut self, op: Unop, lhs: ExprId) -> Type {
+    fn infer_unop_expr(&mut self, expr: ExprId, op: Unop, lhs: ExprId) -> Type {
         let lhs_expectation = op.lhs_expectation();
         let lhs_type = self.infer_expr(&lhs_expectation, lhs);
+        if let Some(fn_type) = self.lookup_operator(&lhs_type, op.operator_trait()) {
+            match fn_type.params.as_slice() {
+                [param] => {
+                    self.try_to_unify(Left(lhs), param, &lhs_type);
+                }
+                params => self.result.diagnostics.push(Diagnostic::ArgCountMismatch {
+                    expr,
+                    ty: fn_type.clone().into(),
+                    expected: params.len(),
+                    got: 1,
+                    fix_its: Vec::new(),
+                }),
+            }
+            return fn_type.ret;
+        }
+        let ty = match op {
+            Unop::Add | Unop::Sub => self.expect_numeric(&lhs_type),
+            Unop::BitNot => self.expect_int_or_bool(lhs, &lhs_type, true),
+            Unop::Not => return op.return_type(&lhs_type),
+        };
+        if ty != Type::Unknown {
+            return ty;
+        }
+        if lhs_type != Type::Unknown {
+            self.result.diagnostics.push(Diagnostic::CannotApplyUnop {
+                lhs_type: lhs_type.clone(),
+                op,
+            });
+        }
         op.return_type(&lhs_type)
     }
 
+    /// The type of an operand of an arithmetic operator, which must be an
+    /// integer or a float. An operand whose type is not known yet is left
+    /// alone, since it could still be either. Returns `Type::Unknown` if `ty`
+    /// is not a number.
+    fn expect_numeric(&mut self, ty: &Type) -> Type {
+        match self.propagate_type_as_far_as_possible(ty) {
+            ty @ Type::Infer(InferType::Var(_)) => ty,
+            ty if ty.is_numeric() || ty == Type::NEVER => ty,
+            _ => Type::Unknown,
+        }
+    }
+
+    /// The type of `id`, the operand of a bitwise operator, which must be an
+    /// integer, or `Bool` if `allow_bool`. An operand whose type is not known
+    /// yet is assumed to be an integer. Returns `Type::Unknown` if `ty` is
+    /// neither.
+    fn expect_int_or_bool(&mut self, id: ExprId, ty: &Type, allow_bool: bool) -> Type {
+        match self.propagate_type_as_far_as_possible(ty) {
+            ty @ Type::Infer(InferType::Var(_)) => {
+                let int = self.new_int_var();
+                self.try_to_unify_and_propagate_as_far_as_possible(Left(id), &int, &ty)
+            }
+            ty if ty.is_int() || (allow_bool && ty == Type::BOOL) => ty,
+            _ => Type::Unknown,
+        }
+    }
+
     fn is_lvalue(&self, id: ExprId) -> bool {
-        let expr = &self.module.data[id];
-        matches!(expr, Expr::Var(_) | Expr::Field { .. })
+        match self.module.data[id] {
+            Expr::Var(_) => true,
+            Expr::Field { expr, .. } => self.is_lvalue(expr),
+            _ => false,
+        }
     }
 
-    fn infer_binop_expr(&mut self, op: Binop, lhs: ExprId, rhs: ExprId) -> Type {
-        if let Binop::Assign = op {
-            if !self.is_lvalue(lhs) {
+    /// Checks that the variable an lvalue (or the base of a field lvalue)
+    /// refers to is a local binding declared with `mut`.
+    fn check_lvalue_is_mut(&mut self, lhs: ExprId) {
+        let mut base = lhs;
+        while let Expr::Field { expr, .. } = self.module.data[base] {
+            base = expr;
+        }
+        let var = match self.module.data[base] {
+            Expr::Var(var) => &self.module.data[var],
+            _ => return,
+        };
+        match self.scopes.lookup_expr(base, var) {
+            Some(Denotation::Local(binding)) => {
+                if let Pat::Var { is_mut: false, .. } = self.module.data[binding] {
+                    self.result
+                        .diagnostics
+                        .push(Diagnostic::AssignToImmutable { lhs, binding });
+                }
+            }
+            // only locals have a place to assign to: consts are evaluated at
+            // compile time, and fns are not stored anywhere
+            Some(_) => self.result.diagnostics.push(Diagnostic::NotLValue { lhs }),
+            // already reported as unbound
+            None => {}
+        }
+    }
+
+    fn infer_binop_expr(&mut self, expr: ExprId, op: Binop, lhs: ExprId, rhs: ExprId) -> Type {
+        if let Binop::Assign | Binop::CompoundAssign(_) = op {
+            if self.is_lvalue(lhs) {
+                self.check_lvalue_is_mut(lhs);
+            } else {
                 self.result.diagnostics.push(Diagnostic::NotLValue { lhs });
             }
         }
 
         let lhs_expectation = op.lhs_expectation();
         let lhs_type = self.infer_expr(&lhs_expectation, lhs);
-        let rhs_expectation = op.rhs_expectation(&lhs_type);
+        if let Some(fn_type) = self.lookup_operator(&lhs_type, op.operator_trait()) {
+            return self.infer_overloaded_binop(expr, &fn_type, op, lhs, &lhs_type, rhs);
+        }
+        let rhs_expectation = match op {
+            Binop::Cmp(op) if !self.is_comparable(&lhs_type, op) => Type::Unknown,
+            Binop::Cmp(op) => {
+                // the operands may still turn out to be closures
+                if lhs_type.any(&|ty| matches!(ty, Type::Infer(InferType::Var(_)))) {
+                    self.unresolved_cmps.push((op, lhs_type.clone()));
+                }
+                lhs_type.clone()
+            }
+            // shifts require both sides to have the same type
+            Binop::Bit(op) | Binop::CompoundAssign(CompoundBinop::Bit(op)) => {
+                let allow_bool = !matches!(op, BitBinop::Shl | BitBinop::Shr);
+                self.expect_int_or_bool(lhs, &lhs_type, allow_bool)
+            }
+            Binop::Arithmetic(_) | Binop::CompoundAssign(CompoundBinop::Arithmetic(_)) => {
+                self.expect_numeric(&lhs_type)
+            }
+            _ => op.rhs_expectation(&lhs_type),
+        };
         if lhs_type != Type::Unknown && rhs_expectation == Type::Unknown {
             self.result.diagnostics.push(Diagnostic::CannotApplyBinop {
                 lhs_type,
@@ -677,11 +1664,150 @@ impl Ctx {
         op.return_type(&rhs_type)
     }
 
-    fn infer_loop_expr(&mut self, expected: &Type, expr: ExprId) -> Type {
-        self.with_loop_type(Type::NEVER, |this| {
-            this.infer_expr(expected, expr);
-            this.loop_type.clone().unwrap()
-        })
+    /// `lhs op rhs` where `op` is overloaded by a fn of type `fn_type`, which
+    /// is called with `lhs` and `rhs` as its arguments. Comparisons must
+    /// return `Bool`, and compound assignments must return the type of `lhs`.
+    fn infer_overloaded_binop(
+        &mut self,
+        expr: ExprId,
+        fn_type: &FnType,
+        op: Binop,
+        lhs: ExprId,
+        lhs_type: &Type,
+        rhs: ExprId,
+    ) -> Type {
+        match fn_type.params.as_slice() {
+            [lhs_param, rhs_param] => {
+                self.try_to_unify(Left(lhs), lhs_param, lhs_type);
+                self.infer_expr(rhs_param, rhs);
+            }
+            params => {
+                self.result.diagnostics.push(Diagnostic::ArgCountMismatch {
+                    expr,
+                    ty: fn_type.clone().into(),
+                    expected: params.len(),
+                    got: 2,
+                    fix_its: Vec::new(),
+                });
+                self.infer_expr(&Type::Unknown, rhs);
+            }
+        }
+        match op {
+            Binop::Cmp(_) => {
+                self.try_to_unify(Left(expr), &Type::BOOL, &fn_type.ret);
+                Type::BOOL
+            }
+            Binop::CompoundAssign(_) => {
+                self.try_to_unify(Left(expr), lhs_type, &fn_type.ret);
+                Type::UNIT
+            }
+            _ => fn_type.ret.clone(),
+        }
+    }
+
+    /// Whether `op` can compare two values of type `ty`. Equality is
+    /// structural, so it is defined for tuples, structs and enums whose fields
+    /// can be compared, but not for closures or recursive types, which must
+    /// implement `Eq` instead. Ordering is only defined for scalars and
+    /// tuples, which are compared lexicographically. A struct or enum that
+    /// implements `Eq` or `Ord` is compared with its impl, and so is a type
+    /// parameter bounded by the trait, also as a field of another type.
+    fn is_comparable(&self, ty: &Type, op: CmpBinop) -> bool {
+        self.is_comparable_inner(&mut Vec::new(), ty, op)
+    }
+
+    fn is_comparable_inner(&self, seen: &mut Vec<Ctor>, ty: &Type, op: CmpBinop) -> bool {
+        let (ctor, params) = match ty {
+            Type::App { ctor, params } => (ctor, params),
+            Type::Unknown | Type::Infer(_) => return true,
+        };
+        let is_ordering = !matches!(op, CmpBinop::Eq | CmpBinop::NotEq);
+        let operator_trait = if is_ordering {
+            Builtin::Ord
+        } else {
+            Builtin::Eq
+        };
+        let denotation = match ctor {
+            Ctor::Struct(id) => Some(Denotation::Struct(*id)),
+            Ctor::Enum(id) => Some(Denotation::Enum(*id)),
+            _ => None,
+        };
+        let impl_fn = denotation.and_then(|it| self.scopes.lookup_operator(it, operator_trait));
+        if impl_fn.is_some() {
+            return true;
+        }
+        let fields = match ctor {
+            Ctor::Fn => return false,
+            Ctor::Param(fn_id, idx) => {
+                let bounds = &self.bounds[*fn_id][*idx as usize];
+                return bounds.contains(&TraitId::Builtin(operator_trait));
+            }
+            Ctor::Tuple => {
+                return params
+                    .iter()
+                    .all(|ty| self.is_comparable_inner(seen, ty, op))
+            }
+            Ctor::Struct(_) | Ctor::Enum(_) if is_ordering => return false,
+            Ctor::Struct(_) | Ctor::Enum(_) if seen.contains(ctor) => return false,
+            Ctor::Struct(id) => self.module.data[*id].fields.clone(),
+            Ctor::Enum(id) => (self.module.data[*id].variants.iter())
+                .flat_map(|variant| variant.fields.iter().copied())
+                .collect(),
+            _ => return true,
+        };
+        seen.push(*ctor);
+        let result = fields.iter().all(|field| {
+            let ty = self.result.type_of_type[field.ty].clone();
+            self.is_comparable_inner(seen, &ty, op)
+        });
+        seen.pop();
+        result
+    }
+
+    fn infer_cast_expr(&mut self, id: ExprId, expr: ExprId, ty: TypeId) -> Type {
+        let mut from = self.infer_expr(&Type::Unknown, expr);
+        let to = self.resolve_type(ty);
+        // only `U8` can be cast to `Char`, so an int literal cast to one is a `U8`
+        if to == Type::CHAR && matches!(from, Type::Infer(InferType::Int(_))) {
+            from = self.try_to_unify_and_propagate_as_far_as_possible(Left(expr), &Type::U8, &from);
+        }
+        if !from.is_castable_to(&to) {
+            self.result.diagnostics.push(Diagnostic::InvalidCast {
+                expr: id,
+                from,
+                to: to.clone(),
+            });
+        }
+        to
+    }
+
+    fn infer_loop_expr(&mut self, id: ExprId, expected: &Type, body: ExprId) -> Type {
+        self.loops.push((id, Type::NEVER));
+        self.infer_expr(expected, body);
+        let (_, loop_type) = self.loops.pop().unwrap();
+        loop_type
+    }
+
+    /// Whether `expr` is the start or end of a desugared `for` loop's range,
+    /// see `lower_for_expr`.
+    fn is_for_range_bound(&self, expr: ExprId) -> bool {
+        (self.module.data.for_ranges.iter()).any(|&(start, end)| expr == start || expr == end)
+    }
+
+    /// The start or end of a `for` loop's range, which must be an integer so
+    /// that the loop counter can be compared with the end and incremented.
+    /// `pat` binds an integer even if `expr` is not one, so that the
+    /// desugared loop does not report further errors.
+    fn infer_for_range_bound(&mut self, pat: PatId, expr: ExprId) {
+        let ty = self.infer_expr(&Type::Unknown, expr);
+        let int = self.new_int_var();
+        if self.coerce(&ty, &int).is_err() {
+            self.result
+                .diagnostics
+                .push(Diagnostic::ForRangeNotInt { expr, ty });
+        }
+        let int = self.propagate_type_as_far_as_possible(&int);
+        self.infer_pat(&int, pat);
     }
 
     fn infer_return_expr(&mut self, parent_expr: ExprId, expr: Option<ExprId>) -> Type {
@@ -703,37 +1829,43 @@ impl Ctx {
         Type::NEVER
     }
 
-    fn infer_break_expr(&mut self, parent_expr: ExprId, expr: Option<ExprId>) -> Type {
+    fn infer_break_expr(
+        &mut self,
+        parent_expr: ExprId,
+        label: &Option<Label>,
+        expr: Option<ExprId>,
+    ) -> Type {
         let result_type = expr.map_or(Type::UNIT, |expr| self.infer_expr(&Type::Unknown, expr));
-        match self.loop_type.as_mut() {
-            None => {
-                self.result
-                    .diagnostics
-                    .push(Diagnostic::BreakNotInLoop(expr.unwrap_or(parent_expr)));
+        match self.target_loop(parent_expr, expr.unwrap_or(parent_expr), label) {
+            None => {}
+            Some(idx) if self.loops[idx].1 == Type::NEVER => self.loops[idx].1 = result_type,
+            // every `break` of a loop must agree with the first one
+            Some(idx) => {
+                let loop_type = self.loops[idx].1.clone();
+                self.try_to_unify(Left(expr.unwrap_or(parent_expr)), &loop_type, &result_type);
             }
-            Some(loop_type) => *loop_type = result_type,
         }
         Type::NEVER
     }
 
-    fn infer_continue_expr(&mut self, parent_expr: ExprId) -> Type {
-        match self.loop_type.clone() {
-            None => {
-                self.result
-                    .diagnostics
-                    .push(Diagnostic::BreakNotInLoop(parent_expr));
-            }
-            Some(_) => {}
-        }
+    fn infer_continue_expr(&mut self, parent_expr: ExprId, label: &Option<Label>) -> Type {
+        self.target_loop(parent_expr, parent_expr, label);
         Type::NEVER
     }
 
     fn infer_block_expr(&mut self, expected: &Type, stmts: &[Stmt], expr: Option<ExprId>) -> Type {
         for stmt in stmts {
             match stmt {
-                Stmt::Expr(expr) => self.infer_expr(&Type::Unknown, *expr),
-                Stmt::Let { pat, ty, expr } => self.infer_binding(*pat, *ty, Some(*expr)),
-            };
+                Stmt::Expr(expr) => {
+                    self.infer_expr(&Type::Unknown, *expr);
+                }
+                Stmt::Let { pat, expr, .. } if self.is_for_range_bound(*expr) => {
+                    self.infer_for_range_bound(*pat, *expr);
+                }
+                Stmt::Let { pat, ty, expr } => {
+                    self.infer_binding(*pat, *ty, Some(*expr));
+                }
+            }
         }
 
         match expr {
@@ -743,13 +1875,41 @@ impl Ctx {
     }
 }
 
-impl Lit {
-    const fn ty(self) -> Type {
+impl Type {
+    const fn is_numeric(&self) -> bool {
         match self {
-            Self::Bool(_) => Type::BOOL,
-            Self::Int(_) => Type::INT,
-            Self::Float(_) => Type::FLOAT,
-            Self::Char(_) => Type::CHAR,
+            Self::App { ctor, .. } => ctor.is_int() || ctor.is_float(),
+            Self::Infer(InferType::Int(_) | InferType::Float(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether `self` is one of the builtin types that have built-in impls,
+    /// or a numeric literal's type that will turn out to be one.
+    fn is_scalar(&self) -> bool {
+        self.is_numeric() || (self != &Self::NEVER && Builtin::of_type(self).is_some())
+    }
+
+    const fn is_int(&self) -> bool {
+        match self {
+            Self::App { ctor, .. } => ctor.is_int(),
+            Self::Infer(InferType::Int(_)) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether `self as to` is a valid conversion: any numeric type can be
+    /// cast to any other numeric type, and `Bool`/`Char` can be cast to
+    /// integers. Types that are not known yet are assumed to be castable,
+    /// since inference will report them anyway.
+    fn is_castable_to(&self, to: &Self) -> bool {
+        match (self, to) {
+            (Self::Unknown | Self::Infer(InferType::Var(_)), _)
+            | (_, Self::Unknown | Self::Infer(InferType::Var(_))) => true,
+            (from, to) if from.is_numeric() && to.is_numeric() => true,
+            (from, to) if (from == &Self::BOOL || from == &Self::CHAR) && to.is_int() => true,
+            (from, to) if from == &Self::U8 && to == &Self::CHAR => true,
+            (from, to) => from == to,
         }
     }
 }
@@ -757,14 +1917,14 @@ impl Lit {
 impl Unop {
     const fn lhs_expectation(self) -> Type {
         match self {
-            Self::Add | Self::Sub => Type::Unknown,
+            Self::Add | Self::Sub | Self::BitNot => Type::Unknown,
             Self::Not => Type::BOOL,
         }
     }
 
     fn return_type(self, lhs_type: &Type) -> Type {
         match self {
-            Self::Add | Self::Sub => lhs_type.clone(),
+            Self::Add | Self::Sub | Self::BitNot => lhs_type.clone(),
             Self::Not => Type::BOOL,
         }
     }
@@ -773,23 +1933,30 @@ impl Binop {
     const fn lhs_expectation(self) -> Type {
         match self {
             Self::Lazy(LazyBinop::And | LazyBinop::Or) => Type::BOOL,
-            Self::Arithmetic(_) | Self::Cmp(_) | Self::Assign => Type::Unknown,
+            Self::Arithmetic(_)
+            | Self::Bit(_)
+            | Self::Cmp(_)
+            | Self::Assign
+            | Self::CompoundAssign(_) => Type::Unknown,
         }
     }
 
+    /// Arithmetic and bitwise operators are checked by `Ctx::expect_numeric`
+    /// and `Ctx::expect_int_or_bool` instead.
     fn rhs_expectation(self, lhs_type: &Type) -> Type {
         match self {
             Self::Lazy(LazyBinop::And | LazyBinop::Or) => Type::BOOL,
-            Self::Arithmetic(_) | Self::Cmp(_) | Self::Assign => lhs_type.clone(),
+            Self::Arithmetic(_) | Self::Bit(_) | Self::Cmp(_) | Self::Assign => lhs_type.clone(),
+            Self::CompoundAssign(op) => Self::from(op).rhs_expectation(lhs_type),
         }
     }
 
     fn return_type(self, rhs_type: &Type) -> Type {
         match self {
             Self::Lazy(LazyBinop::And | LazyBinop::Or) => Type::BOOL,
-            Self::Arithmetic(_) => rhs_type.clone(),
+            Self::Arithmetic(_) | Self::Bit(_) => rhs_type.clone(),
             Self::Cmp(_) => Type::BOOL,
-            Self::Assign => Type::UNIT,
+            Self::Assign | Self::CompoundAssign(_) => Type::UNIT,
         }
     }
 }
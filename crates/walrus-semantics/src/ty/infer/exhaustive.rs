@@ -0,0 +1,181 @@
+//! Checking that the arms of a `match` cover every value, and that the
+//! pattern of a binding cannot fail. This is the usefulness algorithm from
+//! "Warnings for pattern matching" (Maranget, 2007), specialized to asking
+//! whether any value is left unmatched.
+
+use super::*;
+
+/// A pattern reduced to the values it matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Pattern {
+    Wild,
+    /// The only constructor of a tuple, or the variant of an enum with the
+    /// given index. `fields` has one pattern per field of the constructor.
+    Ctor {
+        ctor: usize,
+        fields: Vec<Self>,
+    },
+}
+
+type Row = Vec<Pattern>;
+
+impl Ctx {
+    pub(super) fn check_match_is_exhaustive(
+        &mut self,
+        expr: ExprId,
+        test_ty: &Type,
+        arms: &[MatchArm],
+    ) {
+        // the type of `test` has already been reported
+        if test_ty == &Type::Unknown {
+            return;
+        }
+        let rows: Vec<Row> = arms.iter().map(|arm| vec![self.pattern(arm.pat)]).collect();
+        if !self.has_unmatched_values(&rows, &[test_ty.clone()]) {
+            return;
+        }
+
+        let missing_variants = match test_ty.ctor() {
+            Some(Ctor::Enum(id)) => {
+                let ctors = self.ctors(test_ty).unwrap_or_default();
+                (self.module.data[*id].variants.iter())
+                    .zip(ctors)
+                    .enumerate()
+                    .filter(|(ctor, (_, fields))| {
+                        self.has_unmatched_values(&specialize(&rows, *ctor, fields.len()), fields)
+                    })
+                    .map(|(_, (variant, _))| variant.name)
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        self.result
+            .diagnostics
+            .push(Diagnostic::NonExhaustiveMatch {
+                expr,
+                missing_variants,
+            });
+    }
+
+    pub(super) fn is_irrefutable(&self, pat: PatId, ty: &Type) -> bool {
+        !self.has_unmatched_values(&[vec![self.pattern(pat)]], &[ty.clone()])
+    }
+
+    fn pattern(&self, id: PatId) -> Pattern {
+        match &self.module.data[id] {
+            Pat::Var { .. } | Pat::Ignore => Pattern::Wild,
+            Pat::Tuple(pats) => Pattern::Ctor {
+                ctor: 0,
+                fields: pats.iter().map(|pat| self.pattern(*pat)).collect(),
+            },
+            Pat::Enum {
+                variant, fields, ..
+            } => {
+                // a bad enum pattern has already been reported, so it is
+                // treated like `_` to avoid cascading diagnostics
+                let enum_def = match self.result.type_of_pat[id].ctor() {
+                    Some(Ctor::Enum(id)) => &self.module.data[*id],
+                    _ => return Pattern::Wild,
+                };
+                let name = &self.module.data[*variant];
+                let ctor = enum_def
+                    .variants
+                    .iter()
+                    .position(|variant| &self.module.data[variant.name] == name);
+                let ctor = match ctor {
+                    Some(ctor) => ctor,
+                    None => return Pattern::Wild,
+                };
+                // fields that are left out match anything
+                let fields = enum_def.variants[ctor]
+                    .fields
+                    .iter()
+                    .map(|field_def| {
+                        let name = &self.module.data[field_def.name];
+                        fields
+                            .iter()
+                            .find(|field| &self.module.data[field.name] == name)
+                            .map_or(Pattern::Wild, |field| self.pattern(field.pat))
+                    })
+                    .collect();
+                Pattern::Ctor { ctor, fields }
+            }
+        }
+    }
+
+    /// The constructors of `ty`, as the types of their fields, or `None` if
+    /// its values cannot be listed by constructor (eg `Int`), so that only
+    /// `_` matches all of them.
+    fn ctors(&self, ty: &Type) -> Option<Vec<Vec<Type>>> {
+        match ty {
+            Type::App {
+                ctor: Ctor::Tuple,
+                params,
+            } => Some(vec![params.clone()]),
+            Type::App {
+                ctor: Ctor::Enum(id),
+                ..
+            } => Some(
+                (self.module.data[*id].variants.iter())
+                    .map(|variant| {
+                        (variant.fields.iter())
+                            .map(|field| self.result.type_of_type[field.ty].clone())
+                            .collect()
+                    })
+                    .collect(),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Whether some values of the types `tys` match none of the `rows`.
+    fn has_unmatched_values(&self, rows: &[Row], tys: &[Type]) -> bool {
+        let (ty, tys) = match tys.split_first() {
+            None => return rows.is_empty(),
+            Some(split) => split,
+        };
+
+        let ctors = self.ctors(ty).filter(|ctors| {
+            (0..ctors.len()).all(|ctor| {
+                rows.iter()
+                    .any(|row| matches!(&row[0], Pattern::Ctor { ctor: c, .. } if *c == ctor))
+            })
+        });
+        match ctors {
+            // every constructor is matched by some row, so each one must be
+            // checked against the rows that match it
+            Some(ctors) => ctors.iter().enumerate().any(|(ctor, fields)| {
+                let rows = specialize(rows, ctor, fields.len());
+                let tys = fields.iter().chain(tys).cloned().collect::<Vec<_>>();
+                self.has_unmatched_values(&rows, &tys)
+            }),
+            // any value built with a missing constructor can only be matched
+            // by a row starting with `_`
+            None => {
+                let rows = (rows.iter())
+                    .filter(|row| row[0] == Pattern::Wild)
+                    .map(|row| row[1..].to_vec())
+                    .collect::<Vec<_>>();
+                self.has_unmatched_values(&rows, tys)
+            }
+        }
+    }
+}
+
+/// The rows that match values built with `ctor`, with the first column
+/// replaced by the fields of `ctor`.
+fn specialize(rows: &[Row], ctor: usize, arity: usize) -> Vec<Row> {
+    rows.iter()
+        .filter_map(|row| {
+            let fields = match &row[0] {
+                Pattern::Wild => vec![Pattern::Wild; arity],
+                Pattern::Ctor { ctor: c, fields } if *c == ctor => (fields.iter().cloned())
+                    .chain(std::iter::repeat(Pattern::Wild))
+                    .take(arity)
+                    .collect(),
+                Pattern::Ctor { .. } => return None,
+            };
+            Some(fields.into_iter().chain(row[1..].iter().cloned()).collect())
+        })
+        .collect()
+}
@@ -0,0 +1,60 @@
+//! Structs and enums are laid out inline, so a type that contains itself
+//! would have infinite size. Codegen breaks such cycles by storing the fields
+//! that close them on the heap.
+
+use super::{Ctor, InferenceResult, Type};
+use crate::hir::{ModuleData, StructField, TypeId};
+use std::collections::HashSet;
+
+/// The `ty` of every struct or enum field whose type contains the struct or
+/// enum it belongs to, other than behind a fn. Values of these fields are
+/// boxed.
+pub fn boxed_fields(module: &ModuleData, types: &InferenceResult) -> HashSet<TypeId> {
+    let struct_fields = (module.struct_defs.iter())
+        .flat_map(|(id, struct_def)| struct_def.fields.iter().map(move |f| (Ctor::Struct(id), f)));
+    let enum_fields = (module.enum_defs.iter()).flat_map(|(id, enum_def)| {
+        (enum_def.variants.iter())
+            .flat_map(move |variant| variant.fields.iter().map(move |f| (Ctor::Enum(id), f)))
+    });
+    struct_fields
+        .chain(enum_fields)
+        .filter(|(ctor, field)| contains(module, types, &types[field.ty], *ctor, &mut Vec::new()))
+        .map(|(_, field)| field.ty)
+        .collect()
+}
+
+/// Whether a value of type `ty` contains a value of `ctor` inline. `seen`
+/// holds the structs and enums being visited, so that cycles not involving
+/// `ctor` terminate.
+fn contains(
+    module: &ModuleData,
+    types: &InferenceResult,
+    ty: &Type,
+    ctor: Ctor,
+    seen: &mut Vec<Ctor>,
+) -> bool {
+    let (this, params) = match ty {
+        Type::App { ctor: this, params } => (*this, params),
+        Type::Unknown | Type::Infer(_) => return false,
+    };
+    let fields: Vec<&StructField> = match this {
+        _ if this == ctor => return true,
+        Ctor::Tuple => {
+            return params
+                .iter()
+                .any(|ty| contains(module, types, ty, ctor, seen))
+        }
+        Ctor::Struct(_) | Ctor::Enum(_) if seen.contains(&this) => return false,
+        Ctor::Struct(id) => module[id].fields.iter().collect(),
+        Ctor::Enum(id) => (module[id].variants.iter())
+            .flat_map(|variant| variant.fields.iter())
+            .collect(),
+        _ => return false,
+    };
+    seen.push(this);
+    let result = fields
+        .iter()
+        .any(|field| contains(module, types, &types[field.ty], ctor, seen));
+    seen.pop();
+    result
+}
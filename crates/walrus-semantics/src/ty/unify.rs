@@ -25,6 +25,15 @@ pub enum TypeVarValue {
     Unknown,
 }
 
+/// Why two types could not be unified.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum UnifyError {
+    /// The types have different constructors or arities.
+    Mismatch,
+    /// Binding a type variable would make it contain itself, eg `?T = fn(?T)`.
+    InfiniteType,
+}
+
 impl TypeVarValue {
     const fn as_known(&self) -> Option<&Type> {
         match self {
@@ -129,23 +138,43 @@ impl InferenceTable {
         })
     }
 
+    /// Replaces type variables at the root of `ty` by their known types. A
+    /// type variable may be known to be another (eg integer) type variable, so
+    /// this loops until the root is either a concrete type or unknown.
     fn propagate_type_shallow(&mut self, ty: &Type) -> Type {
+        let mut ty = ty.clone();
+        while let Type::Infer(tv) = ty {
+            let inner = tv.to_inner();
+            let value = self.var_unification_table.inlined_probe_value(inner);
+            match value.as_known() {
+                Some(known_ty) => ty = known_ty.clone(),
+                None => break,
+            }
+        }
+        ty
+    }
+
+    /// Returns true if the type variable `tv` appears anywhere inside `ty`.
+    fn occurs(&mut self, tv: TypeVarId, ty: &Type) -> bool {
         match ty {
-            Type::Infer(tv) => {
-                let inner = tv.to_inner();
-                let value = self.var_unification_table.inlined_probe_value(inner);
-                match value.as_known() {
-                    Some(known_ty) => known_ty.clone(),
-                    None => ty.clone(),
+            Type::App { params, .. } => params.iter().any(|ty| self.occurs(tv, ty)),
+            Type::Unknown => false,
+            Type::Infer(var) => {
+                let var = var.to_inner();
+                if self.var_unification_table.unioned(tv, var) {
+                    return true;
+                }
+                match self.var_unification_table.probe_value(var) {
+                    TypeVarValue::Known(ty) => self.occurs(tv, &ty),
+                    TypeVarValue::Unknown => false,
                 }
             }
-            _ => ty.clone(),
         }
     }
 
-    pub fn unify(&mut self, t1: &Type, t2: &Type) -> bool {
+    pub fn unify(&mut self, t1: &Type, t2: &Type) -> Result<(), UnifyError> {
         if t1 == t2 {
-            return true;
+            return Ok(());
         }
 
         let t1 = self.propagate_type_shallow(&t1.clone());
@@ -161,29 +190,53 @@ impl InferenceTable {
                     params: params2,
                 },
             ) if ctor1 == ctor2 => {
-                params1.len() == params2.len()
-                    && (params1.iter())
-                        .zip(params2.iter())
-                        .all(|(t1, t2)| self.unify(t1, t2))
+                if params1.len() != params2.len() {
+                    return Err(UnifyError::Mismatch);
+                }
+                (params1.iter())
+                    .zip(params2.iter())
+                    .try_for_each(|(t1, t2)| self.unify(t1, t2))
             }
-            (Type::Unknown, _) | (_, Type::Unknown) => true,
-            (Type::Infer(InferType::Var(tv1)), Type::Infer(InferType::Var(tv2))) => {
+            (Type::Unknown, _) | (_, Type::Unknown) => Ok(()),
+            (Type::Infer(InferType::Var(tv1)), Type::Infer(InferType::Var(tv2)))
+            | (Type::Infer(InferType::Int(tv1)), Type::Infer(InferType::Int(tv2)))
+            | (Type::Infer(InferType::Float(tv1)), Type::Infer(InferType::Float(tv2))) => {
                 self.var_unification_table.union(tv1, tv2);
-                true
+                Ok(())
             }
 
             (Type::Infer(InferType::Var(tv)), other) | (other, Type::Infer(InferType::Var(tv))) => {
+                if self.occurs(tv, &other) {
+                    return Err(UnifyError::InfiniteType);
+                }
+                self.var_unification_table
+                    .union_value(tv, TypeVarValue::Known(other));
+                Ok(())
+            }
+
+            (Type::Infer(InferType::Int(tv)), other) | (other, Type::Infer(InferType::Int(tv)))
+                if other.ctor().map_or(false, |ctor| ctor.is_int()) =>
+            {
+                self.var_unification_table
+                    .union_value(tv, TypeVarValue::Known(other));
+                Ok(())
+            }
+
+            (Type::Infer(InferType::Float(tv)), other)
+            | (other, Type::Infer(InferType::Float(tv)))
+                if other.ctor().map_or(false, |ctor| ctor.is_float()) =>
+            {
                 self.var_unification_table
                     .union_value(tv, TypeVarValue::Known(other));
-                true
+                Ok(())
             }
 
             // below Type::Infer so that
             // unify(TypeVar, Never) => Never, instead of
             // unify(TypeVar, Never) => TypeVar
-            (ty, _) | (_, ty) if ty == Type::NEVER => true,
+            (ty, _) | (_, ty) if ty == Type::NEVER => Ok(()),
 
-            _ => false,
+            _ => Err(UnifyError::Mismatch),
         }
     }
 }
@@ -89,9 +89,27 @@ pub struct Delimited<T, Open, Close> {
     pub close: Close,
 }
 
+macro_rules! delimited_span {
+    ($open:ident, $close:ident) => {
+        impl<T> Delimited<T, $open, $close> {
+            pub fn span(&self) -> text_size::TextRange { self.open.span.cover(self.close.span) }
+        }
+    };
+}
+delimited_span!(LParen, RParen);
+delimited_span!(LCurly, RCurly);
+delimited_span!(LSquare, RSquare);
+delimited_span!(Less, Greater);
+
 pub type Paren<T> = Delimited<T, LParen, RParen>;
 pub type Curly<T> = Delimited<T, LCurly, RCurly>;
+pub type Square<T> = Delimited<T, LSquare, RSquare>;
+pub type Angle<T> = Delimited<T, Less, Greater>;
 pub type Tuple<T> = Paren<Punctuated0<T, Comma>>;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Var(pub Ident);
+
+impl Var {
+    pub const fn span(&self) -> text_size::TextRange { self.0.span }
+}
@@ -3,6 +3,8 @@ use super::*;
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SourceFile {
     pub decls: Vec<Decl>,
+    /// The malformed tokens that were skipped before parsing.
+    pub lex_errors: Vec<walrus_lexer::LexError>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -10,17 +12,54 @@ pub enum Decl {
     Fn(FnDef),
     Struct(StructDef),
     Enum(EnumDef),
+    Impl(ImplDef),
+    Trait(TraitDef),
+    TypeAlias(TypeAliasDef),
+    Const(ConstDef),
+    ExternFn(ExternFnDef),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FnDef {
+    pub attrs: Vec<Attr>,
     pub kw_fn: KwFn,
     pub name: Var,
+    pub generics: Option<GenericParams>,
     pub params: ParamList,
     pub ret: Option<RetType>,
     pub expr: Expr,
 }
 
+/// `<T: Trait + Trait, U>`, the type parameters of a generic fn.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GenericParams(pub Angle<Punctuated0<GenericParam, Comma>>);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GenericParam {
+    pub name: Var,
+    pub bounds: Option<Bounds>,
+}
+
+/// The `: Trait + Trait` after a type parameter.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Bounds {
+    pub colon: Colon,
+    pub traits: Punctuated1NoTrail<Var, Plus>,
+}
+
+/// `#[name(arg, ...)]`, attached to the declaration that follows it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Attr {
+    pub pound: Pound,
+    pub body: Square<AttrBody>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AttrBody {
+    pub name: Var,
+    pub args: Tuple<Var>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct StructDef {
     pub kw_struct: KwStruct,
@@ -35,6 +74,68 @@ pub struct EnumDef {
     pub variants: Curly<Punctuated0<EnumVariant, Comma>>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ImplDef {
+    pub kw_impl: KwImpl,
+    pub trait_ref: Option<TraitRef>,
+    pub ty: Type,
+    pub fns: Curly<Vec<FnDef>>,
+}
+
+/// The `Trait for` in `impl Trait for Type`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TraitRef {
+    pub name: Var,
+    pub kw_for: KwFor,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TraitDef {
+    pub kw_trait: KwTrait,
+    pub name: Var,
+    pub fns: Curly<Vec<FnSig>>,
+}
+
+/// `type Name = Type;`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TypeAliasDef {
+    pub kw_type: KwType,
+    pub name: Var,
+    pub eq: Eq,
+    pub ty: Type,
+    pub semicolon: Semicolon,
+}
+
+/// `const NAME: Type = expr;`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ConstDef {
+    pub kw_const: KwConst,
+    pub name: Var,
+    pub ascription: Ascription,
+    pub eq: Eq,
+    pub expr: Expr,
+    pub semicolon: Semicolon,
+}
+
+/// `extern "C" fn name(params) -> ret;`, a fn defined outside the program,
+/// such as in libc. The ABI defaults to `"C"` if it is omitted.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ExternFnDef {
+    pub kw_extern: KwExtern,
+    pub abi: Option<Str>,
+    pub sig: FnSig,
+}
+
+/// A fn without a body, declared in a `trait` or as an `extern` fn.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FnSig {
+    pub kw_fn: KwFn,
+    pub name: Var,
+    pub params: ParamList,
+    pub ret: Option<RetType>,
+    pub semicolon: Semicolon,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct StructField {
     pub name: Var,
@@ -9,23 +9,104 @@ pub enum Expr {
     Lambda(LambdaExpr),
     Unary(UnaryExpr),
     Binary(BinaryExpr),
+    Cast(CastExpr),
     Call(CallExpr),
+    MethodCall(MethodCallExpr),
     Field(FieldExpr),
     Struct(StructExpr),
     Enum(EnumExpr),
+    Path(PathExpr),
     If(IfExpr),
+    Match(MatchExpr),
     Return(ReturnExpr),
     Break(BreakExpr),
     Continue(ContinueExpr),
     Loop(LoopExpr),
+    While(WhileExpr),
+    For(ForExpr),
     Block(Block),
 }
 
+impl Expr {
+    /// The span from the first token of the expression to its last.
+    pub fn span(&self) -> text_size::TextRange {
+        match self {
+            Self::Lit(lit) => lit.span(),
+            Self::Var(var) => var.span(),
+            Self::Paren(expr) => expr.span(),
+            Self::Tuple(exprs) => exprs.span(),
+            Self::Lambda(expr) => expr.params.0.span().cover(expr.expr.span()),
+            Self::Unary(expr) => expr.op.span().cover(expr.expr.span()),
+            Self::Binary(expr) => expr.lhs.span().cover(expr.rhs.span()),
+            Self::Cast(expr) => expr.expr.span().cover(expr.ty.span()),
+            Self::Call(expr) => expr.func.span().cover(expr.args.0.span()),
+            Self::MethodCall(expr) => expr.receiver.span().cover(expr.args.0.span()),
+            Self::Field(expr) => {
+                let field = match &expr.field {
+                    Field::Tuple(idx) => idx.span,
+                    Field::Named(var) => var.span(),
+                };
+                expr.base.span().cover(field)
+            }
+            Self::Struct(expr) => expr.name.span().cover(expr.fields.span()),
+            Self::Enum(expr) => expr.name.span().cover(expr.fields.span()),
+            Self::Path(expr) => expr.ty.span().cover(expr.name.span()),
+            Self::If(expr) => {
+                let last = match &expr.else_branch {
+                    None => &expr.then_branch,
+                    Some(ElseExpr::ElseBlock { block, .. }) => block,
+                    Some(ElseExpr::ElseIf { if_expr, .. }) => if_expr,
+                };
+                expr.kw_if.span.cover(last.span())
+            }
+            Self::Match(expr) => expr.kw_match.span.cover(expr.arms.span()),
+            Self::Return(expr) => {
+                let span = expr.kw_return.span;
+                (expr.expr.as_ref()).map_or(span, |value| span.cover(value.span()))
+            }
+            Self::Break(expr) => {
+                let span = expr.kw_break.span;
+                let span = (expr.label.as_ref()).map_or(span, |label| span.cover(label.span));
+                (expr.expr.as_ref()).map_or(span, |value| span.cover(value.span()))
+            }
+            Self::Continue(expr) => {
+                let span = expr.kw_continue.span;
+                (expr.label.as_ref()).map_or(span, |label| span.cover(label.span))
+            }
+            Self::Loop(expr) => {
+                label_span(expr.label.as_ref(), expr.kw_loop.span).cover(expr.expr.span())
+            }
+            Self::While(expr) => {
+                label_span(expr.label.as_ref(), expr.kw_while.span).cover(expr.expr.span())
+            }
+            Self::For(expr) => {
+                label_span(expr.label.as_ref(), expr.kw_for.span).cover(expr.expr.span())
+            }
+            Self::Block(block) => block.lcurly.span.cover(block.rcurly.span),
+        }
+    }
+}
+
+/// The span of a loop's label if it has one, otherwise the span of its
+/// keyword.
+fn label_span(label: Option<&LabelDecl>, keyword: text_size::TextRange) -> text_size::TextRange {
+    label.map_or(keyword, |label| label.label.span)
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct CallExpr {
     pub func: Box<Expr>,
     pub args: ArgList,
 }
+/// `receiver.method(args)`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MethodCallExpr {
+    pub receiver: Box<Expr>,
+    pub dot: Dot,
+    pub method: Var,
+    pub args: ArgList,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ArgList(pub Paren<Punctuated0<Expr, Comma>>);
 
@@ -42,6 +123,13 @@ pub struct UnaryExpr {
     pub expr: Box<Expr>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CastExpr {
+    pub expr: Box<Expr>,
+    pub kw_as: KwAs,
+    pub ty: Type,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Binop {
     Or(OrOr),
@@ -50,7 +138,23 @@ pub enum Binop {
     Sub(Minus),
     Mul(Star),
     Div(Slash),
+    Rem(Percent),
+    BitAnd(And),
+    BitOr(Or),
+    BitXor(Caret),
+    Shl(LessLess),
+    Shr(GreaterGreater),
     Assign(Eq),
+    AddAssign(PlusEq),
+    SubAssign(MinusEq),
+    MulAssign(StarEq),
+    DivAssign(SlashEq),
+    RemAssign(PercentEq),
+    BitAndAssign(AndEq),
+    BitOrAssign(OrEq),
+    BitXorAssign(CaretEq),
+    ShlAssign(LessLessEq),
+    ShrAssign(GreaterGreaterEq),
     Eq(EqEq),
     NotEq(BangEq),
     Less(Less),
@@ -59,11 +163,59 @@ pub enum Binop {
     GreaterEq(GreaterEq),
 }
 
+impl Binop {
+    pub const fn span(&self) -> text_size::TextRange {
+        match self {
+            Self::Or(OrOr { span })
+            | Self::And(AndAnd { span })
+            | Self::Add(Plus { span })
+            | Self::Sub(Minus { span })
+            | Self::Mul(Star { span })
+            | Self::Div(Slash { span })
+            | Self::Rem(Percent { span })
+            | Self::BitAnd(And { span })
+            | Self::BitOr(Or { span })
+            | Self::BitXor(Caret { span })
+            | Self::Shl(LessLess { span })
+            | Self::Shr(GreaterGreater { span })
+            | Self::Assign(Eq { span })
+            | Self::AddAssign(PlusEq { span })
+            | Self::SubAssign(MinusEq { span })
+            | Self::MulAssign(StarEq { span })
+            | Self::DivAssign(SlashEq { span })
+            | Self::RemAssign(PercentEq { span })
+            | Self::BitAndAssign(AndEq { span })
+            | Self::BitOrAssign(OrEq { span })
+            | Self::BitXorAssign(CaretEq { span })
+            | Self::ShlAssign(LessLessEq { span })
+            | Self::ShrAssign(GreaterGreaterEq { span })
+            | Self::Eq(EqEq { span })
+            | Self::NotEq(BangEq { span })
+            | Self::Less(Less { span })
+            | Self::LessEq(LessEq { span })
+            | Self::Greater(Greater { span })
+            | Self::GreaterEq(GreaterEq { span }) => *span,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum Unop {
     Not(Bang),
     Add(Plus),
     Sub(Minus),
+    BitNot(Tilde),
+}
+
+impl Unop {
+    pub const fn span(&self) -> text_size::TextRange {
+        match self {
+            Self::Not(Bang { span })
+            | Self::Add(Plus { span })
+            | Self::Sub(Minus { span })
+            | Self::BitNot(Tilde { span }) => *span,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -92,6 +244,14 @@ pub struct EnumExpr {
     pub fields: Curly<Punctuated0<FieldInit, Comma>>,
 }
 
+/// `Type::name`, naming a function from an `impl` block.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PathExpr {
+    pub ty: Var,
+    pub colon_colon: ColonColon,
+    pub name: Var,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FieldInit {
     pub name: Var,
@@ -113,6 +273,20 @@ pub enum ElseExpr {
     ElseIf { kw_else: KwElse, if_expr: Box<Expr> },
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MatchExpr {
+    pub kw_match: KwMatch,
+    pub test_expr: Box<Expr>,
+    pub arms: Curly<Punctuated0<MatchArm, Comma>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MatchArm {
+    pub pat: Pat,
+    pub fat_arrow: FatArrow,
+    pub expr: Expr,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Block {
     pub lcurly: LCurly,
@@ -130,12 +304,14 @@ pub struct ReturnExpr {
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BreakExpr {
     pub kw_break: KwBreak,
+    pub label: Option<Label>,
     pub expr: Option<Box<Expr>>,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ContinueExpr {
     pub kw_continue: KwContinue,
+    pub label: Option<Label>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -147,6 +323,34 @@ pub struct LambdaExpr {
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct LoopExpr {
+    pub label: Option<LabelDecl>,
     pub kw_loop: KwLoop,
     pub expr: Box<Expr>,
 }
+
+/// The `'label:` before a loop.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LabelDecl {
+    pub label: Label,
+    pub colon: Colon,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WhileExpr {
+    pub label: Option<LabelDecl>,
+    pub kw_while: KwWhile,
+    pub test_expr: Box<Expr>,
+    pub expr: Box<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ForExpr {
+    pub label: Option<LabelDecl>,
+    pub kw_for: KwFor,
+    pub pat: Pat,
+    pub kw_in: KwIn,
+    pub start: Box<Expr>,
+    pub dot_dot: DotDot,
+    pub end: Box<Expr>,
+    pub expr: Box<Expr>,
+}
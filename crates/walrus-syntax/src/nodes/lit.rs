@@ -8,6 +8,22 @@ pub enum Lit {
     Char(CharLit),
 }
 
+impl Lit {
+    pub const fn span(&self) -> text_size::TextRange {
+        match self {
+            Self::Bool(BoolLit::True(KwTrue { span }))
+            | Self::Bool(BoolLit::False(KwFalse { span }))
+            | Self::Int(IntLit::Dec(DecInt { span, .. }))
+            | Self::Int(IntLit::Bin(BinInt { span, .. }))
+            | Self::Int(IntLit::Hex(HexInt { span, .. }))
+            | Self::Float(FloatLit(Float { span, .. }))
+            | Self::Char(CharLit::Simple(SimpleChar { span, .. }))
+            | Self::Char(CharLit::Escaped(EscapedChar { span, .. }))
+            | Self::Char(CharLit::Unicode(UnicodeChar { span, .. })) => *span,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum BoolLit {
     True(KwTrue),
@@ -2,10 +2,27 @@ use super::*;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Pat {
-    Var(Var),
+    Var { kw_mut: Option<KwMut>, var: Var },
     Ignore(Underscore),
     Paren(Paren<Self>),
     Tuple(Tuple<Self>),
+    Enum(EnumPat),
+}
+
+/// `Foo::Bar {x: pat, y: pat}`. Fields that are left out are not matched.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EnumPat {
+    pub name: Var,
+    pub colon_colon: ColonColon,
+    pub variant: Var,
+    pub fields: Curly<Punctuated0<FieldPat, Comma>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FieldPat {
+    pub name: Var,
+    pub colon: Colon,
+    pub pat: Pat,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
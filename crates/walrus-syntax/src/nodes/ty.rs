@@ -12,6 +12,18 @@ pub enum Type {
     },
 }
 
+impl Type {
+    pub fn span(&self) -> text_size::TextRange {
+        match self {
+            Self::Var(var) => var.span(),
+            Self::Infer(underscore) => underscore.span,
+            Self::Paren(ty) => ty.span(),
+            Self::Tuple(tys) => tys.span(),
+            Self::Fn { args, ret } => args.span().cover(ret.ty.span()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct RetType {
     pub thin_arrow: ThinArrow,
@@ -46,21 +46,33 @@ token!(Whitespace);
 token!(LineComment);
 token!(BlockComment);
 
+token!(KwAs);
 token!(KwBreak);
+token!(KwConst);
 token!(KwContinue);
 token!(KwElse);
 token!(KwEnum);
+token!(KwExtern);
 token!(KwFalse);
 token!(KwFn);
+token!(KwFor);
 token!(KwIf);
+token!(KwImpl);
 token!(KwImport);
+token!(KwIn);
 token!(KwLet);
 token!(KwLoop);
+token!(KwMatch);
+token!(KwMut);
 token!(KwReturn);
 token!(KwTrue);
 token!(KwStruct);
+token!(KwTrait);
+token!(KwType);
+token!(KwWhile);
 
 token_with_text!(Ident);
+token_with_text!(Label);
 token_with_text!(DecInt);
 token_with_text!(BinInt);
 token_with_text!(HexInt);
@@ -68,13 +80,17 @@ token_with_text!(Float);
 token_with_text!(SimpleChar);
 token_with_text!(EscapedChar);
 token_with_text!(UnicodeChar);
+token_with_text!(Str);
 
 token!(LParen);
 token!(RParen);
 token!(LCurly);
 token!(RCurly);
+token!(LSquare);
+token!(RSquare);
 
 token!(Dot);
+token!(DotDot);
 token!(Comma);
 token!(Semicolon);
 token!(Colon);
@@ -82,6 +98,7 @@ token!(ColonColon);
 token!(ThinArrow);
 token!(FatArrow);
 token!(Underscore);
+token!(Pound);
 
 token!(Bang);
 token!(AndAnd);
@@ -91,6 +108,14 @@ token!(Plus);
 token!(Minus);
 token!(Star);
 token!(Slash);
+token!(Percent);
+
+token!(And);
+token!(Or);
+token!(Caret);
+token!(Tilde);
+token!(LessLess);
+token!(GreaterGreater);
 
 token!(Eq);
 token!(EqEq);
@@ -99,3 +124,14 @@ token!(Less);
 token!(LessEq);
 token!(Greater);
 token!(GreaterEq);
+
+token!(PlusEq);
+token!(MinusEq);
+token!(StarEq);
+token!(SlashEq);
+token!(PercentEq);
+token!(AndEq);
+token!(OrEq);
+token!(CaretEq);
+token!(LessLessEq);
+token!(GreaterGreaterEq);